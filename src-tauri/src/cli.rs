@@ -0,0 +1,170 @@
+use std::{
+    env,
+    path::PathBuf,
+    thread,
+    time::Duration,
+};
+
+use serde_json::Value;
+
+use super::soundmanager::{
+    control::RemoteSoundbite,
+    ipc::{self, IpcRequest, IpcResponse},
+    utils::IpcError,
+};
+
+//The first instance may still be starting when the second one launches
+const FORWARD_ATTEMPTS: u32 = 20;
+const FORWARD_RETRY_DELAY: Duration = Duration::from_millis(250);
+
+const USAGE: &str = "Usage: soundbar <command>
+
+Commands:
+  play <name>             Play a soundbite
+  stop [name]             Stop a soundbite, or every playing one
+  list                    List the soundbites, playing ones are marked with *
+  volume [name] [value]   Show or set the master volume, or a soundbite volume
+  import <file> [name]    Import an audio file as a soundbite
+  help                    Show this help";
+
+//Runs the subcommand in args against the running instance, None when
+// there's no subcommand and the app should start
+pub fn run(args: &[String]) -> Option<i32> {
+    let command = args.first()?.as_str();
+    if !matches!(command, "play" | "stop" | "list" | "volume" | "import" | "help" | "--help") {
+        return None;
+    }
+
+    attach_console();
+
+    let request = match parse(command, &args[1..]) {
+        Ok(Some(request)) => request,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return Some(0);
+        },
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            return Some(2);
+        },
+    };
+
+    match ipc::send_request(&request) {
+        Ok(IpcResponse::Ok { data }) => {
+            print_result(&request, data);
+            Some(0)
+        },
+        Ok(IpcResponse::Error { message }) => {
+            eprintln!("{}", message);
+            Some(1)
+        },
+        Err(err) => {
+            eprintln!("{}", err);
+            Some(1)
+        },
+    }
+}
+
+//Hands the launch arguments of a second instance to the running one,
+// returns the exit code
+pub fn forward(args: &[String]) -> i32 {
+    attach_console();
+
+    //Paths are made absolute, anything else is passed as is
+    let args: Vec<String> = args.iter()
+        .map(|arg| {
+            let path = absolute(arg);
+            if path.exists() {
+                path.display().to_string()
+            } else {
+                arg.clone()
+            }
+        })
+        .collect();
+    let request = IpcRequest::Forward { args };
+
+    for _ in 0..FORWARD_ATTEMPTS {
+        match ipc::send_request(&request) {
+            Ok(IpcResponse::Ok { .. }) => return 0,
+            Ok(IpcResponse::Error { message }) => {
+                eprintln!("{}", message);
+                return 1;
+            },
+            Err(IpcError::NotRunning) => thread::sleep(FORWARD_RETRY_DELAY),
+            Err(err) => {
+                eprintln!("Unable to reach the running soundbar, {}", err);
+                return 1;
+            },
+        }
+    }
+
+    eprintln!("soundbar is already running but not answering");
+    1
+}
+
+//None for help
+fn parse(command: &str, args: &[String]) -> Result<Option<IpcRequest>, String> {
+    let request = match (command, args) {
+        ("play", [name]) => IpcRequest::Play { name: name.clone() },
+        ("stop", []) => IpcRequest::Stop { name: None },
+        ("stop", [name]) => IpcRequest::Stop { name: Some(name.clone()) },
+        ("list", []) => IpcRequest::List,
+        ("volume", []) => IpcRequest::Volume { name: None, volume: None },
+        ("volume", [value]) => match value.parse::<f32>() {
+            Ok(volume) => IpcRequest::Volume { name: None, volume: Some(volume) },
+            Err(_) => IpcRequest::Volume { name: Some(value.clone()), volume: None },
+        },
+        ("volume", [name, value]) => match value.parse::<f32>() {
+            Ok(volume) => IpcRequest::Volume { name: Some(name.clone()), volume: Some(volume) },
+            Err(_) => return Err(format!("Invalid volume {}", value)),
+        },
+        ("import", [file, rest @ ..]) if rest.len() <= 1 => IpcRequest::Import {
+            path: absolute(file),
+            name: rest.first().cloned(),
+        },
+        ("help" | "--help", _) => return Ok(None),
+        _ => return Err(format!("Invalid arguments for {}", command)),
+    };
+
+    Ok(Some(request))
+}
+
+//The running instance has its own working directory
+fn absolute(file: &str) -> PathBuf {
+    let path = PathBuf::from(file);
+    match env::current_dir() {
+        Ok(dir) if path.is_relative() => dir.join(path),
+        _ => path,
+    }
+}
+
+fn print_result(request: &IpcRequest, data: Value) {
+    match request {
+        IpcRequest::List => {
+            let soundbites: Vec<RemoteSoundbite> = serde_json::from_value(data).unwrap_or_default();
+            for soundbite in soundbites {
+                let marker = if soundbite.playing { "*" } else { " " };
+                println!("{} {}", marker, soundbite.info.name);
+            }
+        },
+        IpcRequest::Volume { volume: None, .. } => match data.get("volume") {
+            Some(Value::Null) | None => println!("default"),
+            Some(volume) => println!("{}", volume),
+        },
+        IpcRequest::Import { .. } => {
+            if let Value::String(name) = data {
+                println!("Importing {}", name);
+            }
+        },
+        _ => {},
+    }
+}
+
+//Release builds have no console of their own on Windows, output goes to
+// the one the command was typed in
+fn attach_console() {
+    #[cfg(windows)]
+    unsafe {
+        winapi::um::wincon::AttachConsole(winapi::um::wincon::ATTACH_PARENT_PROCESS);
+    }
+}
@@ -1,11 +1,42 @@
+use std::{
+    path::PathBuf,
+    time::Duration,
+};
+
 use tauri::State;
 use anyhow::Result;
+use crossbeam::channel::RecvTimeoutError;
 use log::error;
 
 use super::soundmanager::{
+    self,
     soundstate::{SoundState, Message},
     soundbite::SoundbiteData,
-    key_task::KeyTaskCode,
+    key_task::{
+        KeyTaskCode,
+        KeyBinding,
+        BindingTarget,
+        TriggerMode,
+        CapturedChord,
+        ChordConflict,
+        DroppedBinding,
+    },
+    control,
+    export,
+    focus::{ActivationRules, FocusedWindow},
+    group::ChokeGroup,
+    key_hook,
+    pack::{self, PackBinding},
+    profile::{Profile, ProfileInfo},
+    queue::{Playlist, QueueInfo, Transition},
+    schedule::{Schedule, ScheduleRule},
+    variant::{Variant, VariantMode, VariantSet},
+    macros::{Macro, MacroStep},
+    search,
+    trigger::{self, TriggerCode, TriggerInfo, LearnedTrigger},
+    settings::{Settings, ImportLimits, SwallowEscape, ApiSettings, OscSettings},
+    api,
+    osc,
     utils::{
         SoundManagerError,
         NewSoundbiteError,
@@ -34,6 +65,64 @@ pub fn add_soundbite(
     Ok(soundbite_name)
 }
 
+#[tauri::command]
+pub fn import_soundbite(
+    path: String,
+    name: Option<String>,
+    state: State<'_, SoundState>
+) -> Result<String, SoundManagerError> {
+    control::import(&state, PathBuf::from(path), name)
+}
+
+#[tauri::command]
+pub fn import_folder(
+    path: String,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let folder = PathBuf::from(path);
+    if !folder.is_dir() {
+        return Err(SoundManagerError::NewSoundbiteError(
+            NewSoundbiteError::FileNotFound(folder.display().to_string())
+        ));
+    }
+
+    if let Err(err) = state.messages.send(Message::ImportFolder(folder.clone())) {
+        error!(
+            "Unable to send command to import folder {} [[{:?}]]",
+            folder.display(),
+            err
+        );
+        return Err(SoundManagerError::NewSoundbiteError(
+            NewSoundbiteError::UnableToSendSoundbite(folder.display().to_string())
+        ));
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_watch_folder(
+    path: Option<String>,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let folder = path.map(PathBuf::from);
+    if let Some(folder) = &folder {
+        if !folder.is_dir() {
+            return Err(SoundManagerError::NewSoundbiteError(
+                NewSoundbiteError::FileNotFound(folder.display().to_string())
+            ));
+        }
+    }
+
+    state.settings.lock().unwrap().watch_folder = folder.clone();
+    if let Err(err) = state.messages.send(Message::WatchFolder(folder)) {
+        error!("Unable to send command to watch folder [[{:?}]]", err);
+        return Err(SoundManagerError::UnableToSendMessage);
+    }
+
+    Ok(())
+}
+
 #[tauri::command]
 pub fn remove_soundbite(
     name: String,
@@ -43,17 +132,16 @@ pub fn remove_soundbite(
     match soundbites.iter().position(|s| s.data.name == name) {
         Some(index) => {
             let mut soundbites_keytasks = state.soundbites_keytasks.lock().unwrap();
-            let keys: Vec<KeyTaskCode> = soundbites_keytasks.iter().filter_map(
-                |(&key, &val)| if val == index { Some(key) } else { None }
-            ).collect();
-            keys.iter().for_each(
-                |keycode| {
-                    soundbites_keytasks.remove(keycode);
-                }
+            let mut trigger_bindings = state.trigger_bindings.lock().unwrap();
+            let mut profiles = state.profiles.lock().unwrap();
+            soundmanager::remove_soundbite(
+                &mut soundbites,
+                &mut soundbites_keytasks,
+                &mut trigger_bindings,
+                &mut profiles,
+                index
             );
 
-            soundbites.remove(index);
-
             Ok(())
         }
         None => Err(SoundManagerError::SoundbiteNotFound(name)),
@@ -61,166 +149,1745 @@ pub fn remove_soundbite(
 }
 
 #[tauri::command]
-pub fn play_soundbite(
+pub fn export_soundbite(
     name: String,
+    path: String,
+    baked: bool,
     state: State<'_, SoundState>
-) {
-    let soundbites = state.soundbites.lock().unwrap();
-    soundbites
+) -> Result<(), SoundManagerError> {
+    //Copied so playback isn't held up while writing the file
+    let data = state.soundbites.lock().unwrap()
         .iter()
         .find(|soundbite| soundbite.data.name == name)
-        .map(|soundbite| soundbite.play());
+        .map(|soundbite| soundbite.data.clone());
+    match data {
+        Some(data) => export::export_wav(&data, &PathBuf::from(path), baked),
+        None => Err(SoundManagerError::SoundbiteNotFound(name)),
+    }
 }
 
 #[tauri::command]
-pub fn stop_soundbite(
+pub fn export_soundbites(
+    names: Vec<String>,
+    folder: String,
+    baked: bool,
+    state: State<'_, SoundState>
+) -> Result<Vec<String>, SoundManagerError> {
+    let folder = PathBuf::from(folder);
+    if !folder.is_dir() {
+        return Err(SoundManagerError::UnableToExport(folder.display().to_string()));
+    }
+
+    //Copied so playback isn't held up while writing the files
+    let selected: Vec<SoundbiteData> = {
+        let soundbites = state.soundbites.lock().unwrap();
+        if let Some(name) = names.iter().find(
+            |name| !soundbites.iter().any(|soundbite| soundbite.data.name == **name)
+        ) {
+            return Err(SoundManagerError::SoundbiteNotFound(name.clone()));
+        }
+
+        soundbites.iter()
+            .filter(|s| names.contains(&s.data.name))
+            .map(|s| s.data.clone())
+            .collect()
+    };
+
+    let file_names = export::unique_file_names(
+        &selected.iter().map(|data| data.name.as_str()).collect::<Vec<_>>(),
+        "wav"
+    );
+    let mut exported = Vec::new();
+    for (data, file_name) in selected.iter().zip(file_names) {
+        let path = folder.join(file_name);
+        export::export_wav(data, &path, baked)?;
+        exported.push(path.display().to_string());
+    }
+
+    Ok(exported)
+}
+
+#[tauri::command(async)]
+pub fn export_pack(
+    path: String,
+    names: Option<Vec<String>>,
+    include_bindings: bool,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    //Copied so playback isn't held up while writing the pack
+    let (data, bindings, categories) = {
+        let soundbites = state.soundbites.lock().unwrap();
+        if let Some(names) = &names {
+            if let Some(name) = names.iter().find(
+                |name| !soundbites.iter().any(|soundbite| soundbite.data.name == **name)
+            ) {
+                return Err(SoundManagerError::SoundbiteNotFound(name.clone()));
+            }
+        }
+
+        let selected: Vec<usize> = (0..soundbites.len()).filter(|&index| match &names {
+            Some(names) => names.contains(&soundbites[index].data.name),
+            None => true,
+        }).collect();
+
+        let bindings: Vec<PackBinding> = if include_bindings {
+            let soundbites_keytasks = state.soundbites_keytasks.lock().unwrap();
+            soundbites_keytasks.iter()
+                .filter_map(|(&keycode, binding)| {
+                    let index = binding.soundbite().filter(|index| selected.contains(index))?;
+                    Some(PackBinding {
+                        soundbite: soundbites.get(index)?.data.name.clone(),
+                        keycode,
+                        swallow: binding.swallow,
+                        trigger: binding.trigger,
+                    })
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let data: Vec<SoundbiteData> = selected.iter().map(|&index| soundbites[index].data.clone()).collect();
+        let categories: Vec<String> = state.categories.lock().unwrap()
+            .iter()
+            .filter(|category| data.iter().any(|d| d.category.as_ref() == Some(*category)))
+            .cloned()
+            .collect();
+        (data, bindings, categories)
+    };
+
+    pack::write_pack(&PathBuf::from(path), &data, bindings, categories)
+}
+
+#[tauri::command]
+pub fn import_pack(
+    path: String,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let path = PathBuf::from(path);
+    if !path.is_file() {
+        return Err(SoundManagerError::InvalidPack(path.display().to_string()));
+    }
+
+    if let Err(err) = state.messages.send(Message::ImportPack(path)) {
+        error!("Unable to send command to import pack [[{:?}]]", err);
+        return Err(SoundManagerError::UnableToSendMessage);
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn play_soundbite(
     name: String,
     state: State<'_, SoundState>
-) {
-    let soundbites = state.soundbites.lock().unwrap();
-    soundbites
-        .iter()
-        .find(|soundbite| soundbite.data.name == name)
-        .map(|soundbite| soundbite.stop());
+) -> Result<(), SoundManagerError> {
+    control::play(&state, &name)
 }
 
+//Every playing soundbite when no name is given
 #[tauri::command]
-pub fn set_name(
+pub fn stop_soundbite(
+    name: Option<String>,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    control::stop(&state, name.as_deref())
+}
+
+#[tauri::command]
+pub fn get_variant_sets(
+    state: State<'_, SoundState>
+) -> Vec<VariantSet> {
+    state.variant_sets.lock().unwrap().clone()
+}
+
+//Replaces the variant set with the same name, if any, keeping its bindings
+#[tauri::command]
+pub fn save_variant_set(
     name: String,
-    new_name: String,
-    state: State<'_, SoundState>,
+    variants: Vec<Variant>,
+    mode: VariantMode,
+    state: State<'_, SoundState>
 ) -> Result<(), SoundManagerError> {
-    let mut soundbites = state.soundbites.lock().unwrap();
-    match soundbites.iter().find(|soundbite| soundbite.data.name == new_name) {
-        Some(_) => Err(SoundManagerError::SoundbiteAlreadyExists(new_name)),
-        None => {
-            match soundbites.iter().position(|soundbite| soundbite.data.name == name) {
-                Some(index) => {
-                    soundbites[index].data.name = new_name;
-                    Ok(())
-                },
-                None => Err(SoundManagerError::SoundbiteNotFound(name)),
-            }
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(SoundManagerError::InvalidVariantSetName);
+    }
+
+    {
+        let soundbites = state.soundbites.lock().unwrap();
+        if let Some(missing) = variants.iter().find(
+            |variant| !soundbites.iter().any(|s| s.data.name == variant.soundbite)
+        ) {
+            return Err(SoundManagerError::SoundbiteNotFound(missing.soundbite.clone()));
         }
     }
+
+    let variant_set = VariantSet::new(name, variants, mode);
+    let mut variant_sets = state.variant_sets.lock().unwrap();
+    match variant_sets.iter_mut().find(|other| other.name == variant_set.name) {
+        Some(other) => *other = variant_set,
+        None => variant_sets.push(variant_set),
+    }
+    Ok(())
 }
 
 #[tauri::command]
-pub fn set_volume(
+pub fn rename_variant_set(
     name: String,
-    volume: f32,
-    state: State<'_, SoundState>,
+    new_name: String,
+    state: State<'_, SoundState>
 ) -> Result<(), SoundManagerError> {
-    if volume <= 0f32 || volume > 200f32 {
-        return Err(SoundManagerError::InvalidVolumeValue);
+    let new_name = new_name.trim().to_string();
+    if new_name.is_empty() {
+        return Err(SoundManagerError::InvalidVariantSetName);
     }
 
-    let mut soundbites = state.soundbites.lock().unwrap();
-    match soundbites.iter().position(|s| s.data.name == name) {
-        Some(index) => {
-            soundbites[index].set_volume(volume);
+    let mut variant_sets = state.variant_sets.lock().unwrap();
+    if variant_sets.iter().any(|variant_set| variant_set.name == new_name) {
+        return Err(SoundManagerError::VariantSetAlreadyExists(new_name));
+    }
+
+    match variant_sets.iter_mut().find(|variant_set| variant_set.name == name) {
+        Some(variant_set) => {
+            variant_set.name = new_name.clone();
+            soundmanager::retarget_bindings(
+                &mut state.soundbites_keytasks.lock().unwrap(),
+                &mut state.trigger_bindings.lock().unwrap(),
+                &mut state.profiles.lock().unwrap(),
+                &BindingTarget::VariantSet(name),
+                Some(&BindingTarget::VariantSet(new_name)),
+            );
             Ok(())
         },
-        None => Err(SoundManagerError::SoundbiteNotFound(name)),
+        None => Err(SoundManagerError::VariantSetNotFound(name)),
     }
 }
 
+//Removes its key codes and triggers as well
 #[tauri::command]
-pub fn set_speed(
+pub fn remove_variant_set(
     name: String,
-    speed: f32,
-    state: State<'_, SoundState>,
+    state: State<'_, SoundState>
 ) -> Result<(), SoundManagerError> {
-    if speed <= 0f32 || speed > 200f32 {
-        return Err(SoundManagerError::InvalidSpeedValue);
-    }
-
-    let mut soundbites = state.soundbites.lock().unwrap();
-    match soundbites.iter().position(|s| s.data.name == name) {
+    let mut variant_sets = state.variant_sets.lock().unwrap();
+    match variant_sets.iter().position(|variant_set| variant_set.name == name) {
         Some(index) => {
-            soundbites[index].set_speed(speed);
+            variant_sets.remove(index);
+            soundmanager::retarget_bindings(
+                &mut state.soundbites_keytasks.lock().unwrap(),
+                &mut state.trigger_bindings.lock().unwrap(),
+                &mut state.profiles.lock().unwrap(),
+                &BindingTarget::VariantSet(name),
+                None,
+            );
             Ok(())
         },
-        None => Err(SoundManagerError::SoundbiteNotFound(name)),
+        None => Err(SoundManagerError::VariantSetNotFound(name)),
     }
 }
 
 #[tauri::command]
-pub fn set_keytask_code(
+pub fn play_variant_set(
+    name: String,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    if !state.variant_sets.lock().unwrap().iter().any(|variant_set| variant_set.name == name) {
+        return Err(SoundManagerError::VariantSetNotFound(name));
+    }
+
+    if let Err(err) = state.messages.send(Message::PlayVariantSet(name)) {
+        error!("Unable to send command to play variant set [[{:?}]]", err);
+        return Err(SoundManagerError::UnableToSendMessage);
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_variant_set_keycode(
     name: String,
     keytask_code: KeyTaskCode,
     state: State<'_, SoundState>
 ) -> Result<(), SoundManagerError> {
-    let soundbites = state.soundbites.lock().unwrap();
-    if let Some(index) = soundbites.iter().position(|soundbite| soundbite.data.name == name) {
-        let mut soundbites_keytasks = state.soundbites_keytasks.lock().unwrap();
-        if let Some(_) = soundbites_keytasks.get(&keytask_code) {
-            return Err(SoundManagerError::KeyTaskUsed(keytask_code));
-        }
+    if !state.variant_sets.lock().unwrap().iter().any(|variant_set| variant_set.name == name) {
+        return Err(SoundManagerError::VariantSetNotFound(name));
+    }
 
-        soundbites_keytasks.retain(|_, v| *v != index);
-        soundbites_keytasks.insert(keytask_code, index);
-        Ok(())
-    } else {
-        Err(SoundManagerError::SoundbiteNotFound(name))
+    bind_keycode(BindingTarget::VariantSet(name), keytask_code, &state)
+}
+
+#[tauri::command]
+pub fn set_variant_set_keytask_options(
+    name: String,
+    swallow: bool,
+    trigger: TriggerMode,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    set_keytask_options(BindingTarget::VariantSet(name), swallow, trigger, &state)
+}
+
+#[tauri::command]
+pub fn remove_variant_set_keycode(
+    name: String,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let target = BindingTarget::VariantSet(name);
+    state.soundbites_keytasks.lock().unwrap().retain(|_, binding| binding.target != target);
+    Ok(())
+}
+
+//MIDI or gamepad trigger for a variant set
+#[tauri::command]
+pub fn set_variant_set_trigger(
+    name: String,
+    trigger: TriggerCode,
+    mode: Option<TriggerMode>,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    if !state.variant_sets.lock().unwrap().iter().any(|variant_set| variant_set.name == name) {
+        return Err(SoundManagerError::VariantSetNotFound(name));
     }
+
+    bind_trigger(BindingTarget::VariantSet(name), trigger, mode, &state)
 }
 
 #[tauri::command]
-pub fn remove_keytask_code(
+pub fn get_macros(
+    state: State<'_, SoundState>
+) -> Vec<Macro> {
+    state.macros.lock().unwrap().clone()
+}
+
+//Replaces the macro with the same name, if any, keeping its bindings
+#[tauri::command]
+pub fn save_macro(
     name: String,
+    steps: Vec<MacroStep>,
     state: State<'_, SoundState>
 ) -> Result<(), SoundManagerError> {
-    let soundbites = state.soundbites.lock().unwrap();
-    if let Some(index) = soundbites.iter().position(|soundbite| soundbite.data.name == name) {
-        let mut soundbites_keytasks = state.soundbites_keytasks.lock().unwrap();
-        let keys: Vec<KeyTaskCode> = soundbites_keytasks.iter().filter_map(
-            |(&key, &val)| if val == index { Some(key) } else { None }
-        ).collect();
-        keys.iter().for_each(
-            |keycode| {
-                soundbites_keytasks.remove(keycode);
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(SoundManagerError::InvalidMacroName);
+    }
+
+    if let Some(index) = steps.iter().position(|step| !step.is_valid()) {
+        return Err(SoundManagerError::InvalidMacroStep(index + 1));
+    }
+
+    {
+        let soundbites = state.soundbites.lock().unwrap();
+        if let Some(missing) = steps.iter()
+            .filter_map(|step| step.soundbite())
+            .find(|name| !soundbites.iter().any(|s| s.data.name == **name))
+        {
+            return Err(SoundManagerError::SoundbiteNotFound(missing.clone()));
+        }
+    }
+
+    {
+        let profiles = state.profiles.lock().unwrap();
+        for step in steps.iter() {
+            if let MacroStep::SwitchProfile { profile } = step {
+                if profiles.get(profile).is_none() {
+                    return Err(SoundManagerError::ProfileNotFound(profile.clone()));
+                }
             }
-        );
+        }
     }
 
+    let macro_ = Macro { name, steps };
+    let mut macros = state.macros.lock().unwrap();
+    match macros.iter_mut().find(|other| other.name == macro_.name) {
+        Some(other) => *other = macro_,
+        None => macros.push(macro_),
+    }
     Ok(())
 }
 
 #[tauri::command]
-pub fn get_soundbite(
+pub fn rename_macro(
     name: String,
+    new_name: String,
     state: State<'_, SoundState>
-) -> Result<SoundbiteInfo, SoundManagerError> {
-    let soundbites = state.soundbites.lock().unwrap();
-    match soundbites.iter().position(|soundbite| *soundbite.data.name == name) {
-        Some(index) => {
-            let soundbites_keytasks = state.soundbites_keytasks.lock().unwrap();
-            let keycode = match soundbites_keytasks.iter().find(|(_, &value)| {
-                value == index
-            }) {
-                Some(item) => item.0.clone(),
-                None => 0
-            };
+) -> Result<(), SoundManagerError> {
+    let new_name = new_name.trim().to_string();
+    if new_name.is_empty() {
+        return Err(SoundManagerError::InvalidMacroName);
+    }
 
-            let soundbite = &soundbites[index];
-            Ok(SoundbiteInfo {
-                name: soundbite.data.name.clone(),
-                volume: soundbite.data.volume,
-                speed: soundbite.data.speed,
-                keycode
-            })
+    let mut macros = state.macros.lock().unwrap();
+    if macros.iter().any(|macro_| macro_.name == new_name) {
+        return Err(SoundManagerError::MacroAlreadyExists(new_name));
+    }
+
+    match macros.iter_mut().find(|macro_| macro_.name == name) {
+        Some(macro_) => {
+            macro_.name = new_name.clone();
+            soundmanager::retarget_bindings(
+                &mut state.soundbites_keytasks.lock().unwrap(),
+                &mut state.trigger_bindings.lock().unwrap(),
+                &mut state.profiles.lock().unwrap(),
+                &BindingTarget::Macro(name),
+                Some(&BindingTarget::Macro(new_name)),
+            );
+            Ok(())
         },
-        None => {
-            Err(SoundManagerError::SoundbiteNotFound(name.clone()))
-        }
+        None => Err(SoundManagerError::MacroNotFound(name)),
     }
 }
 
+//Removes its key codes and triggers as well
 #[tauri::command]
-pub fn get_soundbites(
+pub fn remove_macro(
+    name: String,
     state: State<'_, SoundState>
-) -> Vec<String> {
-    let soundbites = state.soundbites.lock().unwrap();
-    soundbites.iter().map(|soundbite| soundbite.data.name.clone()).collect()
+) -> Result<(), SoundManagerError> {
+    let mut macros = state.macros.lock().unwrap();
+    match macros.iter().position(|macro_| macro_.name == name) {
+        Some(index) => {
+            macros.remove(index);
+            soundmanager::retarget_bindings(
+                &mut state.soundbites_keytasks.lock().unwrap(),
+                &mut state.trigger_bindings.lock().unwrap(),
+                &mut state.profiles.lock().unwrap(),
+                &BindingTarget::Macro(name.clone()),
+                None,
+            );
+            if let Err(err) = state.messages.send(Message::StopMacro(name)) {
+                error!("Unable to send command to stop macro [[{:?}]]", err);
+            }
+            Ok(())
+        },
+        None => Err(SoundManagerError::MacroNotFound(name)),
+    }
+}
+
+#[tauri::command]
+pub fn run_macro(
+    name: String,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    if !state.macros.lock().unwrap().iter().any(|macro_| macro_.name == name) {
+        return Err(SoundManagerError::MacroNotFound(name));
+    }
+
+    if let Err(err) = state.messages.send(Message::RunMacro(name)) {
+        error!("Unable to send command to run macro [[{:?}]]", err);
+        return Err(SoundManagerError::UnableToSendMessage);
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_macro(
+    name: String,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    if let Err(err) = state.messages.send(Message::StopMacro(name)) {
+        error!("Unable to send command to stop macro [[{:?}]]", err);
+        return Err(SoundManagerError::UnableToSendMessage);
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_macro_keycode(
+    name: String,
+    keytask_code: KeyTaskCode,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    if !state.macros.lock().unwrap().iter().any(|macro_| macro_.name == name) {
+        return Err(SoundManagerError::MacroNotFound(name));
+    }
+
+    bind_keycode(BindingTarget::Macro(name), keytask_code, &state)
+}
+
+#[tauri::command]
+pub fn set_macro_keytask_options(
+    name: String,
+    swallow: bool,
+    trigger: TriggerMode,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    set_keytask_options(BindingTarget::Macro(name), swallow, trigger, &state)
+}
+
+#[tauri::command]
+pub fn remove_macro_keycode(
+    name: String,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let target = BindingTarget::Macro(name);
+    state.soundbites_keytasks.lock().unwrap().retain(|_, binding| binding.target != target);
+    Ok(())
+}
+
+//MIDI or gamepad trigger for a macro
+#[tauri::command]
+pub fn set_macro_trigger(
+    name: String,
+    trigger: TriggerCode,
+    mode: Option<TriggerMode>,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    if !state.macros.lock().unwrap().iter().any(|macro_| macro_.name == name) {
+        return Err(SoundManagerError::MacroNotFound(name));
+    }
+
+    bind_trigger(BindingTarget::Macro(name), trigger, mode, &state)
+}
+
+//Same rules as set_keytask_code, for variant sets and macros
+fn bind_keycode(
+    target: BindingTarget,
+    keytask_code: KeyTaskCode,
+    state: &SoundState
+) -> Result<(), SoundManagerError> {
+    if chord_conflict(keytask_code, state).is_some() {
+        return Err(SoundManagerError::KeyTaskUsed(keytask_code));
+    }
+
+    let mut soundbites_keytasks = state.soundbites_keytasks.lock().unwrap();
+    //Rebinding keeps the options of the previous chord
+    let binding = soundbites_keytasks.values()
+        .find(|binding| binding.target == target)
+        .cloned()
+        .unwrap_or_else(|| KeyBinding::new(target.clone()));
+    soundbites_keytasks.retain(|_, binding| binding.target != target);
+    soundbites_keytasks.insert(keytask_code, binding);
+    Ok(())
+}
+
+fn set_keytask_options(
+    target: BindingTarget,
+    swallow: bool,
+    trigger: TriggerMode,
+    state: &SoundState
+) -> Result<(), SoundManagerError> {
+    {
+        let mut soundbites_keytasks = state.soundbites_keytasks.lock().unwrap();
+        if let Some(binding) = soundbites_keytasks.values_mut().find(|binding| binding.target == target) {
+            binding.swallow = swallow;
+            binding.trigger = trigger;
+            return Ok(());
+        }
+    }
+
+    //Soundbites are locked before key tasks, not while holding them
+    let name = match &target {
+        BindingTarget::Soundbite(_) => soundmanager::binding_target_name(
+            &state.soundbites.lock().unwrap(),
+            &target
+        ),
+        BindingTarget::VariantSet(name) | BindingTarget::Macro(name) => name.clone(),
+    };
+    Err(SoundManagerError::BindingNotSet(name))
+}
+
+fn bind_trigger(
+    target: BindingTarget,
+    trigger: TriggerCode,
+    mode: Option<TriggerMode>,
+    state: &SoundState
+) -> Result<(), SoundManagerError> {
+    let mut trigger_bindings = state.trigger_bindings.lock().unwrap();
+    match trigger_bindings.get(&trigger) {
+        Some(binding) if binding.target != target => {
+            Err(SoundManagerError::TriggerUsed(trigger.label()))
+        },
+        _ => {
+            let mut binding = KeyBinding::new(target);
+            binding.trigger = mode.unwrap_or_default();
+            trigger_bindings.insert(trigger, binding);
+            Ok(())
+        },
+    }
+}
+
+#[tauri::command]
+pub fn get_schedules(
+    state: State<'_, SoundState>
+) -> Vec<Schedule> {
+    state.scheduler.lock().unwrap().schedules.clone()
+}
+
+#[tauri::command]
+pub fn add_schedule(
+    name: String,
+    soundbite: String,
+    rule: ScheduleRule,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(SoundManagerError::InvalidScheduleName);
+    }
+
+    if !state.soundbites.lock().unwrap().iter().any(|s| s.data.name == soundbite) {
+        return Err(SoundManagerError::SoundbiteNotFound(soundbite));
+    }
+
+    let mut scheduler = state.scheduler.lock().unwrap();
+    if scheduler.get_mut(&name).is_some() {
+        return Err(SoundManagerError::ScheduleAlreadyExists(name));
+    }
+
+    rule.validate(scheduler.now()).map_err(SoundManagerError::InvalidSchedule)?;
+    scheduler.add(Schedule::new(name, soundbite, rule));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn remove_schedule(
+    name: String,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let mut scheduler = state.scheduler.lock().unwrap();
+    match scheduler.schedules.iter().position(|schedule| schedule.name == name) {
+        Some(index) => {
+            scheduler.schedules.remove(index);
+            Ok(())
+        },
+        None => Err(SoundManagerError::ScheduleNotFound(name)),
+    }
+}
+
+//Enabling a countdown starts it over
+#[tauri::command]
+pub fn set_schedule_enabled(
+    name: String,
+    enabled: bool,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    if state.scheduler.lock().unwrap().set_enabled(&name, enabled) {
+        Ok(())
+    } else {
+        Err(SoundManagerError::ScheduleNotFound(name))
+    }
+}
+
+#[tauri::command]
+pub fn get_queue(
+    state: State<'_, SoundState>
+) -> QueueInfo {
+    state.queue.lock().unwrap().info()
+}
+
+#[tauri::command]
+pub fn enqueue_soundbite(
+    name: String,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    control::enqueue(&state, &name)
+}
+
+#[tauri::command]
+pub fn queue_next(
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    control::queue_next(&state)
+}
+
+#[tauri::command]
+pub fn queue_previous(
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    control::queue_previous(&state)
+}
+
+#[tauri::command]
+pub fn clear_queue(
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    control::clear_queue(&state)
+}
+
+//Back to back when no transition is given
+#[tauri::command]
+pub fn set_queue_transition(
+    transition: Option<Transition>,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    if transition.is_some_and(|transition| !transition.is_valid()) {
+        return Err(SoundManagerError::InvalidTransition);
+    }
+
+    state.queue.lock().unwrap().transition = transition;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_playlists(
+    state: State<'_, SoundState>
+) -> Vec<Playlist> {
+    state.playlists.lock().unwrap().clone()
+}
+
+//Replaces the playlist with the same name, if any
+#[tauri::command]
+pub fn save_playlist(
+    name: String,
+    soundbites: Vec<String>,
+    transition: Option<Transition>,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(SoundManagerError::InvalidPlaylistName);
+    }
+
+    if transition.is_some_and(|transition| !transition.is_valid()) {
+        return Err(SoundManagerError::InvalidTransition);
+    }
+
+    {
+        let library = state.soundbites.lock().unwrap();
+        if let Some(missing) = soundbites.iter().find(
+            |soundbite| !library.iter().any(|s| s.data.name == **soundbite)
+        ) {
+            return Err(SoundManagerError::SoundbiteNotFound(missing.clone()));
+        }
+    }
+
+    let playlist = Playlist {
+        name,
+        soundbites,
+        transition,
+    };
+    let mut playlists = state.playlists.lock().unwrap();
+    match playlists.iter_mut().find(|other| other.name == playlist.name) {
+        Some(other) => *other = playlist,
+        None => playlists.push(playlist),
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn remove_playlist(
+    name: String,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let mut playlists = state.playlists.lock().unwrap();
+    match playlists.iter().position(|playlist| playlist.name == name) {
+        Some(index) => {
+            playlists.remove(index);
+            Ok(())
+        },
+        None => Err(SoundManagerError::PlaylistNotFound(name)),
+    }
+}
+
+//Replaces the queue with the playlist
+#[tauri::command]
+pub fn play_playlist(
+    name: String,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    if !state.playlists.lock().unwrap().iter().any(|playlist| playlist.name == name) {
+        return Err(SoundManagerError::PlaylistNotFound(name));
+    }
+
+    if let Err(err) = state.messages.send(Message::PlayPlaylist(name)) {
+        error!("Unable to send command to play playlist [[{:?}]]", err);
+        return Err(SoundManagerError::UnableToSendMessage);
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_name(
+    name: String,
+    new_name: String,
+    state: State<'_, SoundState>,
+) -> Result<(), SoundManagerError> {
+    let (done, result) = crossbeam::channel::bounded(1);
+    if let Err(err) = state.messages.send(Message::RenameSoundbite { name, new_name, done }) {
+        error!("Unable to send command to rename soundbite [[{:?}]]", err);
+        return Err(SoundManagerError::UnableToSendMessage);
+    }
+
+    result.recv().unwrap_or(Err(SoundManagerError::UnableToSendMessage))
+}
+
+#[tauri::command]
+pub fn set_volume(
+    name: String,
+    volume: f32,
+    state: State<'_, SoundState>,
+) -> Result<(), SoundManagerError> {
+    control::set_volume(&state, &name, volume)
+}
+
+#[tauri::command]
+pub fn set_speed(
+    name: String,
+    speed: f32,
+    state: State<'_, SoundState>,
+) -> Result<(), SoundManagerError> {
+    if speed <= 0f32 || speed > 200f32 {
+        return Err(SoundManagerError::InvalidSpeedValue);
+    }
+
+    let mut soundbites = state.soundbites.lock().unwrap();
+    match soundbites.iter().position(|s| s.data.name == name) {
+        Some(index) => {
+            soundbites[index].set_speed(speed);
+            Ok(())
+        },
+        None => Err(SoundManagerError::SoundbiteNotFound(name)),
+    }
+}
+
+#[tauri::command]
+pub fn set_keytask_code(
+    name: String,
+    keytask_code: KeyTaskCode,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    if chord_conflict(keytask_code, &state).is_some() {
+        return Err(SoundManagerError::KeyTaskUsed(keytask_code));
+    }
+
+    let soundbites = state.soundbites.lock().unwrap();
+    if let Some(index) = soundbites.iter().position(|soundbite| soundbite.data.name == name) {
+        let mut soundbites_keytasks = state.soundbites_keytasks.lock().unwrap();
+        //Rebinding keeps the options of the previous chord
+        let binding = soundbites_keytasks.values()
+            .find(|binding| binding.is_soundbite(index))
+            .cloned()
+            .unwrap_or_else(|| KeyBinding::new(BindingTarget::Soundbite(index)));
+        soundbites_keytasks.retain(|_, binding| !binding.is_soundbite(index));
+        soundbites_keytasks.insert(keytask_code, binding);
+        Ok(())
+    } else {
+        Err(SoundManagerError::SoundbiteNotFound(name))
+    }
+}
+
+#[tauri::command]
+pub fn set_keytask_swallow(
+    name: String,
+    swallow: bool,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let soundbites = state.soundbites.lock().unwrap();
+    if let Some(index) = soundbites.iter().position(|soundbite| soundbite.data.name == name) {
+        let mut soundbites_keytasks = state.soundbites_keytasks.lock().unwrap();
+        match soundbites_keytasks.values_mut().find(|binding| binding.is_soundbite(index)) {
+            Some(binding) => {
+                binding.swallow = swallow;
+                Ok(())
+            },
+            None => Err(SoundManagerError::KeyTaskNotSet(name)),
+        }
+    } else {
+        Err(SoundManagerError::SoundbiteNotFound(name))
+    }
+}
+
+#[tauri::command]
+pub fn set_keytask_trigger(
+    name: String,
+    trigger: TriggerMode,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let soundbites = state.soundbites.lock().unwrap();
+    if let Some(index) = soundbites.iter().position(|soundbite| soundbite.data.name == name) {
+        let mut soundbites_keytasks = state.soundbites_keytasks.lock().unwrap();
+        match soundbites_keytasks.values_mut().find(|binding| binding.is_soundbite(index)) {
+            Some(binding) => {
+                binding.trigger = trigger;
+                Ok(())
+            },
+            None => Err(SoundManagerError::KeyTaskNotSet(name)),
+        }
+    } else {
+        Err(SoundManagerError::SoundbiteNotFound(name))
+    }
+}
+
+#[tauri::command]
+pub fn remove_keytask_code(
+    name: String,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let soundbites = state.soundbites.lock().unwrap();
+    if let Some(index) = soundbites.iter().position(|soundbite| soundbite.data.name == name) {
+        let mut soundbites_keytasks = state.soundbites_keytasks.lock().unwrap();
+        let keys: Vec<KeyTaskCode> = soundbites_keytasks.iter().filter_map(
+            |(&key, binding)| if binding.is_soundbite(index) { Some(key) } else { None }
+        ).collect();
+        keys.iter().for_each(
+            |keycode| {
+                soundbites_keytasks.remove(keycode);
+            }
+        );
+    }
+
+    Ok(())
+}
+
+const DEFAULT_CAPTURE_TIMEOUT: u64 = 10_000;
+
+//What the chord is bound to already, every chord setter checks it
+fn chord_conflict(keytask_code: KeyTaskCode, state: &SoundState) -> Option<ChordConflict> {
+    let soundbites = state.soundbites.lock().unwrap();
+    let soundbites_keytasks = state.soundbites_keytasks.lock().unwrap();
+    if let Some(binding) = soundbites_keytasks.get(&keytask_code) {
+        return Some(match &binding.target {
+            BindingTarget::Soundbite(_) => ChordConflict::Soundbite {
+                name: soundmanager::binding_target_name(&soundbites, &binding.target),
+            },
+            BindingTarget::VariantSet(name) => ChordConflict::VariantSet {
+                name: name.clone(),
+            },
+            BindingTarget::Macro(name) => ChordConflict::Macro {
+                name: name.clone(),
+            },
+        });
+    }
+
+    if let Some(profile) = state.profiles.lock().unwrap().find_by_switch_keycode(keytask_code) {
+        return Some(ChordConflict::ProfileSwitch {
+            profile: profile.name.clone(),
+        });
+    }
+
+    let settings = state.settings.lock().unwrap();
+    if settings.pause_keycode == Some(keytask_code) {
+        Some(ChordConflict::PauseToggle)
+    } else if settings.swallow_escape.keycode == keytask_code {
+        Some(ChordConflict::SwallowEscape)
+    } else {
+        None
+    }
+}
+
+//Waits for the next chord pressed anywhere, which is not dispatched
+#[tauri::command(async)]
+pub fn capture_keytask_code(
+    timeout: Option<u64>,
+    state: State<'_, SoundState>
+) -> Result<CapturedChord, SoundManagerError> {
+    let (id, captured) = key_hook::start_capture();
+    let timeout = Duration::from_millis(timeout.unwrap_or(DEFAULT_CAPTURE_TIMEOUT));
+    match captured.recv_timeout(timeout) {
+        Ok(mut chord) => {
+            chord.conflict = chord_conflict(chord.keycode, &state);
+            Ok(chord)
+        },
+        Err(RecvTimeoutError::Timeout) => {
+            key_hook::end_capture(id);
+            Err(SoundManagerError::CaptureTimedOut)
+        },
+        Err(RecvTimeoutError::Disconnected) => Err(SoundManagerError::CaptureCancelled),
+    }
+}
+
+#[tauri::command]
+pub fn cancel_capture() {
+    key_hook::stop_capture();
+}
+
+#[tauri::command]
+pub fn get_triggers(
+    state: State<'_, SoundState>
+) -> Vec<TriggerInfo> {
+    let soundbites = state.soundbites.lock().unwrap();
+    let trigger_bindings = state.trigger_bindings.lock().unwrap();
+    trigger_bindings.iter().map(|(trigger, binding)| TriggerInfo {
+        trigger: trigger.clone(),
+        display: trigger.label(),
+        soundbite: soundmanager::binding_target_name(&soundbites, &binding.target),
+        binding: binding.clone(),
+    }).collect()
+}
+
+//A soundbite can have any number of triggers, unlike key codes
+#[tauri::command]
+pub fn set_trigger(
+    name: String,
+    trigger: TriggerCode,
+    mode: Option<TriggerMode>,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let soundbites = state.soundbites.lock().unwrap();
+    let index = match soundbites.iter().position(|soundbite| soundbite.data.name == name) {
+        Some(index) => index,
+        None => return Err(SoundManagerError::SoundbiteNotFound(name)),
+    };
+
+    let mut trigger_bindings = state.trigger_bindings.lock().unwrap();
+    match trigger_bindings.get(&trigger) {
+        Some(binding) if !binding.is_soundbite(index) => {
+            Err(SoundManagerError::TriggerUsed(trigger.label()))
+        },
+        _ => {
+            let mut binding = KeyBinding::new(BindingTarget::Soundbite(index));
+            binding.trigger = mode.unwrap_or_default();
+            trigger_bindings.insert(trigger, binding);
+            Ok(())
+        },
+    }
+}
+
+#[tauri::command]
+pub fn remove_trigger(
+    trigger: TriggerCode,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    match state.trigger_bindings.lock().unwrap().remove(&trigger) {
+        Some(_) => Ok(()),
+        None => Err(SoundManagerError::TriggerNotFound(trigger.label())),
+    }
+}
+
+//Waits for the next MIDI or gamepad input, which is not dispatched
+#[tauri::command(async)]
+pub fn learn_trigger(
+    timeout: Option<u64>,
+    state: State<'_, SoundState>
+) -> Result<LearnedTrigger, SoundManagerError> {
+    let (id, learned) = trigger::start_learn();
+    let timeout = Duration::from_millis(timeout.unwrap_or(DEFAULT_CAPTURE_TIMEOUT));
+    match learned.recv_timeout(timeout) {
+        Ok(trigger) => {
+            let soundbites = state.soundbites.lock().unwrap();
+            let bound_to = state.trigger_bindings.lock().unwrap()
+                .get(&trigger)
+                .map(|binding| soundmanager::binding_target_name(&soundbites, &binding.target));
+
+            Ok(LearnedTrigger {
+                display: trigger.label(),
+                trigger,
+                bound_to,
+            })
+        },
+        Err(RecvTimeoutError::Timeout) => {
+            trigger::end_learn(id);
+            Err(SoundManagerError::LearnTimedOut)
+        },
+        Err(RecvTimeoutError::Disconnected) => Err(SoundManagerError::LearnCancelled),
+    }
+}
+
+#[tauri::command]
+pub fn cancel_learn_trigger() {
+    trigger::stop_learn();
+}
+
+#[tauri::command]
+pub fn get_soundbite(
+    name: String,
+    state: State<'_, SoundState>
+) -> Result<SoundbiteInfo, SoundManagerError> {
+    let soundbites = state.soundbites.lock().unwrap();
+    match soundbites.iter().position(|soundbite| *soundbite.data.name == name) {
+        Some(index) => {
+            let soundbites_keytasks = state.soundbites_keytasks.lock().unwrap();
+            Ok(SoundbiteInfo::new(&soundbites, &soundbites_keytasks, index))
+        },
+        None => {
+            Err(SoundManagerError::SoundbiteNotFound(name.clone()))
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_soundbites(
+    state: State<'_, SoundState>
+) -> Vec<String> {
+    let soundbites = state.soundbites.lock().unwrap();
+    soundbites.iter().map(|soundbite| soundbite.data.name.clone()).collect()
+}
+
+//Soundbites in category, when given, having all the tags
+#[tauri::command]
+pub fn list_soundbites(
+    category: Option<String>,
+    tags: Vec<String>,
+    state: State<'_, SoundState>
+) -> Vec<SoundbiteInfo> {
+    let soundbites = state.soundbites.lock().unwrap();
+    let soundbites_keytasks = state.soundbites_keytasks.lock().unwrap();
+    (0..soundbites.len())
+        .filter(|&index| {
+            let data = &soundbites[index].data;
+            (category.is_none() || data.category == category)
+                && tags.iter().all(|tag| data.has_tag(tag))
+        })
+        .map(|index| SoundbiteInfo::new(&soundbites, &soundbites_keytasks, index))
+        .collect()
+}
+
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+
+//Best matches first, an empty query lists the library in order
+#[tauri::command]
+pub fn search_soundbites(
+    query: String,
+    limit: Option<usize>,
+    state: State<'_, SoundState>
+) -> Vec<SoundbiteInfo> {
+    let soundbites = state.soundbites.lock().unwrap();
+    let soundbites_keytasks = state.soundbites_keytasks.lock().unwrap();
+
+    let mut matches: Vec<(usize, i32)> = soundbites.iter()
+        .enumerate()
+        .filter_map(|(index, soundbite)| {
+            search::score_soundbite(&query, &soundbite.data).map(|score| (index, score))
+        })
+        .collect();
+    matches.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    matches.into_iter()
+        .take(limit.unwrap_or(DEFAULT_SEARCH_LIMIT))
+        .map(|(index, _)| SoundbiteInfo::new(&soundbites, &soundbites_keytasks, index))
+        .collect()
+}
+
+#[tauri::command]
+pub fn get_categories(
+    state: State<'_, SoundState>
+) -> Vec<String> {
+    state.categories.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn create_category(
+    name: String,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(SoundManagerError::InvalidCategoryName);
+    }
+
+    let mut categories = state.categories.lock().unwrap();
+    if categories.contains(&name) {
+        return Err(SoundManagerError::CategoryAlreadyExists(name));
+    }
+
+    categories.push(name);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn rename_category(
+    name: String,
+    new_name: String,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let new_name = new_name.trim().to_string();
+    if new_name.is_empty() {
+        return Err(SoundManagerError::InvalidCategoryName);
+    }
+
+    //Soundbites first, in the same order as everywhere else
+    let mut soundbites = state.soundbites.lock().unwrap();
+    let mut categories = state.categories.lock().unwrap();
+    if categories.contains(&new_name) {
+        return Err(SoundManagerError::CategoryAlreadyExists(new_name));
+    }
+
+    match categories.iter().position(|category| *category == name) {
+        Some(index) => {
+            categories[index] = new_name.clone();
+            soundbites.iter_mut()
+                .filter(|soundbite| soundbite.data.category.as_ref() == Some(&name))
+                .for_each(|soundbite| soundbite.data.category = Some(new_name.clone()));
+            Ok(())
+        },
+        None => Err(SoundManagerError::CategoryNotFound(name)),
+    }
+}
+
+//Soundbites in the removed category become uncategorized
+#[tauri::command]
+pub fn remove_category(
+    name: String,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let mut soundbites = state.soundbites.lock().unwrap();
+    let mut categories = state.categories.lock().unwrap();
+    match categories.iter().position(|category| *category == name) {
+        Some(index) => {
+            categories.remove(index);
+            soundbites.iter_mut()
+                .filter(|soundbite| soundbite.data.category.as_ref() == Some(&name))
+                .for_each(|soundbite| soundbite.data.category = None);
+            Ok(())
+        },
+        None => Err(SoundManagerError::CategoryNotFound(name)),
+    }
+}
+
+#[tauri::command]
+pub fn move_category(
+    name: String,
+    position: usize,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let mut categories = state.categories.lock().unwrap();
+    match categories.iter().position(|category| *category == name) {
+        Some(index) => {
+            let category = categories.remove(index);
+            let position = position.min(categories.len());
+            categories.insert(position, category);
+            Ok(())
+        },
+        None => Err(SoundManagerError::CategoryNotFound(name)),
+    }
+}
+
+#[tauri::command]
+pub fn set_soundbite_category(
+    name: String,
+    category: Option<String>,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    if let Some(category) = &category {
+        if !state.categories.lock().unwrap().contains(category) {
+            return Err(SoundManagerError::CategoryNotFound(category.clone()));
+        }
+    }
+
+    let mut soundbites = state.soundbites.lock().unwrap();
+    match soundbites.iter_mut().find(|soundbite| soundbite.data.name == name) {
+        Some(soundbite) => {
+            soundbite.data.category = category;
+            Ok(())
+        },
+        None => Err(SoundManagerError::SoundbiteNotFound(name)),
+    }
+}
+
+#[tauri::command]
+pub fn get_choke_groups(
+    state: State<'_, SoundState>
+) -> Vec<ChokeGroup> {
+    state.choke_groups.lock().unwrap().clone()
+}
+
+//Members are stopped right away when no crossfade is given
+#[tauri::command]
+pub fn create_choke_group(
+    name: String,
+    crossfade: Option<f32>,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(SoundManagerError::InvalidChokeGroupName);
+    }
+
+    if !ChokeGroup::is_valid_crossfade(crossfade) {
+        return Err(SoundManagerError::InvalidCrossfade);
+    }
+
+    let mut choke_groups = state.choke_groups.lock().unwrap();
+    if choke_groups.iter().any(|group| group.name == name) {
+        return Err(SoundManagerError::ChokeGroupAlreadyExists(name));
+    }
+
+    choke_groups.push(ChokeGroup::new(name, crossfade));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn rename_choke_group(
+    name: String,
+    new_name: String,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let new_name = new_name.trim().to_string();
+    if new_name.is_empty() {
+        return Err(SoundManagerError::InvalidChokeGroupName);
+    }
+
+    let mut soundbites = state.soundbites.lock().unwrap();
+    let mut choke_groups = state.choke_groups.lock().unwrap();
+    if choke_groups.iter().any(|group| group.name == new_name) {
+        return Err(SoundManagerError::ChokeGroupAlreadyExists(new_name));
+    }
+
+    match choke_groups.iter_mut().find(|group| group.name == name) {
+        Some(group) => {
+            group.name = new_name.clone();
+            soundbites.iter_mut()
+                .filter(|soundbite| soundbite.data.choke_group.as_ref() == Some(&name))
+                .for_each(|soundbite| soundbite.data.choke_group = Some(new_name.clone()));
+            Ok(())
+        },
+        None => Err(SoundManagerError::ChokeGroupNotFound(name)),
+    }
+}
+
+//Members of the removed group play freely again
+#[tauri::command]
+pub fn remove_choke_group(
+    name: String,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let mut soundbites = state.soundbites.lock().unwrap();
+    let mut choke_groups = state.choke_groups.lock().unwrap();
+    match choke_groups.iter().position(|group| group.name == name) {
+        Some(index) => {
+            choke_groups.remove(index);
+            soundbites.iter_mut()
+                .filter(|soundbite| soundbite.data.choke_group.as_ref() == Some(&name))
+                .for_each(|soundbite| soundbite.data.choke_group = None);
+            Ok(())
+        },
+        None => Err(SoundManagerError::ChokeGroupNotFound(name)),
+    }
+}
+
+#[tauri::command]
+pub fn set_choke_group_crossfade(
+    name: String,
+    crossfade: Option<f32>,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    if !ChokeGroup::is_valid_crossfade(crossfade) {
+        return Err(SoundManagerError::InvalidCrossfade);
+    }
+
+    let mut choke_groups = state.choke_groups.lock().unwrap();
+    match choke_groups.iter_mut().find(|group| group.name == name) {
+        Some(group) => {
+            group.crossfade = crossfade;
+            Ok(())
+        },
+        None => Err(SoundManagerError::ChokeGroupNotFound(name)),
+    }
+}
+
+#[tauri::command]
+pub fn set_soundbite_choke_group(
+    name: String,
+    group: Option<String>,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let mut soundbites = state.soundbites.lock().unwrap();
+    if let Some(group) = &group {
+        if !state.choke_groups.lock().unwrap().iter().any(|other| other.name == *group) {
+            return Err(SoundManagerError::ChokeGroupNotFound(group.clone()));
+        }
+    }
+
+    match soundbites.iter_mut().find(|soundbite| soundbite.data.name == name) {
+        Some(soundbite) => {
+            soundbite.data.choke_group = group;
+            Ok(())
+        },
+        None => Err(SoundManagerError::SoundbiteNotFound(name)),
+    }
+}
+
+#[tauri::command]
+pub fn add_soundbite_tag(
+    name: String,
+    tag: String,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let tag = tag.trim().to_string();
+    if tag.is_empty() {
+        return Err(SoundManagerError::InvalidTag);
+    }
+
+    let mut soundbites = state.soundbites.lock().unwrap();
+    match soundbites.iter_mut().find(|soundbite| soundbite.data.name == name) {
+        Some(soundbite) => {
+            if !soundbite.data.has_tag(&tag) {
+                soundbite.data.tags.push(tag);
+            }
+            Ok(())
+        },
+        None => Err(SoundManagerError::SoundbiteNotFound(name)),
+    }
+}
+
+#[tauri::command]
+pub fn remove_soundbite_tag(
+    name: String,
+    tag: String,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let mut soundbites = state.soundbites.lock().unwrap();
+    match soundbites.iter_mut().find(|soundbite| soundbite.data.name == name) {
+        Some(soundbite) => {
+            soundbite.data.tags.retain(|t| !t.eq_ignore_ascii_case(&tag));
+            Ok(())
+        },
+        None => Err(SoundManagerError::SoundbiteNotFound(name)),
+    }
+}
+
+#[tauri::command]
+pub fn get_tags(
+    state: State<'_, SoundState>
+) -> Vec<String> {
+    let soundbites = state.soundbites.lock().unwrap();
+    let mut tags: Vec<String> = Vec::new();
+    for tag in soundbites.iter().flat_map(|soundbite| soundbite.data.tags.iter()) {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.clone());
+        }
+    }
+    tags.sort_by_key(|tag| tag.to_lowercase());
+    tags
+}
+
+#[tauri::command]
+pub fn get_profiles(
+    state: State<'_, SoundState>
+) -> Vec<ProfileInfo> {
+    state.profiles.lock().unwrap().info()
+}
+
+//The new profile starts with a copy of copy_from bindings and options
+#[tauri::command]
+pub fn create_profile(
+    name: String,
+    copy_from: Option<String>,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(SoundManagerError::InvalidProfileName);
+    }
+
+    let soundbites_keytasks = state.soundbites_keytasks.lock().unwrap();
+    let mut profiles = state.profiles.lock().unwrap();
+    if profiles.get(&name).is_some() {
+        return Err(SoundManagerError::ProfileAlreadyExists(name));
+    }
+
+    let profile = match copy_from {
+        Some(copy_from) => {
+            let mut profile = match profiles.get(&copy_from) {
+                Some(profile) => profile.clone(),
+                None => return Err(SoundManagerError::ProfileNotFound(copy_from)),
+            };
+            if copy_from == profiles.active {
+                profile.keytasks = soundbites_keytasks.clone();
+            }
+            profile.name = name;
+            profile.switch_keycode = None;
+            profile
+        },
+        None => Profile::new(name),
+    };
+
+    profiles.profiles.push(profile);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn rename_profile(
+    name: String,
+    new_name: String,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let new_name = new_name.trim().to_string();
+    if new_name.is_empty() {
+        return Err(SoundManagerError::InvalidProfileName);
+    }
+
+    let mut profiles = state.profiles.lock().unwrap();
+    if profiles.get(&new_name).is_some() {
+        return Err(SoundManagerError::ProfileAlreadyExists(new_name));
+    }
+
+    if profiles.active == name {
+        profiles.active = new_name.clone();
+        state.settings.lock().unwrap().active_profile = Some(new_name.clone());
+    }
+
+    match profiles.get_mut(&name) {
+        Some(profile) => {
+            profile.name = new_name;
+            Ok(())
+        },
+        None => Err(SoundManagerError::ProfileNotFound(name)),
+    }
+}
+
+#[tauri::command]
+pub fn remove_profile(
+    name: String,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let mut profiles = state.profiles.lock().unwrap();
+    if profiles.active == name {
+        return Err(SoundManagerError::ProfileActive(name));
+    }
+
+    match profiles.profiles.iter().position(|profile| profile.name == name) {
+        Some(index) => {
+            profiles.profiles.remove(index);
+            Ok(())
+        },
+        None => Err(SoundManagerError::ProfileNotFound(name)),
+    }
+}
+
+#[tauri::command]
+pub fn switch_profile(
+    name: String,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    if state.profiles.lock().unwrap().get(&name).is_none() {
+        return Err(SoundManagerError::ProfileNotFound(name));
+    }
+
+    if let Err(err) = state.messages.send(Message::SwitchProfile(name)) {
+        error!("Unable to send command to switch profile [[{:?}]]", err);
+        return Err(SoundManagerError::UnableToSendMessage);
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_profile_master_volume(
+    name: String,
+    volume: Option<f32>,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    if let Some(volume) = volume {
+        if volume <= 0f32 || volume > 200f32 {
+            return Err(SoundManagerError::InvalidVolumeValue);
+        }
+    }
+
+    match state.profiles.lock().unwrap().get_mut(&name) {
+        Some(profile) => {
+            profile.master_volume = volume;
+            Ok(())
+        },
+        None => Err(SoundManagerError::ProfileNotFound(name)),
+    }
+}
+
+#[tauri::command]
+pub fn set_profile_output_device(
+    name: String,
+    device: Option<String>,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    if let Some(device) = &device {
+        if !soundmanager::output_devices().contains(device) {
+            return Err(SoundManagerError::OutputDeviceNotFound(device.clone()));
+        }
+    }
+
+    let mut profiles = state.profiles.lock().unwrap();
+    let is_active = profiles.active == name;
+    match profiles.get_mut(&name) {
+        Some(profile) => profile.output_device = device,
+        None => return Err(SoundManagerError::ProfileNotFound(name)),
+    }
+
+    if is_active {
+        if let Err(err) = state.messages.send(Message::RefreshOutputDevice) {
+            error!("Unable to send command to change output device [[{:?}]]", err);
+            return Err(SoundManagerError::UnableToSendMessage);
+        }
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_profile_switch_keycode(
+    name: String,
+    keytask_code: Option<KeyTaskCode>,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    let soundbites_keytasks = state.soundbites_keytasks.lock().unwrap();
+    let mut profiles = state.profiles.lock().unwrap();
+    if let Some(keytask_code) = keytask_code {
+        //The active profile bindings are the ones in soundbites_keytasks
+        let used = soundbites_keytasks.contains_key(&keytask_code)
+            || profiles.profiles.iter().any(|profile| {
+                (profile.name != name && profile.switch_keycode == Some(keytask_code))
+                || (profile.name != profiles.active && profile.keytasks.contains_key(&keytask_code))
+            })
+            || {
+                let settings = state.settings.lock().unwrap();
+                settings.pause_keycode == Some(keytask_code)
+                    || settings.swallow_escape.keycode == keytask_code
+            };
+        if used {
+            return Err(SoundManagerError::KeyTaskUsed(keytask_code));
+        }
+    }
+
+    match profiles.get_mut(&name) {
+        Some(profile) => {
+            profile.switch_keycode = keytask_code;
+            Ok(())
+        },
+        None => Err(SoundManagerError::ProfileNotFound(name)),
+    }
+}
+
+#[tauri::command]
+pub fn get_output_devices() -> Vec<String> {
+    soundmanager::output_devices()
+}
+
+#[tauri::command]
+pub fn get_settings(
+    state: State<'_, SoundState>
+) -> Settings {
+    state.settings.lock().unwrap().clone()
+}
+
+//Bindings with old key codes that have to be made again
+#[tauri::command]
+pub fn get_dropped_bindings(
+    state: State<'_, SoundState>
+) -> Vec<DroppedBinding> {
+    state.dropped_bindings.as_ref().clone()
+}
+
+#[tauri::command]
+pub fn set_import_limits(
+    max_file_size: u64,
+    max_duration: f32,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    if max_file_size == 0 || max_duration <= 0f32 {
+        return Err(SoundManagerError::InvalidImportLimits);
+    }
+
+    state.settings.lock().unwrap().import_limits = ImportLimits {
+        max_file_size,
+        max_duration,
+    };
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_activation_rules(
+    rules: ActivationRules,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    if !rules.rules.iter().all(|rule| rule.is_valid()) {
+        return Err(SoundManagerError::InvalidActivationRule);
+    }
+
+    key_hook::set_activation_rules(rules.clone());
+    state.settings.lock().unwrap().activation_rules = rules;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_swallow_escape(
+    keytask_code: KeyTaskCode,
+    duration: u64,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    match chord_conflict(keytask_code, &state) {
+        None | Some(ChordConflict::SwallowEscape) => {},
+        Some(_) => return Err(SoundManagerError::KeyTaskUsed(keytask_code)),
+    }
+
+    let escape = SwallowEscape {
+        keycode: keytask_code,
+        duration,
+    };
+    key_hook::set_swallow_escape(escape.clone());
+    state.settings.lock().unwrap().swallow_escape = escape;
+    Ok(())
+}
+
+//Window the rules would be checked against right now, useful to fill in
+// a new rule from the UI
+#[tauri::command]
+pub fn get_focused_window() -> Option<FocusedWindow> {
+    key_hook::focused_window()
+}
+
+#[tauri::command]
+pub fn get_hotkeys_enabled() -> bool {
+    key_hook::hotkeys_enabled()
+}
+
+//Returns the new state
+#[tauri::command]
+pub fn toggle_hotkeys(
+    state: State<'_, SoundState>
+) -> bool {
+    soundmanager::toggle_hotkeys(&state.events)
+}
+
+#[tauri::command]
+pub fn set_pause_keycode(
+    keytask_code: Option<KeyTaskCode>,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    if let Some(keytask_code) = keytask_code {
+        match chord_conflict(keytask_code, &state) {
+            None | Some(ChordConflict::PauseToggle) => {},
+            Some(_) => return Err(SoundManagerError::KeyTaskUsed(keytask_code)),
+        }
+    }
+
+    state.settings.lock().unwrap().pause_keycode = keytask_code;
+    Ok(())
+}
+
+//Restarts the server with the new settings, a token is generated on
+// first enable
+#[tauri::command]
+pub fn set_api_settings(
+    enabled: bool,
+    port: u16,
+    allow_lan: bool,
+    state: State<'_, SoundState>
+) -> Result<ApiSettings, SoundManagerError> {
+    let mut api_settings = state.settings.lock().unwrap().api.clone();
+    api_settings.enabled = enabled;
+    api_settings.port = port;
+    api_settings.allow_lan = allow_lan;
+    if api_settings.token.is_empty() {
+        api_settings.token = api::generate_token();
+    }
+
+    let result = api::restart(&state, &api_settings);
+    //Kept even when the server failed to start, so it's retried on launch
+    state.settings.lock().unwrap().api = api_settings.clone();
+    result.map(|_| api_settings)
+}
+
+//Invalidates the old token, returns the new one
+#[tauri::command]
+pub fn regenerate_api_token(
+    state: State<'_, SoundState>
+) -> Result<String, SoundManagerError> {
+    let mut api_settings = state.settings.lock().unwrap().api.clone();
+    api_settings.token = api::generate_token();
+
+    state.settings.lock().unwrap().api = api_settings.clone();
+    api::restart(&state, &api_settings)?;
+    Ok(api_settings.token)
+}
+
+#[tauri::command]
+pub fn set_osc_settings(
+    settings: OscSettings,
+    state: State<'_, SoundState>
+) -> Result<(), SoundManagerError> {
+    osc::restart(&state, &settings)?;
+    state.settings.lock().unwrap().osc = settings;
+    Ok(())
 }
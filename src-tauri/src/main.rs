@@ -27,51 +27,155 @@ use soundmanager::{
     SoundbitesKeyTasks,
     SOUNDBITES_FILE,
     KEYTASKS_FILE,
-    ROOT_FOLDER, key_task::KeyTaskCode,
+    SETTINGS_FILE,
+    CATEGORIES_FILE,
+    PROFILES_FILE,
+    TRIGGERS_FILE,
+    PLAYLISTS_FILE,
+    CHOKE_GROUPS_FILE,
+    VARIANT_SETS_FILE,
+    SCHEDULES_FILE,
+    MACROS_FILE,
+    Categories,
+    ROOT_FOLDER, key_task::{self, KeyTaskCode, KeyBinding, DroppedBinding},
+    events::{Event, EventBus},
+    api,
+    instance,
+    ipc,
+    osc,
+    settings::{Settings, SwallowEscape},
+    profile::{Profile, Profiles},
+    queue::{Playlist, Playlists, Queue},
+    group::{ChokeGroup, ChokeGroups},
+    variant::{VariantSet, VariantSets},
+    macros::{Macro, Macros},
+    schedule::{Schedule, Scheduler},
+    trigger::{TriggerBindings, TriggerCode},
 };
 
 mod commands;
 use commands::*;
 
+mod cli;
+
 fn main() {
+    let (root_folder, logs_folder) = generate_app_folders();
+    if let Ok(folder) = ROOT_FOLDER.lock().as_mut() {
+        folder.clone_from(&root_folder);
+    }
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = cli::run(&args) {
+        std::process::exit(code);
+    }
+
+    //Logged once logging is set up
+    let lock_error = match instance::acquire_lock() {
+        Ok(true) => None,
+        Ok(false) => std::process::exit(cli::forward(&args)),
+        Err(err) => Some(err),
+    };
+
     tauri::Builder::default()
-        .setup(|app| {
+        .setup(move |app| {
             #[cfg(debug_assertions)]
             {
                 let window = app.get_window("main").unwrap();
                 window.open_devtools();
             }
 
-            let (root_folder, logs_folder) = generate_app_folders();
-            if let Ok(folder) = ROOT_FOLDER.lock().as_mut() {
-                folder.clone_from(&root_folder);
-            }
-
             init_logging(&logs_folder);
 
+            if let Some(err) = &lock_error {
+                error!(
+                    "Unable to lock {}, another instance may be running [[{:?}]]",
+                    instance::LOCK_FILE,
+                    err
+                );
+            }
+
             trace!("App init");
             trace!("Root folder: {}", root_folder.display());
             trace!("Logs folder: {}", logs_folder.display());
 
-            let soundbites = Arc::new(Mutex::new(load_soundbites()));
-            let soundbites_keytasks = Arc::new(Mutex::new(load_keytasks()));
+            let mut dropped_bindings = Vec::new();
+            let settings = load_settings(&mut dropped_bindings);
+            let api_settings = settings.api.clone();
+            let osc_settings = settings.osc.clone();
+            let soundbites = load_soundbites();
+            let keytasks = load_keytasks(&soundbites, &mut dropped_bindings);
+            let profiles = load_profiles(
+                settings.active_profile.clone(),
+                &soundbites,
+                &mut dropped_bindings
+            );
 
             let (messages_tx, messages_rx) = unbounded();
             let (responses_tx, responses_rx) = unbounded();
 
-            app.manage(SoundState::new(
-                soundbites.clone(),
-                soundbites_keytasks.clone(),
-                messages_tx,
-                responses_rx,
-            ));
+            let state = SoundState {
+                soundbites: Arc::new(Mutex::new(soundbites)),
+                soundbites_keytasks: Arc::new(Mutex::new(keytasks)),
+                trigger_bindings: Arc::new(Mutex::new(load_triggers())),
+                categories: Arc::new(Mutex::new(load_categories())),
+                choke_groups: Arc::new(Mutex::new(load_choke_groups())),
+                variant_sets: Arc::new(Mutex::new(load_variant_sets())),
+                macros: Arc::new(Mutex::new(load_macros())),
+                profiles: Arc::new(Mutex::new(profiles)),
+                settings: Arc::new(Mutex::new(settings)),
+                queue: Arc::new(Mutex::new(Queue::default())),
+                playlists: Arc::new(Mutex::new(load_playlists())),
+                scheduler: Arc::new(Mutex::new(Scheduler::new(load_schedules()))),
+                events: EventBus::new(),
+                dropped_bindings: Arc::new(dropped_bindings),
+
+                messages: messages_tx,
+                responses: responses_rx,
+            };
+            app.manage(state.clone());
+
+            if let Err(err) = api::restart(&state, &api_settings) {
+                error!("API server not started [[{:?}]]", err);
+            }
+
+            if let Err(err) = osc::restart(&state, &osc_settings) {
+                error!("OSC server not started [[{:?}]]", err);
+            }
+
+            #[cfg(target_os = "linux")]
+            if let Err(err) = soundmanager::mpris::start(&state) {
+                error!("MPRIS interface not registered [[{:?}]]", err);
+            }
+
+            if let Err(err) = ipc::start_server(state.clone()) {
+                error!("IPC server not started, the command line won't work [[{:?}]]", err);
+            }
+
+            instance::handle_args(&state, &args);
+
+            let app_handle = app.handle();
+            let events_rx = state.events.subscribe();
+            thread::spawn(move || {
+                for event in events_rx.iter() {
+                    if let Event::SecondInstance { .. } = event {
+                        if let Some(window) = app_handle.get_window("main") {
+                            let _ = window.unminimize();
+                            let _ = window.show();
+                            let _ = window.set_focus();
+                        }
+                    }
+
+                    if let Err(err) = app_handle.emit_all(event.name(), event.clone()) {
+                        error!("Unable to emit event {} [[{:?}]]", event.name(), err);
+                    }
+                }
+            });
 
             thread::spawn(move || {
                 SoundManager::new(
                     messages_rx,
                     responses_tx,
-                    soundbites,
-                    soundbites_keytasks
+                    &state,
                 ).run();
             });
 
@@ -82,21 +186,117 @@ fn main() {
                 let state: State<SoundState> = event.window().state();
                 save_soundbites(&state);
                 save_keytasks(&state);
+                save_triggers(&state);
+                save_categories(&state);
+                save_choke_groups(&state);
+                save_variant_sets(&state);
+                save_macros(&state);
+                save_profiles(&state);
+                save_playlists(&state);
+                save_schedules(&state);
+                save_settings(&state);
             }
             _ => {}
         })
         .invoke_handler(tauri::generate_handler![
             add_soundbite,
+            import_soundbite,
+            import_folder,
+            set_watch_folder,
             remove_soundbite,
+            export_soundbite,
+            export_soundbites,
+            export_pack,
+            import_pack,
             play_soundbite,
             stop_soundbite,
+            get_variant_sets,
+            save_variant_set,
+            rename_variant_set,
+            remove_variant_set,
+            play_variant_set,
+            set_variant_set_keycode,
+            set_variant_set_keytask_options,
+            remove_variant_set_keycode,
+            set_variant_set_trigger,
+            get_macros,
+            save_macro,
+            rename_macro,
+            remove_macro,
+            run_macro,
+            stop_macro,
+            set_macro_keycode,
+            set_macro_keytask_options,
+            remove_macro_keycode,
+            set_macro_trigger,
+            get_schedules,
+            add_schedule,
+            remove_schedule,
+            set_schedule_enabled,
+            get_queue,
+            enqueue_soundbite,
+            queue_next,
+            queue_previous,
+            clear_queue,
+            set_queue_transition,
+            get_playlists,
+            save_playlist,
+            remove_playlist,
+            play_playlist,
             set_name,
             set_volume,
             set_speed,
             set_keytask_code,
             remove_keytask_code,
+            set_keytask_swallow,
+            set_keytask_trigger,
+            capture_keytask_code,
+            cancel_capture,
+            get_triggers,
+            set_trigger,
+            remove_trigger,
+            learn_trigger,
+            cancel_learn_trigger,
             get_soundbite,
             get_soundbites,
+            list_soundbites,
+            search_soundbites,
+            get_categories,
+            create_category,
+            rename_category,
+            remove_category,
+            move_category,
+            set_soundbite_category,
+            get_choke_groups,
+            create_choke_group,
+            rename_choke_group,
+            remove_choke_group,
+            set_choke_group_crossfade,
+            set_soundbite_choke_group,
+            add_soundbite_tag,
+            remove_soundbite_tag,
+            get_tags,
+            get_profiles,
+            create_profile,
+            rename_profile,
+            remove_profile,
+            switch_profile,
+            set_profile_master_volume,
+            set_profile_output_device,
+            set_profile_switch_keycode,
+            get_output_devices,
+            get_settings,
+            get_dropped_bindings,
+            set_import_limits,
+            set_activation_rules,
+            get_focused_window,
+            set_swallow_escape,
+            get_hotkeys_enabled,
+            toggle_hotkeys,
+            set_pause_keycode,
+            set_api_settings,
+            regenerate_api_token,
+            set_osc_settings,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -187,7 +387,51 @@ fn load_soundbites() -> Soundbites {
     soundbites
 }
 
-fn load_keytasks() -> SoundbitesKeyTasks {
+fn load_triggers() -> TriggerBindings {
+    let mut triggers = TriggerBindings::new();
+
+    if let Ok(root_folder) = ROOT_FOLDER.lock().as_ref() {
+        if let Ok(file) = File::open(root_folder.join(TRIGGERS_FILE)) {
+            let reader = BufReader::new(file);
+
+            for line in reader.lines().map_while(Result::ok) {
+                match serde_json::from_str::<(TriggerCode, KeyBinding)>(&line) {
+                    Ok((trigger, binding)) => {
+                        triggers.insert(trigger, binding);
+                    },
+                    Err(err) => error!("Unable to read trigger [[{:?}]]", err),
+                }
+            }
+        }
+    }
+
+    triggers
+}
+
+//Old key codes more than one chord packed to are left out and reported, so
+// they can be bound again
+fn migrate_keycode(
+    code: KeyTaskCode,
+    bound_to: impl FnOnce() -> String,
+    dropped_bindings: &mut Vec<DroppedBinding>
+) -> Option<KeyTaskCode> {
+    let migrated = key_task::migrate_keycode(code);
+    if migrated.is_none() {
+        let bound_to = bound_to();
+        error!("Binding of {} dropped, key code {:#06X} can't be migrated", bound_to, code);
+        dropped_bindings.push(DroppedBinding {
+            keycode: code,
+            bound_to,
+        });
+    }
+
+    migrated
+}
+
+fn load_keytasks(
+    soundbites: &Soundbites,
+    dropped_bindings: &mut Vec<DroppedBinding>
+) -> SoundbitesKeyTasks {
     let mut keytasks = SoundbitesKeyTasks::new();
 
     if let Ok(root_folder) = ROOT_FOLDER.lock().as_ref() {
@@ -196,8 +440,14 @@ fn load_keytasks() -> SoundbitesKeyTasks {
 
             for line in reader.lines() {
                 if let Ok(line) = line {
-                    if let Ok(keytask) = serde_json::from_str::<(KeyTaskCode, usize)>(&line) {
-                        keytasks.insert(keytask.0, keytask.1);
+                    if let Ok((code, binding)) = serde_json::from_str::<(KeyTaskCode, KeyBinding)>(&line) {
+                        if let Some(code) = migrate_keycode(
+                            code,
+                            || soundmanager::binding_target_name(soundbites, &binding.target),
+                            dropped_bindings
+                        ) {
+                            keytasks.insert(code, binding);
+                        }
                     }
                 }
             }
@@ -207,6 +457,190 @@ fn load_keytasks() -> SoundbitesKeyTasks {
     keytasks
 }
 
+fn load_categories() -> Categories {
+    let mut categories = Categories::new();
+
+    if let Ok(root_folder) = ROOT_FOLDER.lock().as_ref() {
+        if let Ok(file) = File::open(root_folder.join(CATEGORIES_FILE)) {
+            let reader = BufReader::new(file);
+
+            for line in reader.lines() {
+                if let Ok(line) = line {
+                    if let Ok(category) = serde_json::from_str::<String>(&line) {
+                        categories.push(category);
+                    }
+                }
+            }
+        }
+    }
+
+    categories
+}
+
+fn load_choke_groups() -> ChokeGroups {
+    let mut choke_groups = ChokeGroups::new();
+
+    if let Ok(root_folder) = ROOT_FOLDER.lock().as_ref() {
+        if let Ok(file) = File::open(root_folder.join(CHOKE_GROUPS_FILE)) {
+            let reader = BufReader::new(file);
+
+            for line in reader.lines().map_while(Result::ok) {
+                match serde_json::from_str::<ChokeGroup>(&line) {
+                    Ok(group) => choke_groups.push(group),
+                    Err(err) => error!("Unable to read choke group [[{:?}]]", err),
+                }
+            }
+        }
+    }
+
+    choke_groups
+}
+
+fn load_variant_sets() -> VariantSets {
+    let mut variant_sets = VariantSets::new();
+
+    if let Ok(root_folder) = ROOT_FOLDER.lock().as_ref() {
+        if let Ok(file) = File::open(root_folder.join(VARIANT_SETS_FILE)) {
+            let reader = BufReader::new(file);
+
+            for line in reader.lines().map_while(Result::ok) {
+                match serde_json::from_str::<VariantSet>(&line) {
+                    Ok(variant_set) => variant_sets.push(variant_set),
+                    Err(err) => error!("Unable to read variant set [[{:?}]]", err),
+                }
+            }
+        }
+    }
+
+    variant_sets
+}
+
+fn load_macros() -> Macros {
+    let mut macros = Macros::new();
+
+    if let Ok(root_folder) = ROOT_FOLDER.lock().as_ref() {
+        if let Ok(file) = File::open(root_folder.join(MACROS_FILE)) {
+            let reader = BufReader::new(file);
+
+            for line in reader.lines().map_while(Result::ok) {
+                match serde_json::from_str::<Macro>(&line) {
+                    Ok(macro_) => macros.push(macro_),
+                    Err(err) => error!("Unable to read macro [[{:?}]]", err),
+                }
+            }
+        }
+    }
+
+    macros
+}
+
+//kdata.dat keeps holding the bindings of the active profile, the ones in
+// pdata.dat for it are only a copy
+fn load_profiles(
+    active: Option<String>,
+    soundbites: &Soundbites,
+    dropped_bindings: &mut Vec<DroppedBinding>
+) -> Profiles {
+    let mut profiles = Vec::new();
+
+    if let Ok(root_folder) = ROOT_FOLDER.lock().as_ref() {
+        if let Ok(file) = File::open(root_folder.join(PROFILES_FILE)) {
+            let reader = BufReader::new(file);
+
+            for line in reader.lines() {
+                if let Ok(line) = line {
+                    if let Ok(mut profile) = serde_json::from_str::<Profile>(&line) {
+                        let name = profile.name.clone();
+                        profile.keytasks = profile.keytasks.into_iter()
+                            .filter_map(|(code, binding)| migrate_keycode(
+                                code,
+                                || format!(
+                                    "{} in profile {}",
+                                    soundmanager::binding_target_name(soundbites, &binding.target),
+                                    name
+                                ),
+                                dropped_bindings
+                            ).map(|code| (code, binding)))
+                            .collect();
+                        profile.switch_keycode = profile.switch_keycode.and_then(|code| migrate_keycode(
+                            code,
+                            || format!("switching to profile {}", name),
+                            dropped_bindings
+                        ));
+                        profiles.push(profile);
+                    }
+                }
+            }
+        }
+    }
+
+    Profiles::new(profiles, active)
+}
+
+fn load_playlists() -> Playlists {
+    let mut playlists = Playlists::new();
+
+    if let Ok(root_folder) = ROOT_FOLDER.lock().as_ref() {
+        if let Ok(file) = File::open(root_folder.join(PLAYLISTS_FILE)) {
+            let reader = BufReader::new(file);
+
+            for line in reader.lines().map_while(Result::ok) {
+                match serde_json::from_str::<Playlist>(&line) {
+                    Ok(playlist) => playlists.push(playlist),
+                    Err(err) => error!("Unable to read playlist [[{:?}]]", err),
+                }
+            }
+        }
+    }
+
+    playlists
+}
+
+fn load_schedules() -> Vec<Schedule> {
+    let mut schedules = Vec::new();
+
+    if let Ok(root_folder) = ROOT_FOLDER.lock().as_ref() {
+        if let Ok(file) = File::open(root_folder.join(SCHEDULES_FILE)) {
+            let reader = BufReader::new(file);
+
+            for line in reader.lines().map_while(Result::ok) {
+                match serde_json::from_str::<Schedule>(&line) {
+                    Ok(schedule) => schedules.push(schedule),
+                    Err(err) => error!("Unable to read schedule [[{:?}]]", err),
+                }
+            }
+        }
+    }
+
+    schedules
+}
+
+fn load_settings(dropped_bindings: &mut Vec<DroppedBinding>) -> Settings {
+    if let Ok(root_folder) = ROOT_FOLDER.lock().as_ref() {
+        if let Ok(file) = File::open(root_folder.join(SETTINGS_FILE)) {
+            match serde_json::from_reader::<_, Settings>(BufReader::new(file)) {
+                Ok(mut settings) => {
+                    settings.pause_keycode = settings.pause_keycode.and_then(|code| migrate_keycode(
+                        code,
+                        || "pausing hotkeys".to_string(),
+                        dropped_bindings
+                    ));
+                    //Back to the default chord rather than none
+                    settings.swallow_escape.keycode = migrate_keycode(
+                        settings.swallow_escape.keycode,
+                        || "swallow escape".to_string(),
+                        dropped_bindings
+                    ).unwrap_or_else(|| SwallowEscape::default().keycode);
+                    return settings;
+                },
+                Err(err) => error!("Unable to read settings, using defaults [[{:?}]]", err),
+            }
+        }
+    }
+
+    Settings::default()
+}
+
 fn save_soundbites(state: &State<SoundState>) {
     let soundbites = state.soundbites.lock().unwrap();
     
@@ -217,7 +651,7 @@ fn save_soundbites(state: &State<SoundState>) {
         for soundbite in soundbites.iter() {
             match serde_json::to_string(&soundbite.data) {
                 Ok(string) => {
-                    writer.write(string.as_bytes()).map_err(|err|
+                    writeln!(writer, "{}", string).map_err(|err|
                         error!(
                             "Unable to save soundbite named {} [[{:?}]]",
                             soundbite.data.name,
@@ -239,6 +673,34 @@ fn save_soundbites(state: &State<SoundState>) {
     }
 }
 
+fn save_triggers(state: &State<SoundState>) {
+    let trigger_bindings = state.trigger_bindings.lock().unwrap();
+
+    if let Ok(root_folder) = ROOT_FOLDER.lock().as_ref() {
+        let file = File::create(root_folder.join(TRIGGERS_FILE)).unwrap();
+        let mut writer = BufWriter::new(file);
+
+        for trigger in trigger_bindings.iter() {
+            match serde_json::to_string(&trigger) {
+                Ok(string) => {
+                    if let Err(err) = writeln!(writer, "{}", string) {
+                        error!("Unable to save trigger {} [[{:?}]]", trigger.0.label(), err);
+                    }
+                },
+                Err(err) => error!(
+                    "Unable to write trigger {} on file [[{:?}]]",
+                    trigger.0.label(),
+                    err
+                ),
+            }
+        }
+
+        if let Err(err) = writer.flush() {
+            error!("Unable to flush buffer to save triggers [[{:?}]]", err);
+        }
+    }
+}
+
 fn save_keytasks(state: &State<SoundState>) {
     let soundbites_keytasks = state.soundbites_keytasks.lock().unwrap();
 
@@ -249,22 +711,249 @@ fn save_keytasks(state: &State<SoundState>) {
         for keytask in soundbites_keytasks.iter() {
             match serde_json::to_string(&keytask) {
                 Ok(string) => {
-                    writer.write(string.as_bytes()).map_err(|err|
+                    writeln!(writer, "{}", string).map_err(|err|
                         error!(
-                            "Unable to save keytask {} - {} [[{:?}]]",
+                            "Unable to save keytask {} - {:?} [[{:?}]]",
                             keytask.0,
-                            keytask.1,
+                            keytask.1.target,
                             err
                         )
                     ).unwrap();
                 },
                 Err(err) => error!(
-                    "Unable to write keytask {} - {} on file [[{:?}]]",
+                    "Unable to write keytask {} - {:?} on file [[{:?}]]",
                     keytask.0,
-                    keytask.1,
+                    keytask.1.target,
+                    err
+                ),
+            }
+        }
+
+        if let Err(err) = writer.flush() {
+            error!("Unable to flush buffer to save keytasks [[{:?}]]", err);
+        }
+    }
+}
+
+fn save_categories(state: &State<SoundState>) {
+    let categories = state.categories.lock().unwrap();
+
+    if let Ok(root_folder) = ROOT_FOLDER.lock().as_ref() {
+        let file = File::create(root_folder.join(CATEGORIES_FILE)).unwrap();
+        let mut writer = BufWriter::new(file);
+
+        for category in categories.iter() {
+            match serde_json::to_string(category) {
+                Ok(string) => {
+                    writeln!(writer, "{}", string).map_err(|err|
+                        error!(
+                            "Unable to save category named {} [[{:?}]]",
+                            category,
+                            err
+                        )
+                    ).unwrap();
+                },
+                Err(err) => error!(
+                    "Unable to write category named {} on file [[{:?}]]",
+                    category,
+                    err
+                ),
+            }
+        }
+
+        if let Err(err) = writer.flush() {
+            error!("Unable to flush buffer to save categories [[{:?}]]", err);
+        }
+    }
+}
+
+fn save_choke_groups(state: &State<SoundState>) {
+    let choke_groups = state.choke_groups.lock().unwrap();
+
+    if let Ok(root_folder) = ROOT_FOLDER.lock().as_ref() {
+        let file = File::create(root_folder.join(CHOKE_GROUPS_FILE)).unwrap();
+        let mut writer = BufWriter::new(file);
+
+        for group in choke_groups.iter() {
+            match serde_json::to_string(group) {
+                Ok(string) => {
+                    if let Err(err) = writeln!(writer, "{}", string) {
+                        error!("Unable to save choke group named {} [[{:?}]]", group.name, err);
+                    }
+                },
+                Err(err) => error!(
+                    "Unable to write choke group named {} on file [[{:?}]]",
+                    group.name,
+                    err
+                ),
+            }
+        }
+
+        if let Err(err) = writer.flush() {
+            error!("Unable to flush buffer to save choke groups [[{:?}]]", err);
+        }
+    }
+}
+
+fn save_variant_sets(state: &State<SoundState>) {
+    let variant_sets = state.variant_sets.lock().unwrap();
+
+    if let Ok(root_folder) = ROOT_FOLDER.lock().as_ref() {
+        let file = File::create(root_folder.join(VARIANT_SETS_FILE)).unwrap();
+        let mut writer = BufWriter::new(file);
+
+        for variant_set in variant_sets.iter() {
+            match serde_json::to_string(variant_set) {
+                Ok(string) => {
+                    if let Err(err) = writeln!(writer, "{}", string) {
+                        error!("Unable to save variant set named {} [[{:?}]]", variant_set.name, err);
+                    }
+                },
+                Err(err) => error!(
+                    "Unable to write variant set named {} on file [[{:?}]]",
+                    variant_set.name,
+                    err
+                ),
+            }
+        }
+
+        if let Err(err) = writer.flush() {
+            error!("Unable to flush buffer to save variant sets [[{:?}]]", err);
+        }
+    }
+}
+
+fn save_macros(state: &State<SoundState>) {
+    let macros = state.macros.lock().unwrap();
+
+    if let Ok(root_folder) = ROOT_FOLDER.lock().as_ref() {
+        let file = File::create(root_folder.join(MACROS_FILE)).unwrap();
+        let mut writer = BufWriter::new(file);
+
+        for macro_ in macros.iter() {
+            match serde_json::to_string(macro_) {
+                Ok(string) => {
+                    if let Err(err) = writeln!(writer, "{}", string) {
+                        error!("Unable to save macro named {} [[{:?}]]", macro_.name, err);
+                    }
+                },
+                Err(err) => error!(
+                    "Unable to write macro named {} on file [[{:?}]]",
+                    macro_.name,
+                    err
+                ),
+            }
+        }
+
+        if let Err(err) = writer.flush() {
+            error!("Unable to flush buffer to save macros [[{:?}]]", err);
+        }
+    }
+}
+
+fn save_profiles(state: &State<SoundState>) {
+    let soundbites_keytasks = state.soundbites_keytasks.lock().unwrap();
+    let mut profiles = state.profiles.lock().unwrap();
+    profiles.active_mut().keytasks = soundbites_keytasks.clone();
+
+    if let Ok(root_folder) = ROOT_FOLDER.lock().as_ref() {
+        let file = File::create(root_folder.join(PROFILES_FILE)).unwrap();
+        let mut writer = BufWriter::new(file);
+
+        for profile in profiles.profiles.iter() {
+            match serde_json::to_string(profile) {
+                Ok(string) => {
+                    writeln!(writer, "{}", string).map_err(|err|
+                        error!(
+                            "Unable to save profile named {} [[{:?}]]",
+                            profile.name,
+                            err
+                        )
+                    ).unwrap();
+                },
+                Err(err) => error!(
+                    "Unable to write profile named {} on file [[{:?}]]",
+                    profile.name,
                     err
                 ),
             }
         }
+
+        if let Err(err) = writer.flush() {
+            error!("Unable to flush buffer to save profiles [[{:?}]]", err);
+        }
+    }
+}
+
+fn save_playlists(state: &State<SoundState>) {
+    let playlists = state.playlists.lock().unwrap();
+
+    if let Ok(root_folder) = ROOT_FOLDER.lock().as_ref() {
+        let file = File::create(root_folder.join(PLAYLISTS_FILE)).unwrap();
+        let mut writer = BufWriter::new(file);
+
+        for playlist in playlists.iter() {
+            match serde_json::to_string(playlist) {
+                Ok(string) => {
+                    if let Err(err) = writeln!(writer, "{}", string) {
+                        error!("Unable to save playlist named {} [[{:?}]]", playlist.name, err);
+                    }
+                },
+                Err(err) => error!(
+                    "Unable to write playlist named {} on file [[{:?}]]",
+                    playlist.name,
+                    err
+                ),
+            }
+        }
+
+        if let Err(err) = writer.flush() {
+            error!("Unable to flush buffer to save playlists [[{:?}]]", err);
+        }
+    }
+}
+
+fn save_schedules(state: &State<SoundState>) {
+    let scheduler = state.scheduler.lock().unwrap();
+
+    if let Ok(root_folder) = ROOT_FOLDER.lock().as_ref() {
+        let file = File::create(root_folder.join(SCHEDULES_FILE)).unwrap();
+        let mut writer = BufWriter::new(file);
+
+        for schedule in scheduler.schedules.iter() {
+            match serde_json::to_string(schedule) {
+                Ok(string) => {
+                    if let Err(err) = writeln!(writer, "{}", string) {
+                        error!("Unable to save schedule named {} [[{:?}]]", schedule.name, err);
+                    }
+                },
+                Err(err) => error!(
+                    "Unable to write schedule named {} on file [[{:?}]]",
+                    schedule.name,
+                    err
+                ),
+            }
+        }
+
+        if let Err(err) = writer.flush() {
+            error!("Unable to flush buffer to save schedules [[{:?}]]", err);
+        }
+    }
+}
+
+fn save_settings(state: &State<SoundState>) {
+    let settings = state.settings.lock().unwrap();
+
+    if let Ok(root_folder) = ROOT_FOLDER.lock().as_ref() {
+        let file = File::create(root_folder.join(SETTINGS_FILE)).unwrap();
+        let mut writer = BufWriter::new(file);
+
+        if let Err(err) = serde_json::to_writer(&mut writer, &*settings) {
+            error!("Unable to write settings on file [[{:?}]]", err);
+        }
+
+        if let Err(err) = writer.flush() {
+            error!("Unable to flush buffer to save settings [[{:?}]]", err);
+        }
     }
 }
@@ -0,0 +1,344 @@
+use std::{
+    io::{Cursor, Read},
+    net::{Ipv4Addr, SocketAddr},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crossbeam::channel::RecvTimeoutError;
+use log::{trace, error};
+use once_cell::sync::Lazy;
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{
+    Serialize,
+    Deserialize
+};
+use serde_json::json;
+use tiny_http::{Header, Method, Request, Response, Server};
+use tungstenite::{
+    handshake::derive_accept_key,
+    protocol::Role,
+    Message as WsMessage,
+    WebSocket,
+};
+
+use super::{
+    control,
+    events::Event,
+    settings::ApiSettings,
+    soundstate::SoundState,
+    utils::SoundManagerError,
+};
+
+//Larger bodies are cut, every endpoint takes a tiny JSON object at most
+const MAX_BODY_SIZE: u64 = 64 * 1024;
+//Pings keep idle WebSockets alive and notice the ones gone away
+const PING_INTERVAL: Duration = Duration::from_secs(10);
+const TOKEN_LENGTH: usize = 32;
+
+type JsonResponse = Response<Cursor<Vec<u8>>>;
+
+#[derive(Deserialize)]
+struct VolumeBody {
+    volume: Option<f32>,
+}
+
+struct ApiServer {
+    server: Arc<Server>,
+    //Cleared on stop, WebSocket threads check it between events
+    running: Arc<AtomicBool>,
+    //Joined on drop so the port is free again for a restart
+    acceptor: Option<JoinHandle<()>>,
+}
+
+impl Drop for ApiServer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        self.server.unblock();
+        if let Some(acceptor) = self.acceptor.take() {
+            let _ = acceptor.join();
+        }
+    }
+}
+
+static API_SERVER: Lazy<Mutex<Option<ApiServer>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn generate_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(TOKEN_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+//Stops the running server, if any, and starts a new one when enabled
+pub fn restart(state: &SoundState, settings: &ApiSettings) -> Result<(), SoundManagerError> {
+    let mut api_server = API_SERVER.lock().unwrap();
+    *api_server = None;
+
+    if !settings.enabled {
+        return Ok(());
+    }
+
+    let address = if settings.allow_lan {
+        Ipv4Addr::UNSPECIFIED
+    } else {
+        Ipv4Addr::LOCALHOST
+    };
+    let server = match Server::http(SocketAddr::from((address, settings.port))) {
+        Ok(server) => Arc::new(server),
+        Err(err) => {
+            error!("Unable to start API server on port {} [[{:?}]]", settings.port, err);
+            return Err(SoundManagerError::ApiUnavailable(err.to_string()));
+        }
+    };
+
+    let running = Arc::new(AtomicBool::new(true));
+    let token = settings.token.clone();
+    let state = state.clone();
+    let acceptor = {
+        let server = server.clone();
+        let running = running.clone();
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                handle_request(request, &token, &state, &running);
+            }
+            trace!("API server stopped");
+        })
+    };
+
+    trace!("API server listening on {}:{}", address, settings.port);
+    *api_server = Some(ApiServer {
+        server,
+        running,
+        acceptor: Some(acceptor),
+    });
+    Ok(())
+}
+
+fn handle_request(
+    mut request: Request,
+    token: &str,
+    state: &SoundState,
+    running: &Arc<AtomicBool>,
+) {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let segments: Vec<String> = path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(percent_decode)
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+    //CORS preflight, lets browser overlays call the API
+    if *request.method() == Method::Options {
+        respond(request, Response::empty(204)
+            .with_header(header("Access-Control-Allow-Methods", "GET, POST, PUT, OPTIONS"))
+            .with_header(header("Access-Control-Allow-Headers", "Authorization, Content-Type")));
+        return;
+    }
+
+    if !is_authorized(&request, query, token) {
+        respond(request, error_response(401, "Invalid or missing token"));
+        return;
+    }
+
+    let mut body = String::new();
+    if let Err(err) = request.as_reader().take(MAX_BODY_SIZE).read_to_string(&mut body) {
+        error!("Unable to read API request body [[{:?}]]", err);
+        respond(request, error_response(400, "Unable to read body"));
+        return;
+    }
+
+    let response = match (request.method(), segments.as_slice()) {
+        (Method::Get, ["api", "events"]) => {
+            upgrade_websocket(request, state.clone(), running.clone());
+            return;
+        },
+        (Method::Get, ["api", "soundbites"]) => json_response(200, &control::list(state)),
+        (Method::Post, ["api", "soundbites", name, "play"]) => {
+            to_response(control::play(state, name))
+        },
+        (Method::Post, ["api", "soundbites", name, "stop"]) => {
+            to_response(control::stop(state, Some(name)))
+        },
+        (Method::Post, ["api", "stop"]) => to_response(control::stop(state, None)),
+        (Method::Put, ["api", "soundbites", name, "volume"]) => {
+            match serde_json::from_str::<VolumeBody>(&body) {
+                Ok(VolumeBody { volume: Some(volume) }) => to_response(
+                    control::set_volume(state, name, volume).map(|_| json!({ "volume": volume }))
+                ),
+                _ => error_response(400, "Expected {\"volume\": number}"),
+            }
+        },
+        (Method::Get, ["api", "volume"]) => {
+            json_response(200, &json!({ "volume": control::master_volume(state) }))
+        },
+        (Method::Put, ["api", "volume"]) => {
+            match serde_json::from_str::<VolumeBody>(&body) {
+                Ok(VolumeBody { volume }) => to_response(
+                    control::set_master_volume(state, volume).map(|_| json!({ "volume": volume }))
+                ),
+                Err(_) => error_response(400, "Expected {\"volume\": number or null}"),
+            }
+        },
+        _ => error_response(404, "Not found"),
+    };
+
+    respond(request, response);
+}
+
+//Status code of the error, the message is its display
+fn error_status(err: SoundManagerError) -> JsonResponse {
+    let status = match err {
+        SoundManagerError::SoundbiteNotFound(_) => 404,
+        SoundManagerError::UnableToSendMessage => 500,
+        _ => 400,
+    };
+    error_response(status, &err.to_string())
+}
+
+fn to_response<T: Serialize>(result: Result<T, SoundManagerError>) -> JsonResponse {
+    match result {
+        Ok(value) => json_response(200, &value),
+        Err(err) => error_status(err),
+    }
+}
+
+//Streams every manager event as a JSON text message, starting with the
+// soundbites already playing
+fn upgrade_websocket(request: Request, state: SoundState, running: Arc<AtomicBool>) {
+    let key = request.headers().iter()
+        .find(|header| header.field.equiv("Sec-WebSocket-Key"))
+        .map(|header| header.value.to_string());
+    let key = match key {
+        Some(key) => key,
+        None => {
+            respond(request, error_response(400, "Expected a WebSocket upgrade"));
+            return;
+        }
+    };
+
+    let response = Response::empty(101)
+        .with_header(header("Upgrade", "websocket"))
+        .with_header(header("Connection", "Upgrade"))
+        .with_header(header("Sec-WebSocket-Accept", &derive_accept_key(key.as_bytes())));
+    let stream = request.upgrade("websocket", response);
+
+    thread::spawn(move || {
+        let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+        let events = state.events.subscribe();
+
+        let playing: Vec<Event> = state.soundbites.lock().unwrap()
+            .iter()
+            .filter(|soundbite| soundbite.is_playing())
+            .map(|soundbite| Event::PlaybackStarted { name: soundbite.data.name.clone() })
+            .collect();
+        for event in playing {
+            if socket.send(event_message(&event)).is_err() {
+                return;
+            }
+        }
+
+        while running.load(Ordering::SeqCst) {
+            let message = match events.recv_timeout(PING_INTERVAL) {
+                Ok(event) => event_message(&event),
+                Err(RecvTimeoutError::Timeout) => WsMessage::Ping(Vec::new()),
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+
+            if let Err(err) = socket.send(message) {
+                trace!("API WebSocket closed [[{:?}]]", err);
+                return;
+            }
+        }
+
+        let _ = socket.close(None);
+        let _ = socket.flush();
+    });
+}
+
+fn event_message(event: &Event) -> WsMessage {
+    match serde_json::to_string(event) {
+        Ok(json) => WsMessage::Text(json),
+        Err(err) => {
+            error!("Unable to serialize event {} [[{:?}]]", event.name(), err);
+            WsMessage::Text(String::new())
+        }
+    }
+}
+
+//Bearer token, or a token query parameter since browsers can't set
+// headers on WebSockets
+fn is_authorized(request: &Request, query: &str, token: &str) -> bool {
+    if token.is_empty() {
+        return false;
+    }
+
+    let bearer = request.headers().iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .and_then(|header| header.value.as_str().strip_prefix("Bearer "))
+        .map(str::trim);
+    let query_token = query.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == "token")
+        .map(|(_, value)| percent_decode(value));
+
+    let matches = |given: Option<&str>| given.is_some_and(|given| constant_time_eq(given, token));
+    matches(bearer) | matches(query_token.as_deref())
+}
+
+//Takes as long whatever the first mismatch, not to leak the token a byte
+// at a time once the API is reachable from the network
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            },
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            },
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field.as_bytes(), value.as_bytes()).unwrap()
+}
+
+fn json_response<T: Serialize>(status: u16, value: &T) -> JsonResponse {
+    let body = serde_json::to_vec(value).unwrap_or_default();
+    Response::from_data(body)
+        .with_status_code(status)
+        .with_header(header("Content-Type", "application/json"))
+}
+
+fn error_response(status: u16, message: &str) -> JsonResponse {
+    json_response(status, &json!({ "error": message }))
+}
+
+fn respond<R: Read>(request: Request, response: Response<R>) {
+    let response = response.with_header(header("Access-Control-Allow-Origin", "*"));
+    if let Err(err) = request.respond(response) {
+        error!("Unable to send API response [[{:?}]]", err);
+    }
+}
@@ -0,0 +1,158 @@
+use std::path::PathBuf;
+
+use log::error;
+use serde::{
+    Serialize,
+    Deserialize
+};
+
+use super::{
+    import,
+    soundstate::{Message, SoundState},
+    utils::{NewSoundbiteError, SoundManagerError, SoundbiteInfo},
+};
+
+//Actions shared by the remote control surfaces, the API and the IPC server
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RemoteSoundbite {
+    #[serde(flatten)]
+    pub info: SoundbiteInfo,
+    pub playing: bool,
+}
+
+pub fn list(state: &SoundState) -> Vec<RemoteSoundbite> {
+    let soundbites = state.soundbites.lock().unwrap();
+    let soundbites_keytasks = state.soundbites_keytasks.lock().unwrap();
+    (0..soundbites.len())
+        .map(|index| RemoteSoundbite {
+            info: SoundbiteInfo::new(&soundbites, &soundbites_keytasks, index),
+            playing: soundbites[index].is_playing(),
+        })
+        .collect()
+}
+
+pub fn play(state: &SoundState, name: &str) -> Result<(), SoundManagerError> {
+    check_exists(state, name)?;
+    send(state, Message::PlaySoundbite(name.to_string()))
+}
+
+//Every playing soundbite when None
+pub fn stop(state: &SoundState, name: Option<&str>) -> Result<(), SoundManagerError> {
+    if let Some(name) = name {
+        check_exists(state, name)?;
+    }
+    send(state, Message::StopSoundbite(name.map(str::to_string)))
+}
+
+//Plays right away when the queue is idle
+pub fn enqueue(state: &SoundState, name: &str) -> Result<(), SoundManagerError> {
+    check_exists(state, name)?;
+    send(state, Message::Enqueue(name.to_string()))
+}
+
+pub fn queue_next(state: &SoundState) -> Result<(), SoundManagerError> {
+    send(state, Message::QueueNext)
+}
+
+pub fn queue_previous(state: &SoundState) -> Result<(), SoundManagerError> {
+    send(state, Message::QueuePrevious)
+}
+
+pub fn clear_queue(state: &SoundState) -> Result<(), SoundManagerError> {
+    send(state, Message::ClearQueue)
+}
+
+pub fn set_volume(state: &SoundState, name: &str, volume: f32) -> Result<(), SoundManagerError> {
+    if volume <= 0f32 || volume > 200f32 {
+        return Err(SoundManagerError::InvalidVolumeValue);
+    }
+
+    let mut soundbites = state.soundbites.lock().unwrap();
+    match soundbites.iter_mut().find(|soundbite| soundbite.data.name == name) {
+        Some(soundbite) => {
+            soundbite.set_volume(volume);
+            Ok(())
+        },
+        None => Err(SoundManagerError::SoundbiteNotFound(name.to_string())),
+    }
+}
+
+//Master volume of the active profile, None when it follows the default
+pub fn master_volume(state: &SoundState) -> Option<f32> {
+    state.profiles.lock().unwrap().active().master_volume
+}
+
+pub fn set_master_volume(state: &SoundState, volume: Option<f32>) -> Result<(), SoundManagerError> {
+    if let Some(volume) = volume {
+        if volume <= 0f32 || volume > 200f32 {
+            return Err(SoundManagerError::InvalidVolumeValue);
+        }
+    }
+
+    state.profiles.lock().unwrap().active_mut().master_volume = volume;
+    Ok(())
+}
+
+//Named after the file when no name is given, returns the name used
+pub fn import(
+    state: &SoundState,
+    path: PathBuf,
+    name: Option<String>
+) -> Result<String, SoundManagerError> {
+    let soundbite_name = match name {
+        Some(name) => name,
+        None => match import::name_from_path(&path) {
+            Some(name) => name,
+            None => return Err(SoundManagerError::NewSoundbiteError(
+                NewSoundbiteError::FileNotFound(path.display().to_string())
+            )),
+        },
+    };
+
+    let limits = state.settings.lock().unwrap().import_limits.clone();
+    import::check_file(&path, &limits)
+        .map_err(SoundManagerError::NewSoundbiteError)?;
+
+    if state.soundbites.lock().unwrap().iter().any(|s| s.data.name == soundbite_name) {
+        return Err(SoundManagerError::NewSoundbiteError(
+            NewSoundbiteError::NameUsed(soundbite_name)
+        ));
+    }
+
+    if let Err(err) = state.messages.send(Message::ImportSoundbite {
+        name: soundbite_name.clone(),
+        path,
+    }) {
+        error!(
+            "Unable to send command to import soundbite named {soundbite_name} [[{:?}]]",
+            err
+        );
+        return Err(SoundManagerError::NewSoundbiteError(
+            NewSoundbiteError::UnableToSendSoundbite(soundbite_name)
+        ));
+    }
+
+    Ok(soundbite_name)
+}
+
+//The manager only logs unknown names, callers want an error back
+fn check_exists(state: &SoundState, name: &str) -> Result<(), SoundManagerError> {
+    let exists = state.soundbites.lock().unwrap()
+        .iter()
+        .any(|soundbite| soundbite.data.name == name);
+    if exists {
+        Ok(())
+    } else {
+        Err(SoundManagerError::SoundbiteNotFound(name.to_string()))
+    }
+}
+
+fn send(state: &SoundState, message: Message) -> Result<(), SoundManagerError> {
+    if let Err(err) = state.messages.send(message) {
+        error!("Unable to send remote command to backend [[{:?}]]", err);
+        return Err(SoundManagerError::UnableToSendMessage);
+    }
+
+    Ok(())
+}
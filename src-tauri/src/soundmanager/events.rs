@@ -0,0 +1,142 @@
+use std::sync::{Arc, Mutex};
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use serde::Serialize;
+
+use super::{
+    pack::PackImportReport,
+    queue::QueueInfo,
+    utils::SoundManagerError,
+};
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event {
+    ImportStarted {
+        name: String,
+        path: String,
+    },
+    ImportProgress {
+        name: String,
+        //Seconds of audio decoded so far
+        decoded: f32,
+        //Between 0 and 1, only when the decoder knows the total duration
+        progress: Option<f32>,
+    },
+    ImportFinished {
+        name: String,
+    },
+    ImportFailed {
+        name: String,
+        error: SoundManagerError,
+    },
+    FolderImportFinished {
+        path: String,
+        imported: Vec<String>,
+    },
+    SoundbiteUpdated {
+        name: String,
+    },
+    SoundbiteRemoved {
+        name: String,
+    },
+    PackImported {
+        path: String,
+        report: PackImportReport,
+    },
+    PackImportFailed {
+        path: String,
+        error: SoundManagerError,
+    },
+    ProfileSwitched {
+        name: String,
+    },
+    HotkeysToggled {
+        enabled: bool,
+    },
+    PlaybackStarted {
+        name: String,
+    },
+    PlaybackStopped {
+        name: String,
+    },
+    //Played to the end, rather than stopped
+    PlaybackFinished {
+        name: String,
+    },
+    //Sent periodically while playing
+    PlaybackPosition {
+        name: String,
+        //Seconds
+        position: f32,
+        duration: f32,
+    },
+    QueueChanged {
+        queue: QueueInfo,
+    },
+    MacroStarted {
+        name: String,
+    },
+    //Ran every step, rather than stopped
+    MacroFinished {
+        name: String,
+    },
+    ScheduleFired {
+        name: String,
+        soundbite: String,
+    },
+    //Another launch forwarded its arguments and exited
+    SecondInstance {
+        args: Vec<String>,
+    },
+}
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::ImportStarted { .. } => "import-started",
+            Event::ImportProgress { .. } => "import-progress",
+            Event::ImportFinished { .. } => "import-finished",
+            Event::ImportFailed { .. } => "import-failed",
+            Event::FolderImportFinished { .. } => "folder-import-finished",
+            Event::SoundbiteUpdated { .. } => "soundbite-updated",
+            Event::SoundbiteRemoved { .. } => "soundbite-removed",
+            Event::PackImported { .. } => "pack-imported",
+            Event::PackImportFailed { .. } => "pack-import-failed",
+            Event::ProfileSwitched { .. } => "profile-switched",
+            Event::HotkeysToggled { .. } => "hotkeys-toggled",
+            Event::PlaybackStarted { .. } => "playback-started",
+            Event::PlaybackStopped { .. } => "playback-stopped",
+            Event::PlaybackFinished { .. } => "playback-finished",
+            Event::PlaybackPosition { .. } => "playback-position",
+            Event::QueueChanged { .. } => "queue-changed",
+            Event::MacroStarted { .. } => "macro-started",
+            Event::MacroFinished { .. } => "macro-finished",
+            Event::ScheduleFired { .. } => "schedule-fired",
+            Event::SecondInstance { .. } => "second-instance",
+        }
+    }
+}
+
+//Fan-out of manager events, every subscriber gets its own copy
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Vec<Sender<Event>>>>,
+}
+
+impl EventBus {
+    pub fn new() -> EventBus {
+        EventBus::default()
+    }
+
+    pub fn subscribe(&self) -> Receiver<Event> {
+        let (tx, rx) = unbounded();
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    pub fn publish(&self, event: Event) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+}
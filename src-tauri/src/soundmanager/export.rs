@@ -0,0 +1,85 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{BufWriter, Seek, Write},
+    path::{Path, PathBuf},
+};
+
+use hound::{WavSpec, WavWriter, SampleFormat};
+
+use super::{
+    soundbite::SoundbiteData,
+    utils::SoundManagerError,
+};
+
+//Baking applies volume and speed the same way the sink does at playback:
+// samples are scaled by volume and speed becomes a different sample rate
+pub fn export_wav(
+    data: &SoundbiteData,
+    path: &Path,
+    baked: bool,
+) -> Result<(), SoundManagerError> {
+    let export_error = || SoundManagerError::UnableToExport(path.display().to_string());
+
+    let file = File::create(path).map_err(|_| export_error())?;
+    write_wav(data, BufWriter::new(file), baked).map_err(|_| export_error())
+}
+
+pub fn write_wav<W: Write + Seek>(
+    data: &SoundbiteData,
+    writer: W,
+    baked: bool,
+) -> hound::Result<()> {
+    let sample_rate = if baked {
+        (data.sample_rate() as f32 * data.speed).round() as u32
+    } else {
+        data.sample_rate()
+    };
+
+    let spec = WavSpec {
+        channels: data.channels(),
+        sample_rate: sample_rate.max(1),
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+
+    let mut writer = WavWriter::new(writer, spec)?;
+    for &sample in data.samples() {
+        let sample = if baked {
+            (sample as f32 * data.volume)
+                .clamp(i16::MIN as f32, i16::MAX as f32) as i16
+        } else {
+            sample
+        };
+
+        writer.write_sample(sample)?;
+    }
+
+    writer.finalize()
+}
+
+pub fn file_name_for(name: &str, extension: &str) -> PathBuf {
+    let name: String = name.chars().map(|c| match c {
+        '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+        c if c.is_control() => '_',
+        c => c,
+    }).collect();
+
+    PathBuf::from(format!("{}.{}", name, extension))
+}
+
+//File names for several soundbites at once, names sanitized to the same
+// file get a numeric suffix instead of overwriting each other
+pub fn unique_file_names(names: &[&str], extension: &str) -> Vec<PathBuf> {
+    //Case-insensitive, as file systems on Windows and macOS are
+    let mut used = HashSet::new();
+    names.iter().map(|name| {
+        let mut path = file_name_for(name, extension);
+        let mut suffix = 1;
+        while !used.insert(path.to_string_lossy().to_lowercase()) {
+            suffix += 1;
+            path = file_name_for(&format!("{} ({})", name, suffix), extension);
+        }
+        path
+    }).collect()
+}
@@ -0,0 +1,48 @@
+use std::time::{Duration, Instant};
+
+//Linear volume ramp of a playing soundbite, the gain is a factor of its
+// own volume
+#[derive(Debug, Clone)]
+pub struct Fade {
+    pub name: String,
+    from: f32,
+    to: f32,
+    started: Instant,
+    duration: Duration,
+    //Stopped once faded, for fade outs
+    pub stop: bool,
+}
+
+impl Fade {
+    pub fn new(name: String, from: f32, to: f32, duration: Duration, stop: bool) -> Fade {
+        Fade {
+            name,
+            from,
+            to,
+            started: Instant::now(),
+            duration,
+            stop,
+        }
+    }
+
+    pub fn fade_in(name: String, duration: Duration) -> Fade {
+        Fade::new(name, 0f32, 1f32, duration, false)
+    }
+
+    pub fn fade_out(name: String, duration: Duration) -> Fade {
+        Fade::new(name, 1f32, 0f32, duration, true)
+    }
+
+    pub fn gain(&self) -> f32 {
+        if self.is_done() {
+            return self.to;
+        }
+
+        let progress = self.started.elapsed().as_secs_f32() / self.duration.as_secs_f32();
+        self.from + (self.to - self.from) * progress
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.started.elapsed() >= self.duration
+    }
+}
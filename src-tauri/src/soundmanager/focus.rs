@@ -0,0 +1,409 @@
+use serde::{
+    Serialize,
+    Deserialize
+};
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct FocusedWindow {
+    //Executable name, e.g. discord.exe
+    pub process: Option<String>,
+    pub title: Option<String>,
+}
+
+//Source of the window currently holding the keyboard focus, swappable so
+// activation rules can be driven without a desktop session
+pub trait FocusProvider: Send + Sync {
+    fn focused_window(&self) -> Option<FocusedWindow>;
+}
+
+//Always reports the same window, None where focus can't be queried
+#[cfg(any(test, not(any(windows, target_os = "linux"))))]
+pub struct StaticFocusProvider(pub Option<FocusedWindow>);
+
+#[cfg(any(test, not(any(windows, target_os = "linux"))))]
+impl FocusProvider for StaticFocusProvider {
+    fn focused_window(&self) -> Option<FocusedWindow> {
+        self.0.clone()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ActivationMode {
+    #[default]
+    Always,
+    //Hotkeys only fire while a matching window is focused
+    AllowList,
+    //Hotkeys fire unless a matching window is focused
+    DenyList,
+}
+
+//Set fields have all to match, process is compared case-insensitively and
+// title is a case-insensitive substring
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct WindowRule {
+    pub process: Option<String>,
+    pub title: Option<String>,
+}
+
+impl WindowRule {
+    //At least one field set, and no blank one as it could never match
+    pub fn is_valid(&self) -> bool {
+        let blank = |field: &Option<String>| field.as_ref()
+            .is_some_and(|value| value.trim().is_empty());
+
+        (self.process.is_some() || self.title.is_some())
+            && !blank(&self.process)
+            && !blank(&self.title)
+    }
+
+    pub fn matches(&self, window: &FocusedWindow) -> bool {
+        if let Some(process) = &self.process {
+            match &window.process {
+                Some(focused) if focused.eq_ignore_ascii_case(process.trim()) => {},
+                _ => return false,
+            }
+        }
+
+        if let Some(title) = &self.title {
+            match &window.title {
+                Some(focused) if focused.to_lowercase().contains(&title.trim().to_lowercase()) => {},
+                _ => return false,
+            }
+        }
+
+        true
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct ActivationRules {
+    pub mode: ActivationMode,
+    pub rules: Vec<WindowRule>,
+}
+
+impl ActivationRules {
+    //An unknown focused window counts as matching no rule
+    pub fn allows(&self, window: Option<&FocusedWindow>) -> bool {
+        let matched = window
+            .map(|window| self.rules.iter().any(|rule| rule.matches(window)))
+            .unwrap_or(false);
+
+        match self.mode {
+            ActivationMode::Always => true,
+            ActivationMode::AllowList => matched,
+            ActivationMode::DenyList => !matched,
+        }
+    }
+}
+
+pub fn default_provider() -> Box<dyn FocusProvider> {
+    #[cfg(windows)]
+    return Box::new(windows::WindowsFocusProvider);
+
+    #[cfg(target_os = "linux")]
+    return Box::new(x11::X11FocusProvider::new());
+
+    #[cfg(not(any(windows, target_os = "linux")))]
+    return Box::new(StaticFocusProvider(None));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(process: &str, title: &str) -> Option<FocusedWindow> {
+        Some(FocusedWindow {
+            process: Some(process.to_string()),
+            title: Some(title.to_string()),
+        })
+    }
+
+    fn rule(process: Option<&str>, title: Option<&str>) -> WindowRule {
+        WindowRule {
+            process: process.map(str::to_string),
+            title: title.map(str::to_string),
+        }
+    }
+
+    fn rules(mode: ActivationMode) -> ActivationRules {
+        ActivationRules {
+            mode,
+            rules: vec![
+                rule(Some("Discord.exe"), None),
+                rule(None, Some("stream")),
+            ],
+        }
+    }
+
+    fn allows(rules: &ActivationRules, provider: &dyn FocusProvider) -> bool {
+        rules.allows(provider.focused_window().as_ref())
+    }
+
+    #[test]
+    fn always_ignores_focus() {
+        let rules = rules(ActivationMode::Always);
+        assert!(allows(&rules, &StaticFocusProvider(window("game.exe", "Game"))));
+        assert!(allows(&rules, &StaticFocusProvider(None)));
+    }
+
+    #[test]
+    fn allow_list() {
+        let rules = rules(ActivationMode::AllowList);
+        assert!(allows(&rules, &StaticFocusProvider(window("discord.exe", "Friends"))));
+        assert!(allows(&rules, &StaticFocusProvider(window("obs64.exe", "OBS - Live Stream"))));
+        assert!(!allows(&rules, &StaticFocusProvider(window("game.exe", "Game"))));
+        //Unknown focus matches no rule
+        assert!(!allows(&rules, &StaticFocusProvider(None)));
+    }
+
+    #[test]
+    fn deny_list() {
+        let rules = rules(ActivationMode::DenyList);
+        assert!(!allows(&rules, &StaticFocusProvider(window("DISCORD.EXE", "Friends"))));
+        assert!(allows(&rules, &StaticFocusProvider(window("game.exe", "Game"))));
+        assert!(allows(&rules, &StaticFocusProvider(None)));
+    }
+
+    #[test]
+    fn every_set_field_has_to_match() {
+        let rule = rule(Some("obs64.exe"), Some("stream"));
+        assert!(rule.matches(&window("obs64.exe", "Stream - Scene").unwrap()));
+        assert!(!rule.matches(&window("obs64.exe", "Recording").unwrap()));
+        assert!(!rule.matches(&window("chrome.exe", "stream").unwrap()));
+        assert!(!rule.matches(&FocusedWindow {
+            process: Some("obs64.exe".to_string()),
+            title: None,
+        }));
+    }
+
+    #[test]
+    fn blank_fields_are_invalid() {
+        assert!(rule(Some("discord.exe"), None).is_valid());
+        assert!(rule(None, Some("stream")).is_valid());
+        assert!(!rule(None, None).is_valid());
+        assert!(!rule(Some(""), None).is_valid());
+        assert!(!rule(Some("discord.exe"), Some("  ")).is_valid());
+    }
+}
+
+#[cfg(windows)]
+pub mod windows {
+    use std::{
+        ffi::OsString,
+        os::windows::ffi::OsStringExt,
+        path::Path,
+    };
+
+    use winapi::{
+        shared::minwindef::{DWORD, FALSE},
+        um::{
+            handleapi::CloseHandle,
+            processthreadsapi::OpenProcess,
+            winbase::QueryFullProcessImageNameW,
+            winnt::PROCESS_QUERY_LIMITED_INFORMATION,
+            winuser::{
+                GetForegroundWindow,
+                GetWindowTextW,
+                GetWindowThreadProcessId,
+            },
+        },
+    };
+
+    use super::{FocusProvider, FocusedWindow};
+
+    pub struct WindowsFocusProvider;
+
+    impl FocusProvider for WindowsFocusProvider {
+        fn focused_window(&self) -> Option<FocusedWindow> {
+            unsafe {
+                let window = GetForegroundWindow();
+                if window.is_null() {
+                    return None;
+                }
+
+                let mut title = [0u16; 512];
+                let length = GetWindowTextW(window, title.as_mut_ptr(), title.len() as i32);
+                let title = if length > 0 {
+                    Some(OsString::from_wide(&title[..length as usize]).to_string_lossy().to_string())
+                } else {
+                    None
+                };
+
+                let mut process_id: DWORD = 0;
+                GetWindowThreadProcessId(window, &mut process_id);
+
+                Some(FocusedWindow {
+                    process: process_name(process_id),
+                    title,
+                })
+            }
+        }
+    }
+
+    unsafe fn process_name(process_id: DWORD) -> Option<String> {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, FALSE, process_id);
+        if process.is_null() {
+            return None;
+        }
+
+        let mut path = [0u16; 1024];
+        let mut length = path.len() as DWORD;
+        let result = QueryFullProcessImageNameW(process, 0, path.as_mut_ptr(), &mut length);
+        CloseHandle(process);
+        if result == FALSE {
+            return None;
+        }
+
+        let path = OsString::from_wide(&path[..length as usize]);
+        Path::new(&path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub mod x11 {
+    use std::{
+        fs,
+        sync::Mutex,
+    };
+
+    use anyhow::Result;
+    use log::error;
+    use x11rb::{
+        connection::Connection,
+        protocol::xproto::{Atom, AtomEnum, ConnectionExt, Window},
+        rust_connection::RustConnection,
+    };
+
+    use super::{FocusProvider, FocusedWindow};
+
+    struct X11Connection {
+        connection: RustConnection,
+        root: Window,
+        net_active_window: Atom,
+        net_wm_name: Atom,
+        net_wm_pid: Atom,
+        utf8_string: Atom,
+    }
+
+    impl X11Connection {
+        fn new() -> Result<X11Connection> {
+            let (connection, screen) = x11rb::connect(None)?;
+            let root = connection.setup().roots[screen].root;
+            let intern = |name: &[u8]| -> Result<Atom> {
+                Ok(connection.intern_atom(false, name)?.reply()?.atom)
+            };
+
+            Ok(X11Connection {
+                net_active_window: intern(b"_NET_ACTIVE_WINDOW")?,
+                net_wm_name: intern(b"_NET_WM_NAME")?,
+                net_wm_pid: intern(b"_NET_WM_PID")?,
+                utf8_string: intern(b"UTF8_STRING")?,
+                root,
+                connection,
+            })
+        }
+
+        fn focused_window(&self) -> Result<Option<FocusedWindow>> {
+            let active = self.connection.get_property(
+                false,
+                self.root,
+                self.net_active_window,
+                AtomEnum::WINDOW,
+                0,
+                1
+            )?.reply()?;
+            let window = match active.value32().and_then(|mut value| value.next()) {
+                Some(window) if window != 0 => window,
+                _ => return Ok(None),
+            };
+
+            let mut title = self.text_property(window, self.net_wm_name, self.utf8_string)?;
+            if title.is_none() {
+                title = self.text_property(window, AtomEnum::WM_NAME.into(), AtomEnum::STRING.into())?;
+            }
+
+            let pid = self.connection.get_property(
+                false,
+                window,
+                self.net_wm_pid,
+                AtomEnum::CARDINAL,
+                0,
+                1
+            )?.reply()?;
+            let process = pid.value32()
+                .and_then(|mut value| value.next())
+                .and_then(|pid| fs::read_to_string(format!("/proc/{}/comm", pid)).ok())
+                .map(|comm| comm.trim().to_string());
+
+            Ok(Some(FocusedWindow {
+                process,
+                title,
+            }))
+        }
+
+        fn text_property(
+            &self,
+            window: Window,
+            property: Atom,
+            property_type: Atom
+        ) -> Result<Option<String>> {
+            let reply = self.connection.get_property(
+                false,
+                window,
+                property,
+                property_type,
+                0,
+                1024
+            )?.reply()?;
+
+            if reply.value.is_empty() {
+                Ok(None)
+            } else {
+                Ok(Some(String::from_utf8_lossy(&reply.value).to_string()))
+            }
+        }
+    }
+
+    //Connects on first use, so a missing X server only disables the rules
+    pub struct X11FocusProvider {
+        connection: Mutex<Option<X11Connection>>,
+    }
+
+    impl X11FocusProvider {
+        pub fn new() -> X11FocusProvider {
+            X11FocusProvider {
+                connection: Mutex::new(None),
+            }
+        }
+    }
+
+    impl FocusProvider for X11FocusProvider {
+        fn focused_window(&self) -> Option<FocusedWindow> {
+            let mut connection = self.connection.lock().unwrap();
+            if connection.is_none() {
+                match X11Connection::new() {
+                    Ok(new_connection) => *connection = Some(new_connection),
+                    Err(err) => {
+                        error!("Unable to connect to the X server [[{:?}]]", err);
+                        return None;
+                    }
+                }
+            }
+
+            match connection.as_ref()?.focused_window() {
+                Ok(window) => window,
+                Err(err) => {
+                    error!("Unable to get focused window [[{:?}]]", err);
+                    //Reconnect on next call
+                    *connection = None;
+                    None
+                }
+            }
+        }
+    }
+}
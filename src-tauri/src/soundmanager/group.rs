@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use serde::{
+    Serialize,
+    Deserialize
+};
+
+//Longest crossfade, in seconds
+const MAX_CROSSFADE: f32 = 60f32;
+
+//Exclusive group, starting a member stops every other member playing,
+// or crossfades them out
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChokeGroup {
+    pub name: String,
+    //Seconds, members are stopped right away when None
+    #[serde(default)]
+    pub crossfade: Option<f32>,
+}
+
+pub type ChokeGroups = Vec<ChokeGroup>;
+
+impl ChokeGroup {
+    pub fn new(name: String, crossfade: Option<f32>) -> ChokeGroup {
+        ChokeGroup {
+            name,
+            crossfade,
+        }
+    }
+
+    pub fn is_valid_crossfade(crossfade: Option<f32>) -> bool {
+        match crossfade {
+            Some(seconds) => (0f32..=MAX_CROSSFADE).contains(&seconds),
+            None => true,
+        }
+    }
+
+    pub fn crossfade(&self) -> Option<Duration> {
+        self.crossfade
+            .filter(|seconds| (0f32..=MAX_CROSSFADE).contains(seconds))
+            .map(Duration::from_secs_f32)
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    fs::{self, File},
+    io::{BufReader, ErrorKind, Read, Seek},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use rodio::{Decoder, Source};
+
+use super::{
+    settings::ImportLimits,
+    utils::NewSoundbiteError,
+};
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+pub const SUPPORTED_EXTENSIONS: [&str; 4] = ["mp3", "wav", "ogg", "flac"];
+
+pub struct DecodedAudio {
+    pub buffer: Vec<i16>,
+    pub channels: u16,
+    pub sample_rate: u32,
+}
+
+pub fn check_file(
+    path: &Path,
+    limits: &ImportLimits
+) -> Result<u64, NewSoundbiteError> {
+    let file_name = path.display().to_string();
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            return Err(NewSoundbiteError::FileNotFound(file_name));
+        },
+        Err(_) => return Err(NewSoundbiteError::UnableToReadFile(file_name)),
+    };
+
+    if !metadata.is_file() {
+        return Err(NewSoundbiteError::FileNotFound(file_name));
+    }
+
+    if metadata.len() > limits.max_file_size {
+        return Err(NewSoundbiteError::FileTooLarge(
+            file_name,
+            limits.max_file_size
+        ));
+    }
+
+    Ok(metadata.len())
+}
+
+//Decodes the file a sample at a time, so the duration limit is enforced
+// without holding more than max_duration worth of samples in memory.
+// on_progress gets the seconds decoded so far and, when known, the
+// completion ratio
+pub fn decode_file<F>(
+    path: &Path,
+    limits: &ImportLimits,
+    on_progress: F,
+) -> Result<DecodedAudio, NewSoundbiteError>
+where
+    F: FnMut(f32, Option<f32>)
+{
+    check_file(path, limits)?;
+
+    let file_name = path.display().to_string();
+    let file = File::open(path).map_err(
+        |_| NewSoundbiteError::UnableToReadFile(file_name.clone())
+    )?;
+
+    decode_reader(BufReader::new(file), file_name, limits, on_progress)
+}
+
+//Same as decode_file for audio not on disk, e.g. inside a pack. The size
+// limit is up to the caller
+pub fn decode_reader<R, F>(
+    reader: R,
+    file_name: String,
+    limits: &ImportLimits,
+    mut on_progress: F,
+) -> Result<DecodedAudio, NewSoundbiteError>
+where
+    R: Read + Seek + Send + Sync + 'static,
+    F: FnMut(f32, Option<f32>)
+{
+    let source = Decoder::new(reader).map_err(
+        |_| NewSoundbiteError::UnsupportedFormat(file_name.clone())
+    )?;
+
+    let channels = source.channels();
+    let sample_rate = source.sample_rate();
+    if channels == 0 || sample_rate == 0 {
+        return Err(NewSoundbiteError::UnsupportedFormat(file_name));
+    }
+
+    let samples_per_second = channels as f32 * sample_rate as f32;
+    let max_samples = (limits.max_duration * samples_per_second) as usize;
+    let total_duration = source.total_duration().map(|d| d.as_secs_f32());
+    if let Some(total) = total_duration {
+        if total > limits.max_duration {
+            return Err(NewSoundbiteError::DurationTooLong(
+                file_name,
+                limits.max_duration
+            ));
+        }
+    }
+
+    let mut buffer = Vec::new();
+    let mut last_progress = Instant::now();
+    for sample in source {
+        if buffer.len() >= max_samples {
+            return Err(NewSoundbiteError::DurationTooLong(
+                file_name,
+                limits.max_duration
+            ));
+        }
+
+        buffer.push(sample);
+
+        if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            last_progress = Instant::now();
+            let decoded = buffer.len() as f32 / samples_per_second;
+            on_progress(
+                decoded,
+                total_duration.map(|total| (decoded / total).min(1f32))
+            );
+        }
+    }
+
+    if buffer.is_empty() {
+        return Err(NewSoundbiteError::UnsupportedFormat(file_name));
+    }
+
+    on_progress(buffer.len() as f32 / samples_per_second, Some(1f32));
+
+    Ok(DecodedAudio {
+        buffer,
+        channels,
+        sample_rate,
+    })
+}
+
+pub fn is_supported(path: &Path) -> bool {
+    match path.extension() {
+        Some(extension) => {
+            let extension = extension.to_string_lossy().to_lowercase();
+            SUPPORTED_EXTENSIONS.contains(&extension.as_str())
+        },
+        None => false,
+    }
+}
+
+pub fn list_folder(folder: &Path) -> Result<Vec<PathBuf>, NewSoundbiteError> {
+    let entries = fs::read_dir(folder).map_err(
+        |_| NewSoundbiteError::FileNotFound(folder.display().to_string())
+    )?;
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && is_supported(path))
+        .collect();
+    files.sort();
+
+    Ok(files)
+}
+
+pub fn name_from_path(path: &Path) -> Option<String> {
+    path.file_stem().map(|stem| stem.to_string_lossy().to_string())
+}
+
+//Appends " (2)", " (3)", ... to name until it's not used anymore
+pub fn unique_name<F>(name: String, is_used: F) -> String
+where
+    F: Fn(&str) -> bool
+{
+    if !is_used(&name) {
+        return name;
+    }
+
+    let mut counter = 2;
+    loop {
+        let candidate = format!("{} ({})", name, counter);
+        if !is_used(&candidate) {
+            return candidate;
+        }
+        counter += 1;
+    }
+}
@@ -0,0 +1,70 @@
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    path::PathBuf,
+};
+
+use fs4::FileExt;
+use log::{trace, error};
+use once_cell::sync::OnceCell;
+
+use super::{
+    control,
+    import,
+    soundstate::{Message, SoundState},
+    ROOT_FOLDER,
+};
+
+pub const LOCK_FILE: &str = "soundbar.lock";
+
+//Held until the process exits, the OS releases it even after a crash
+static INSTANCE_LOCK: OnceCell<File> = OnceCell::new();
+
+//False when another instance already holds the lock
+pub fn acquire_lock() -> io::Result<bool> {
+    let path = ROOT_FOLDER.lock().unwrap().join(LOCK_FILE);
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(path)?;
+
+    match file.try_lock_exclusive() {
+        Ok(_) => {
+            let _ = INSTANCE_LOCK.set(file);
+            Ok(true)
+        },
+        Err(err) if err.raw_os_error() == fs4::lock_contended_error().raw_os_error() => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+//Launch arguments, from this instance or forwarded by a second one:
+// audio files and folders are imported, --play <name> plays a soundbite
+pub fn handle_args(state: &SoundState, args: &[String]) {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--play" {
+            match args.next() {
+                Some(name) => if let Err(err) = control::play(state, name) {
+                    error!("Unable to play {} from launch arguments [[{:?}]]", name, err);
+                },
+                None => error!("Missing soundbite name after --play"),
+            }
+            continue;
+        }
+
+        let path = PathBuf::from(arg);
+        if path.is_dir() {
+            if let Err(err) = state.messages.send(Message::ImportFolder(path)) {
+                error!("Unable to send command to import folder {} [[{:?}]]", arg, err);
+            }
+        } else if import::is_supported(&path) {
+            if let Err(err) = control::import(state, path, None) {
+                error!("Unable to import {} from launch arguments [[{:?}]]", arg, err);
+            }
+        } else {
+            trace!("Launch argument {} ignored", arg);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    io::{self, BufRead, BufReader, ErrorKind, Write},
+    fs,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use interprocess::local_socket::{LocalSocketListener, LocalSocketStream, NameTypeSupport};
+use log::{trace, error};
+use serde::{
+    Serialize,
+    Deserialize
+};
+use serde_json::{json, Value};
+
+use super::{
+    control,
+    events::Event,
+    instance,
+    soundstate::SoundState,
+    utils::{IpcError, SoundManagerError},
+    ROOT_FOLDER,
+};
+
+const SOCKET_FILE: &str = "soundbar.sock";
+//Used where sockets can't be files, i.e. named pipes on Windows, which are
+// machine wide. The app data folder is hashed into it
+const SOCKET_NAME: &str = "soundbar";
+
+//One JSON line per request, answered by one JSON line
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum IpcRequest {
+    Play {
+        name: String,
+    },
+    //Every playing soundbite when None
+    Stop {
+        name: Option<String>,
+    },
+    List,
+    //Master volume when name is None, reads it when volume is None
+    Volume {
+        name: Option<String>,
+        volume: Option<f32>,
+    },
+    Import {
+        path: PathBuf,
+        name: Option<String>,
+    },
+    //Launch arguments of a second instance
+    Forward {
+        args: Vec<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "status", rename_all = "kebab-case")]
+pub enum IpcResponse {
+    Ok {
+        data: Value,
+    },
+    Error {
+        message: String,
+    },
+}
+
+impl<T: Serialize> From<Result<T, SoundManagerError>> for IpcResponse {
+    fn from(result: Result<T, SoundManagerError>) -> Self {
+        match result {
+            Ok(data) => IpcResponse::Ok {
+                data: serde_json::to_value(data).unwrap_or(Value::Null),
+            },
+            Err(err) => IpcResponse::Error {
+                message: err.to_string(),
+            },
+        }
+    }
+}
+
+//A file next to the app data where supported, a name derived from its path
+// otherwise, so every user gets their own
+fn socket_name() -> String {
+    let root_folder = ROOT_FOLDER.lock().unwrap();
+    match NameTypeSupport::query() {
+        NameTypeSupport::OnlyNamespaced => namespaced_name(&root_folder),
+        NameTypeSupport::OnlyPaths | NameTypeSupport::Both => {
+            root_folder.join(SOCKET_FILE).display().to_string()
+        },
+    }
+}
+
+//FNV-1a, which unlike the std hasher is the same across builds
+fn namespaced_name(root_folder: &Path) -> String {
+    let hash = root_folder.to_string_lossy()
+        .to_lowercase()
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3));
+    format!("@{}-{:016x}.sock", SOCKET_NAME, hash)
+}
+
+pub fn start_server(state: SoundState) -> io::Result<()> {
+    let listener = bind()?;
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let state = state.clone();
+                    thread::spawn(move || handle_connection(stream, &state));
+                },
+                Err(err) => error!("Unable to accept IPC connection [[{:?}]]", err),
+            }
+        }
+    });
+
+    trace!("IPC server listening on {}", socket_name());
+    Ok(())
+}
+
+//A socket file left behind by a crash is removed, one still answering
+// belongs to a running instance
+fn bind() -> io::Result<LocalSocketListener> {
+    let name = socket_name();
+    match LocalSocketListener::bind(name.clone()) {
+        Err(err) if err.kind() == ErrorKind::AddrInUse && !name.starts_with('@') => {
+            if LocalSocketStream::connect(name.clone()).is_ok() {
+                return Err(err);
+            }
+
+            fs::remove_file(&name)?;
+            LocalSocketListener::bind(name)
+        },
+        result => result,
+    }
+}
+
+fn handle_connection(stream: LocalSocketStream, state: &SoundState) {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    if let Err(err) = reader.read_line(&mut line) {
+        error!("Unable to read IPC request [[{:?}]]", err);
+        return;
+    }
+
+    let response = match serde_json::from_str::<IpcRequest>(&line) {
+        Ok(request) => handle_request(request, state),
+        Err(err) => IpcResponse::Error {
+            message: format!("Invalid request, {}", err),
+        },
+    };
+
+    let result = serde_json::to_string(&response)
+        .map_err(io::Error::from)
+        .and_then(|json| writeln!(reader.get_mut(), "{}", json));
+    if let Err(err) = result {
+        error!("Unable to send IPC response [[{:?}]]", err);
+    }
+}
+
+fn handle_request(request: IpcRequest, state: &SoundState) -> IpcResponse {
+    trace!("IPC request {:?}", request);
+    match request {
+        IpcRequest::Play { name } => control::play(state, &name).into(),
+        IpcRequest::Stop { name } => control::stop(state, name.as_deref()).into(),
+        IpcRequest::List => Ok::<_, SoundManagerError>(control::list(state)).into(),
+        IpcRequest::Volume { name: None, volume: None } => {
+            Ok::<_, SoundManagerError>(json!({ "volume": control::master_volume(state) })).into()
+        },
+        IpcRequest::Volume { name: None, volume } => control::set_master_volume(state, volume).into(),
+        IpcRequest::Volume { name: Some(name), volume: None } => {
+            control::list(state)
+                .into_iter()
+                .find(|soundbite| soundbite.info.name == name)
+                .map(|soundbite| json!({ "volume": soundbite.info.volume }))
+                .ok_or(SoundManagerError::SoundbiteNotFound(name))
+                .into()
+        },
+        IpcRequest::Volume { name: Some(name), volume: Some(volume) } => {
+            control::set_volume(state, &name, volume).into()
+        },
+        IpcRequest::Import { path, name } => control::import(state, path, name).into(),
+        IpcRequest::Forward { args } => {
+            instance::handle_args(state, &args);
+            state.events.publish(Event::SecondInstance { args });
+            Ok::<_, SoundManagerError>(()).into()
+        },
+    }
+}
+
+//Sends a single request to the running instance
+pub fn send_request(request: &IpcRequest) -> Result<IpcResponse, IpcError> {
+    let stream = match LocalSocketStream::connect(socket_name()) {
+        Ok(stream) => stream,
+        Err(err) => match err.kind() {
+            ErrorKind::NotFound | ErrorKind::ConnectionRefused => return Err(IpcError::NotRunning),
+            _ => return Err(IpcError::Io(err)),
+        },
+    };
+
+    let mut reader = BufReader::new(stream);
+    writeln!(reader.get_mut(), "{}", serde_json::to_string(request)?)?;
+
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    Ok(serde_json::from_str(&line)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn namespaced_names_differ_per_user() {
+        let alice = namespaced_name(Path::new(r"C:\Users\alice\AppData\Roaming\soundbar"));
+        let bob = namespaced_name(Path::new(r"C:\Users\bob\AppData\Roaming\soundbar"));
+        assert_ne!(alice, bob);
+        assert!(alice.starts_with("@soundbar-") && alice.ends_with(".sock"));
+
+        //Windows paths are case insensitive
+        assert_eq!(alice, namespaced_name(Path::new(r"C:\USERS\Alice\AppData\Roaming\soundbar")));
+    }
+}
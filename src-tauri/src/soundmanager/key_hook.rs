@@ -1,19 +1,241 @@
-use std::sync::Mutex;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
 
-use once_cell::sync::Lazy;
-use super::key_task::KeyTask;
+use crossbeam::channel::{bounded, unbounded, Receiver, Sender};
+use once_cell::sync::{Lazy, OnceCell};
+use super::{
+    key_task::{KeyTask, KeyTaskCode, CapturedChord},
+    key_state::{KeyTracker, KeyEvent, KeyDown, HeldKey},
+    focus::{self, ActivationRules, ActivationMode, FocusProvider, FocusedWindow},
+    settings::SwallowEscape,
+    SoundbitesKeyTasks,
+};
 
-pub static KEY_TASK: Lazy<Mutex<KeyTask>> = Lazy::new(
-    || Mutex::new(KeyTask::default())
+pub static KEY_STATE: Lazy<Mutex<KeyTracker>> = Lazy::new(
+    || Mutex::new(KeyTracker::default())
 );
+pub static KEY_EVENTS: OnceCell<Sender<KeyEvent>> = OnceCell::new();
 pub static APP_STATUS: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(true));
+//Global pause, chords still reach the manager so the toggle chord works
+pub static HOTKEYS_ENABLED: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(true));
 
-pub fn init_key_hook() {
-    if cfg!(windows) {
-        windows::init_key_hook();
-    } else if cfg!(unix) {
-        panic!("unix keyboard hook not implemented");
+pub fn hotkeys_enabled() -> bool {
+    *HOTKEYS_ENABLED.lock().unwrap()
+}
+
+pub struct Activation {
+    pub rules: ActivationRules,
+    pub provider: Box<dyn FocusProvider>,
+}
+
+impl Activation {
+    //The focused window is only queried when some rules are active
+    pub fn allows(&self) -> bool {
+        if self.rules.mode == ActivationMode::Always {
+            return true;
+        }
+
+        let window = self.provider.focused_window();
+        self.rules.allows(window.as_ref())
+    }
+}
+
+pub static ACTIVATION: Lazy<Mutex<Activation>> = Lazy::new(
+    || Mutex::new(Activation {
+        rules: ActivationRules::default(),
+        provider: focus::default_provider(),
+    })
+);
+
+pub fn set_activation_rules(rules: ActivationRules) {
+    ACTIVATION.lock().unwrap().rules = rules;
+}
+
+pub fn focused_window() -> Option<FocusedWindow> {
+    ACTIVATION.lock().unwrap().provider.focused_window()
+}
+
+//Checked on key down before a chord is dispatched
+pub fn is_activation_allowed() -> bool {
+    match ACTIVATION.try_lock() {
+        Ok(activation) => activation.allows(),
+        Err(_) => true,
+    }
+}
+
+#[derive(Default)]
+pub struct Swallow {
+    //Shared with the manager, so profile switches are picked up as well
+    pub bindings: Option<Arc<Mutex<SoundbitesKeyTasks>>>,
+    pub escape: SwallowEscape,
+    suspended_until: Option<Instant>,
+}
+
+pub static SWALLOW: Lazy<Mutex<Swallow>> = Lazy::new(
+    || Mutex::new(Swallow::default())
+);
+
+pub fn set_swallow_escape(escape: SwallowEscape) {
+    SWALLOW.lock().unwrap().escape = escape;
+}
+
+//True when code is the escape chord, which then suspends swallowing
+pub fn swallow_escape(code: KeyTaskCode) -> bool {
+    let mut swallow = SWALLOW.lock().unwrap();
+    if code != swallow.escape.keycode {
+        return false;
+    }
+
+    let duration = Duration::from_secs(swallow.escape.duration);
+    swallow.suspended_until = Some(Instant::now() + duration);
+    log::trace!("Swallowing suspended for {:?}", duration);
+    true
+}
+
+pub fn should_swallow(code: KeyTaskCode) -> bool {
+    if !hotkeys_enabled() {
+        return false;
+    }
+
+    let mut swallow = SWALLOW.lock().unwrap();
+    if let Some(until) = swallow.suspended_until {
+        if Instant::now() < until {
+            return false;
+        }
+        swallow.suspended_until = None;
+    }
+
+    match &swallow.bindings {
+        //Never wait on the bindings from inside the hook
+        Some(bindings) => match bindings.try_lock() {
+            Ok(bindings) => bindings.get(&code).map(|b| b.swallow).unwrap_or(false),
+            Err(_) => false,
+        },
+        None => false,
+    }
+}
+
+//Capture in progress, id is the one handed back by start_capture
+pub struct Capture {
+    id: u64,
+    sender: Sender<CapturedChord>,
+}
+
+pub static CAPTURE: Lazy<Mutex<Option<Capture>>> = Lazy::new(
+    || Mutex::new(None)
+);
+static NEXT_CAPTURE_ID: AtomicU64 = AtomicU64::new(0);
+
+//Replaces any capture in progress, whose receiver gets disconnected
+pub fn start_capture() -> (u64, Receiver<CapturedChord>) {
+    let (tx, rx) = bounded(1);
+    let id = NEXT_CAPTURE_ID.fetch_add(1, Ordering::SeqCst);
+    *CAPTURE.lock().unwrap() = Some(Capture {
+        id,
+        sender: tx,
+    });
+    (id, rx)
+}
+
+pub fn stop_capture() {
+    *CAPTURE.lock().unwrap() = None;
+}
+
+//Stops the capture only if no newer one replaced it
+pub fn end_capture(id: u64) {
+    let mut capture = CAPTURE.lock().unwrap();
+    if capture.as_ref().is_some_and(|capture| capture.id == id) {
+        *capture = None;
+    }
+}
+
+pub fn capture(key_task: &KeyTask) -> bool {
+    let sender = match CAPTURE.lock().unwrap().take() {
+        Some(capture) => capture.sender,
+        None => return false,
+    };
+
+    let _ = sender.send(CapturedChord {
+        keycode: key_task.get_code(),
+        display: key_task.label(),
+        conflict: None,
+    });
+    true
+}
+
+fn send_key_event(event: KeyEvent) {
+    if let Some(sender) = KEY_EVENTS.get() {
+        let _ = sender.send(event);
+    }
+}
+
+//Called by the platform hook on every key down, true when the key has
+// to be blocked from reaching other applications
+pub fn key_down(virtual_key: u32) -> bool {
+    let mut key_state = KEY_STATE.lock().unwrap();
+    let key_task = match key_state.key_down(virtual_key) {
+        KeyDown::Chord(key_task) => key_task,
+        KeyDown::Repeat(held) => return held.swallowed,
+        KeyDown::Other => return false,
+    };
+
+    let code = key_task.get_code();
+    let held = if capture(&key_task) {
+        //Captured chords never reach other applications
+        HeldKey { code, dispatched: false, swallowed: true }
+    } else if swallow_escape(code) || !is_activation_allowed() {
+        HeldKey { code, dispatched: false, swallowed: false }
+    } else {
+        send_key_event(KeyEvent::Pressed(code));
+        HeldKey { code, dispatched: true, swallowed: should_swallow(code) }
+    };
+
+    key_state.hold(virtual_key, held);
+    held.swallowed
+}
+
+pub fn key_up(virtual_key: u32) -> bool {
+    match KEY_STATE.lock().unwrap().key_up(virtual_key) {
+        Some(held) => {
+            if held.dispatched {
+                send_key_event(KeyEvent::Released(held.code));
+            }
+            held.swallowed
+        },
+        None => false,
+    }
+}
+
+pub fn init_key_hook(
+    bindings: Arc<Mutex<SoundbitesKeyTasks>>,
+    escape: SwallowEscape
+) -> Receiver<KeyEvent> {
+    {
+        let mut swallow = SWALLOW.lock().unwrap();
+        swallow.bindings = Some(bindings);
+        swallow.escape = escape;
     }
+
+    let (tx, rx) = unbounded();
+    if KEY_EVENTS.set(tx).is_err() {
+        log::error!("Key hook already initialized");
+    }
+
+    #[cfg(windows)]
+    windows::init_key_hook();
+
+    #[cfg(target_os = "linux")]
+    linux::init_key_hook();
+
+    #[cfg(not(any(windows, target_os = "linux")))]
+    log::error!("Keyboard hook not implemented on this platform");
+
+    rx
 }
 
 #[cfg(target_os="windows")]
@@ -52,8 +274,6 @@ pub mod windows {
         }
     };
 
-    use super::super::key_task::{KeyCode, SysKeyCode};
-
     pub static HOOK: OnceCell<AtomicPtr<HHOOK>> = OnceCell::new();
     
     pub unsafe extern "system" fn keyboard_hook(
@@ -69,56 +289,18 @@ pub mod windows {
             let key_info: &KBDLLHOOKSTRUCT = unsafe {
                 std::mem::transmute(l_param)
             };
-    
-            if let Ok(key_task) = super::KEY_TASK.lock().as_deref_mut() {
-                match w_param as u32 {
-                    WM_KEYDOWN => {
-                        let code = KeyCode::try_from(key_info.vkCode);
-                        key_task.key = if let Ok(key) = code {
-                            Some(key)
-                        } else {
-                            let code = SysKeyCode::try_from(key_info.vkCode);
-                            key_task.sys_key = if let Ok(key) = code {
-                                Some(key)
-                            } else {
-                                None
-                            };
-                            None
-                        };
-                    }
-                    WM_KEYUP => {
-                        key_task.key = None;
-                    },
-                    //FIXME: ALT + key doesn't work if holding ALT
-                    // down after the first stroke
-                    WM_SYSKEYDOWN => {
-                        let code = SysKeyCode::try_from(key_info.vkCode);
-                        key_task.sys_key = match code {
-                            Ok(key) => Some(key),
-                            Err(_) => {
-                                match key_task.sys_key {
-                                    Some(SysKeyCode::LALT) | Some(SysKeyCode::RALT) => {
-                                        let code = KeyCode::try_from(key_info.vkCode);
-                                        key_task.key = if let Ok(key) = code {
-                                            Some(key)
-                                        } else {
-                                            None
-                                        };
-                                        key_task.sys_key.clone()
-                                    },
-                                    _ => None,
-                                }
-                            }
-                        };
-                    }
-                    WM_SYSKEYUP => key_task.sys_key = {
-                        None
-                    },
-                    _ => {},
-                }
+
+            let swallowed = match w_param as u32 {
+                WM_KEYDOWN | WM_SYSKEYDOWN => super::key_down(key_info.vkCode),
+                WM_KEYUP | WM_SYSKEYUP => super::key_up(key_info.vkCode),
+                _ => false,
+            };
+
+            if swallowed {
+                1
+            } else {
+                CallNextHookEx(null_mut(), code, w_param, l_param)
             }
-    
-            CallNextHookEx(null_mut(), code, w_param, l_param)
         }
     }
 
@@ -143,7 +325,162 @@ pub mod windows {
     }
 }
 
-#[cfg(target_os="unix")]
-mod linux {
-    //TODO:
-}
\ No newline at end of file
+//Reads the evdev devices directly, which needs read access to /dev/input,
+// e.g. through the input group. Chords can't be swallowed without grabbing
+// the devices, so they always reach the focused application
+#[cfg(target_os = "linux")]
+pub mod linux {
+    use std::{
+        fs::{self, File},
+        io::Read,
+        mem::size_of,
+        path::Path,
+        thread,
+    };
+
+    use super::super::key_task::{KeyCode, SysKeyCode};
+
+    const INPUT_FOLDER: &str = "/dev/input";
+    //struct input_event, a timeval of two longs then type, code and value
+    pub(super) const TIME_SIZE: usize = 2 * size_of::<usize>();
+    pub(super) const EVENT_SIZE: usize = TIME_SIZE + 8;
+    const EV_KEY: u16 = 1;
+    const KEY_UP: i32 = 0;
+
+    //Virtual key the hook works with, modifiers included
+    pub fn virtual_key(code: u16) -> Option<u32> {
+        KeyCode::from_evdev(code)
+            .map(|key| key.vk())
+            .or_else(|| SysKeyCode::from_evdev(code).map(|sys_key| sys_key.vk()))
+    }
+
+    //Type, code and value of a raw input_event
+    pub(super) fn parse_event(event: &[u8; EVENT_SIZE]) -> (u16, u16, i32) {
+        let kind = u16::from_ne_bytes([event[TIME_SIZE], event[TIME_SIZE + 1]]);
+        let code = u16::from_ne_bytes([event[TIME_SIZE + 2], event[TIME_SIZE + 3]]);
+        let value = i32::from_ne_bytes([
+            event[TIME_SIZE + 4],
+            event[TIME_SIZE + 5],
+            event[TIME_SIZE + 6],
+            event[TIME_SIZE + 7],
+        ]);
+        (kind, code, value)
+    }
+
+    fn read_device(path: &Path, mut device: File) {
+        let mut event = [0u8; EVENT_SIZE];
+        loop {
+            if let Ok(false) = super::APP_STATUS.try_lock().as_deref() {
+                return;
+            }
+            if let Err(err) = device.read_exact(&mut event) {
+                log::error!("Unable to read input device {} [[{:?}]]", path.display(), err);
+                return;
+            }
+
+            let (kind, code, value) = parse_event(&event);
+            if kind != EV_KEY {
+                continue;
+            }
+            //Mouse buttons and such have no virtual key
+            if let Some(virtual_key) = virtual_key(code) {
+                //Auto repeats come as key downs, as they do on Windows
+                if value == KEY_UP {
+                    super::key_up(virtual_key);
+                } else {
+                    super::key_down(virtual_key);
+                }
+            }
+        }
+    }
+
+    pub fn init_key_hook() {
+        let entries = match fs::read_dir(INPUT_FOLDER) {
+            Ok(entries) => entries,
+            Err(err) => {
+                log::error!("Unable to list input devices [[{:?}]]", err);
+                return;
+            },
+        };
+
+        let mut opened = 0;
+        for path in entries.flatten().map(|entry| entry.path()) {
+            let is_event = path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("event"));
+            if !is_event {
+                continue;
+            }
+
+            match File::open(&path) {
+                Ok(device) => {
+                    log::trace!("Reading keys from {}", path.display());
+                    thread::spawn(move || read_device(&path, device));
+                    opened += 1;
+                },
+                Err(err) => log::trace!("Unable to open input device {} [[{:?}]]", path.display(), err),
+            }
+        }
+
+        if opened == 0 {
+            log::error!("No readable input device in {}, hotkeys are disabled", INPUT_FOLDER);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::focus::{StaticFocusProvider, WindowRule};
+
+    fn activation(mode: ActivationMode, process: Option<&str>) -> Activation {
+        Activation {
+            rules: ActivationRules {
+                mode,
+                rules: vec![WindowRule {
+                    process: Some("discord.exe".to_string()),
+                    title: None,
+                }],
+            },
+            provider: Box::new(StaticFocusProvider(process.map(|process| FocusedWindow {
+                process: Some(process.to_string()),
+                title: None,
+            }))),
+        }
+    }
+
+    #[test]
+    fn ending_a_replaced_capture_keeps_the_newer_one() {
+        let (old, old_chords) = start_capture();
+        let (_, chords) = start_capture();
+        assert!(old_chords.recv().is_err());
+
+        end_capture(old);
+        assert!(CAPTURE.lock().unwrap().is_some());
+        stop_capture();
+        assert!(chords.recv().is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn evdev_key_events_map_to_virtual_keys() {
+        let mut event = [0u8; linux::EVENT_SIZE];
+        event[linux::TIME_SIZE..linux::TIME_SIZE + 2].copy_from_slice(&1u16.to_ne_bytes());
+        event[linux::TIME_SIZE + 2..linux::TIME_SIZE + 4].copy_from_slice(&30u16.to_ne_bytes());
+        event[linux::TIME_SIZE + 4..].copy_from_slice(&2i32.to_ne_bytes());
+        assert_eq!(linux::parse_event(&event), (1, 30, 2));
+
+        //KEY_A, KEY_LEFTSHIFT, BTN_LEFT
+        assert_eq!(linux::virtual_key(30), Some(0x41));
+        assert_eq!(linux::virtual_key(42), Some(0xA0));
+        assert_eq!(linux::virtual_key(272), None);
+    }
+
+    #[test]
+    fn activation_queries_the_provider() {
+        assert!(activation(ActivationMode::AllowList, Some("discord.exe")).allows());
+        assert!(!activation(ActivationMode::AllowList, Some("game.exe")).allows());
+        assert!(!activation(ActivationMode::DenyList, Some("discord.exe")).allows());
+        assert!(activation(ActivationMode::Always, None).allows());
+    }
+}
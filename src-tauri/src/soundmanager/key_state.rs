@@ -0,0 +1,223 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    time::{Duration, Instant},
+};
+
+use super::key_task::{KeyTask, KeyTaskCode, KeyCode, SysKeyCode};
+
+//Longest time between the two presses of a double tap
+pub const DOUBLE_TAP_WINDOW: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyEvent {
+    Pressed(KeyTaskCode),
+    Released(KeyTaskCode),
+}
+
+//What was decided for a chord on key down, its repeats and key up follow it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeldKey {
+    pub code: KeyTaskCode,
+    //Pressed was sent, so Released is sent as well
+    pub dispatched: bool,
+    pub swallowed: bool,
+}
+
+pub enum KeyDown {
+    //First key down of a key, with the modifier held at the time
+    Chord(KeyTask),
+    //Auto-repeat of a key already held down
+    Repeat(HeldKey),
+    //Modifier or key without a KeyCode
+    Other,
+}
+
+//Keeps track of the keys held down, so a key up is matched to the chord
+// of its key down even when the modifier was released first
+#[derive(Default)]
+pub struct KeyTracker {
+    //Most recent last, the last one is the modifier of new chords
+    modifiers: Vec<SysKeyCode>,
+    held: HashMap<u32, HeldKey>,
+}
+
+impl KeyTracker {
+    pub fn key_down(&mut self, virtual_key: u32) -> KeyDown {
+        if let Ok(sys_key) = SysKeyCode::try_from(virtual_key) {
+            if !self.modifiers.contains(&sys_key) {
+                self.modifiers.push(sys_key);
+            }
+            return KeyDown::Other;
+        }
+
+        if let Some(held) = self.held.get(&virtual_key) {
+            return KeyDown::Repeat(*held);
+        }
+
+        match KeyCode::try_from(virtual_key) {
+            Ok(key) => KeyDown::Chord(KeyTask::new(key, self.modifiers.last().cloned())),
+            Err(_) => KeyDown::Other,
+        }
+    }
+
+    pub fn hold(&mut self, virtual_key: u32, held: HeldKey) {
+        self.held.insert(virtual_key, held);
+    }
+
+    //None for modifiers and keys whose key down wasn't seen
+    pub fn key_up(&mut self, virtual_key: u32) -> Option<HeldKey> {
+        if let Ok(sys_key) = SysKeyCode::try_from(virtual_key) {
+            self.modifiers.retain(|modifier| *modifier != sys_key);
+            return None;
+        }
+
+        self.held.remove(&virtual_key)
+    }
+}
+
+//First presses of possible double taps, tracked per source
+pub struct DoubleTaps<S> {
+    last_taps: HashMap<S, Instant>,
+}
+
+impl<S: Hash + Eq> DoubleTaps<S> {
+    pub fn new() -> DoubleTaps<S> {
+        DoubleTaps {
+            last_taps: HashMap::new(),
+        }
+    }
+
+    //True on the second press within DOUBLE_TAP_WINDOW, which starts over
+    pub fn tap(&mut self, source: S, now: Instant) -> bool {
+        match self.last_taps.remove(&source) {
+            Some(last) if now.duration_since(last) <= DOUBLE_TAP_WINDOW => true,
+            _ => {
+                self.last_taps.insert(source, now);
+                false
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: u32 = KeyCode::A as u32;
+    const B: u32 = KeyCode::B as u32;
+    const LSHIFT: u32 = SysKeyCode::LSHIFT as u32;
+    const LCTRL: u32 = SysKeyCode::LCTRL as u32;
+
+    fn held(code: KeyTaskCode) -> HeldKey {
+        HeldKey { code, dispatched: true, swallowed: false }
+    }
+
+    fn chord(tracker: &mut KeyTracker, virtual_key: u32) -> KeyTask {
+        match tracker.key_down(virtual_key) {
+            KeyDown::Chord(key_task) => key_task,
+            _ => panic!("no chord for {:#04X}", virtual_key),
+        }
+    }
+
+    #[test]
+    fn press_and_release() {
+        let mut tracker = KeyTracker::default();
+        let key_task = chord(&mut tracker, A);
+        assert_eq!(key_task.key, Some(KeyCode::A));
+        assert_eq!(key_task.sys_key, None);
+
+        tracker.hold(A, held(key_task.get_code()));
+        assert_eq!(tracker.key_up(A), Some(held(key_task.get_code())));
+        assert_eq!(tracker.key_up(A), None);
+    }
+
+    #[test]
+    fn suppresses_repeats() {
+        let mut tracker = KeyTracker::default();
+        let code = chord(&mut tracker, A).get_code();
+        tracker.hold(A, held(code));
+
+        for _ in 0..3 {
+            assert!(matches!(tracker.key_down(A), KeyDown::Repeat(repeat) if repeat == held(code)));
+        }
+
+        tracker.key_up(A);
+        assert!(matches!(tracker.key_down(A), KeyDown::Chord(_)));
+    }
+
+    #[test]
+    fn key_up_without_key_down() {
+        let mut tracker = KeyTracker::default();
+        assert_eq!(tracker.key_up(B), None);
+    }
+
+    #[test]
+    fn tracks_modifiers() {
+        let mut tracker = KeyTracker::default();
+        assert!(matches!(tracker.key_down(LSHIFT), KeyDown::Other));
+        assert_eq!(chord(&mut tracker, A).sys_key, Some(SysKeyCode::LSHIFT));
+
+        //The modifier pressed last wins, and the previous one is back once
+        // it's released
+        tracker.key_down(LCTRL);
+        assert_eq!(chord(&mut tracker, B).sys_key, Some(SysKeyCode::LCTRL));
+        assert_eq!(tracker.key_up(LCTRL), None);
+        assert_eq!(chord(&mut tracker, B).sys_key, Some(SysKeyCode::LSHIFT));
+
+        tracker.key_up(LSHIFT);
+        assert_eq!(chord(&mut tracker, B).sys_key, None);
+    }
+
+    #[test]
+    fn modifier_repeats_are_ignored() {
+        let mut tracker = KeyTracker::default();
+        tracker.key_down(LSHIFT);
+        tracker.key_down(LCTRL);
+        tracker.key_down(LSHIFT);
+        assert_eq!(chord(&mut tracker, A).sys_key, Some(SysKeyCode::LCTRL));
+    }
+
+    #[test]
+    fn key_up_follows_its_key_down_chord() {
+        let mut tracker = KeyTracker::default();
+        tracker.key_down(LSHIFT);
+        let code = chord(&mut tracker, A).get_code();
+        tracker.hold(A, held(code));
+
+        //Released the modifier first
+        tracker.key_up(LSHIFT);
+        assert_eq!(tracker.key_up(A).map(|held| held.code), Some(code));
+    }
+
+    #[test]
+    fn double_tap_within_window() {
+        let mut taps = DoubleTaps::new();
+        let start = Instant::now();
+        assert!(!taps.tap(A, start));
+        assert!(taps.tap(A, start + DOUBLE_TAP_WINDOW));
+        //Starts over after a double tap
+        assert!(!taps.tap(A, start + DOUBLE_TAP_WINDOW * 2));
+    }
+
+    #[test]
+    fn double_tap_too_slow() {
+        let mut taps = DoubleTaps::new();
+        let start = Instant::now();
+        let late = DOUBLE_TAP_WINDOW + Duration::from_millis(1);
+        assert!(!taps.tap(A, start));
+        assert!(!taps.tap(A, start + late));
+        //The late tap is the first of a new double tap
+        assert!(taps.tap(A, start + late * 2 - Duration::from_millis(1)));
+    }
+
+    #[test]
+    fn double_taps_per_source() {
+        let mut taps = DoubleTaps::new();
+        let start = Instant::now();
+        assert!(!taps.tap(A, start));
+        assert!(!taps.tap(B, start));
+        assert!(taps.tap(A, start + Duration::from_millis(10)));
+        assert!(taps.tap(B, start + Duration::from_millis(10)));
+    }
+}
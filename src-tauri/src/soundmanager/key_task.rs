@@ -1,237 +1,246 @@
 use anyhow::{Result, bail};
+use log::warn;
+use serde::{
+    Serialize,
+    Deserialize
+};
 
-#[derive(Debug, Clone)]
-pub enum KeyCode {
-    SPACE = 0x20,
-    
-    PAGE_UP = 0x21,
-    PAGE_DOWN = 0x22,
-    END = 0x23,
-    HOME = 0x24,
-    
-    LEFT_ARROW = 0x25,
-    UP_ARROW = 0x26,
-    RIGHT_ARROW = 0x27,
-    DOWN_ARROW = 0x28,
-    
-    SELECT = 0x29,
-    PRINT = 0x2A,
-    SNAPSHOT = 0x2C,
-    INSERT = 0x2D,
-    DELETE = 0x2E,
-
-    SEMICOLOMN = 0xBA,
-    EQUAL = 0xBB,
-    COMMA = 0xBC,
-    MINUS = 0xBD,
-    FORSLASH = 0xBF,
-    ACCENT = 0xC0,
-    OPEN_PAR = 0xDB,
-    BACKSLASH = 0xDC,
-    CLOSE_PAR = 0xDD,
-    QUOTE = 0xDE,
-    
-    NUM_0 = 0x30,
-    NUM_1 = 0x31,
-    NUM_2 = 0x32,
-    NUM_3 = 0x33,
-    NUM_4 = 0x34,
-    NUM_5 = 0x35,
-    NUM_6 = 0x36,
-    NUM_7 = 0x37,
-    NUM_8 = 0x38,
-    NUM_9 = 0x39,
-
-    A = 0x41,
-    B = 0x42,
-    C = 0x43,
-    D = 0x44,
-    E = 0x45,
-    F = 0x46,
-    G = 0x47,
-    H = 0x48,
-    I = 0x49,
-    J = 0x4A,
-    K = 0x4B,
-    L = 0x4C,
-    M = 0x4D,
-    N = 0x4E,
-    O = 0x4F,
-    P = 0x50,
-    Q = 0x51,
-    R = 0x52,
-    S = 0x53,
-    T = 0x54,
-    U = 0x55,
-    V = 0x56,
-    W = 0x57,
-    X = 0x58,
-    Y = 0x59,
-    Z = 0x5A,
-
-    NUMPAD_0 = 0x60,
-    NUMPAD_1 = 0x61,
-    NUMPAD_2 = 0x62,
-    NUMPAD_3 = 0x63,
-    NUMPAD_4 = 0x64,
-    NUMPAD_5 = 0x65,
-    NUMPAD_6 = 0x66,
-    NUMPAD_7 = 0x67,
-    NUMPAD_8 = 0x68,
-    NUMPAD_9 = 0x69,
-    MULTIPLY = 0x6A,
-    ADD = 0x6B,
-    SEPARATOR = 0x6C,
-    SUBTRACT = 0x6D,
-    DECIMAL = 0x6E,
-    DIVIDE = 0x6F,
-
-    F1 = 0x70,
-    F2 = 0x71,
-    F3 = 0x72,
-    F4 = 0x73,
-    F5 = 0x74,
-    F6 = 0x75,
-    F7 = 0x76,
-    F8 = 0x77,
-    F9 = 0x78,
-    F10 = 0x79,
-    F11 = 0x7A,
-    F12 = 0x7B,
-    F13 = 0x7C,
-    F14 = 0x7D,
-    F15 = 0x7E,
-    F16 = 0x7F,
-    F17 = 0x80,
-    F18 = 0x81,
-    F19 = 0x82,
-    F20 = 0x83,
-    F21 = 0x84,
-    F22 = 0x85,
-    F23 = 0x86,
-    F24 = 0x87,
-
-    NUM_LOCK = 0x90,
-    SCROLL_LOCK = 0x91,
+//Defines KeyCode from one table, so the Windows virtual key, the Linux
+// evdev code and the label of a key can't drift apart. Discriminants are
+// the virtual keys, which KeyTaskCode has always been built from
+macro_rules! key_codes {
+    ($($key:ident = $vk:literal, $evdev:expr, $label:literal;)*) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum KeyCode {
+            $($key = $vk,)*
+        }
+
+        impl KeyCode {
+            pub const ALL: &'static [KeyCode] = &[$(KeyCode::$key,)*];
+
+            pub fn from_vk(vk: u32) -> Option<KeyCode> {
+                match vk {
+                    $($vk => Some(KeyCode::$key),)*
+                    _ => None,
+                }
+            }
+
+            pub fn vk(&self) -> u32 {
+                *self as u32
+            }
+
+            //Only the Linux hook reads evdev codes
+            #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+            pub fn evdev(&self) -> Option<u16> {
+                match self {
+                    $(KeyCode::$key => $evdev,)*
+                }
+            }
+
+            pub fn label(&self) -> &'static str {
+                match self {
+                    $(KeyCode::$key => $label,)*
+                }
+            }
+        }
+    };
+}
+
+key_codes! {
+    CANCEL = 0x03, Some(223), "Cancel";
+    BACKSPACE = 0x08, Some(14), "Backspace";
+    TAB = 0x09, Some(15), "Tab";
+    CLEAR = 0x0C, Some(355), "Clear";
+    ENTER = 0x0D, Some(28), "Enter";
+    PAUSE = 0x13, Some(119), "Pause";
+    CAPS_LOCK = 0x14, Some(58), "Caps Lock";
+    KANA = 0x15, Some(122), "Kana";
+    JUNJA = 0x17, None, "Junja";
+    FINAL = 0x18, None, "Final";
+    HANJA = 0x19, Some(123), "Hanja";
+    ESCAPE = 0x1B, Some(1), "Escape";
+    CONVERT = 0x1C, Some(92), "Convert";
+    NON_CONVERT = 0x1D, Some(94), "Non Convert";
+    ACCEPT = 0x1E, None, "Accept";
+    MODE_CHANGE = 0x1F, None, "Mode Change";
+    SPACE = 0x20, Some(57), "Space";
+    PAGE_UP = 0x21, Some(104), "Page Up";
+    PAGE_DOWN = 0x22, Some(109), "Page Down";
+    END = 0x23, Some(107), "End";
+    HOME = 0x24, Some(102), "Home";
+    LEFT_ARROW = 0x25, Some(105), "Left Arrow";
+    UP_ARROW = 0x26, Some(103), "Up Arrow";
+    RIGHT_ARROW = 0x27, Some(106), "Right Arrow";
+    DOWN_ARROW = 0x28, Some(108), "Down Arrow";
+    SELECT = 0x29, Some(353), "Select";
+    PRINT = 0x2A, Some(210), "Print";
+    EXECUTE = 0x2B, None, "Execute";
+    SNAPSHOT = 0x2C, Some(99), "Print Screen";
+    INSERT = 0x2D, Some(110), "Insert";
+    DELETE = 0x2E, Some(111), "Delete";
+    HELP = 0x2F, Some(138), "Help";
+    NUM_0 = 0x30, Some(11), "0";
+    NUM_1 = 0x31, Some(2), "1";
+    NUM_2 = 0x32, Some(3), "2";
+    NUM_3 = 0x33, Some(4), "3";
+    NUM_4 = 0x34, Some(5), "4";
+    NUM_5 = 0x35, Some(6), "5";
+    NUM_6 = 0x36, Some(7), "6";
+    NUM_7 = 0x37, Some(8), "7";
+    NUM_8 = 0x38, Some(9), "8";
+    NUM_9 = 0x39, Some(10), "9";
+    A = 0x41, Some(30), "A";
+    B = 0x42, Some(48), "B";
+    C = 0x43, Some(46), "C";
+    D = 0x44, Some(32), "D";
+    E = 0x45, Some(18), "E";
+    F = 0x46, Some(33), "F";
+    G = 0x47, Some(34), "G";
+    H = 0x48, Some(35), "H";
+    I = 0x49, Some(23), "I";
+    J = 0x4A, Some(36), "J";
+    K = 0x4B, Some(37), "K";
+    L = 0x4C, Some(38), "L";
+    M = 0x4D, Some(50), "M";
+    N = 0x4E, Some(49), "N";
+    O = 0x4F, Some(24), "O";
+    P = 0x50, Some(25), "P";
+    Q = 0x51, Some(16), "Q";
+    R = 0x52, Some(19), "R";
+    S = 0x53, Some(31), "S";
+    T = 0x54, Some(20), "T";
+    U = 0x55, Some(22), "U";
+    V = 0x56, Some(47), "V";
+    W = 0x57, Some(17), "W";
+    X = 0x58, Some(45), "X";
+    Y = 0x59, Some(21), "Y";
+    Z = 0x5A, Some(44), "Z";
+    APPS = 0x5D, Some(127), "Menu";
+    SLEEP = 0x5F, Some(142), "Sleep";
+    NUMPAD_0 = 0x60, Some(82), "Numpad 0";
+    NUMPAD_1 = 0x61, Some(79), "Numpad 1";
+    NUMPAD_2 = 0x62, Some(80), "Numpad 2";
+    NUMPAD_3 = 0x63, Some(81), "Numpad 3";
+    NUMPAD_4 = 0x64, Some(75), "Numpad 4";
+    NUMPAD_5 = 0x65, Some(76), "Numpad 5";
+    NUMPAD_6 = 0x66, Some(77), "Numpad 6";
+    NUMPAD_7 = 0x67, Some(71), "Numpad 7";
+    NUMPAD_8 = 0x68, Some(72), "Numpad 8";
+    NUMPAD_9 = 0x69, Some(73), "Numpad 9";
+    MULTIPLY = 0x6A, Some(55), "Numpad *";
+    ADD = 0x6B, Some(78), "Numpad +";
+    SEPARATOR = 0x6C, Some(121), "Numpad Separator";
+    SUBTRACT = 0x6D, Some(74), "Numpad -";
+    DECIMAL = 0x6E, Some(83), "Numpad .";
+    DIVIDE = 0x6F, Some(98), "Numpad /";
+    F1 = 0x70, Some(59), "F1";
+    F2 = 0x71, Some(60), "F2";
+    F3 = 0x72, Some(61), "F3";
+    F4 = 0x73, Some(62), "F4";
+    F5 = 0x74, Some(63), "F5";
+    F6 = 0x75, Some(64), "F6";
+    F7 = 0x76, Some(65), "F7";
+    F8 = 0x77, Some(66), "F8";
+    F9 = 0x78, Some(67), "F9";
+    F10 = 0x79, Some(68), "F10";
+    F11 = 0x7A, Some(87), "F11";
+    F12 = 0x7B, Some(88), "F12";
+    F13 = 0x7C, Some(183), "F13";
+    F14 = 0x7D, Some(184), "F14";
+    F15 = 0x7E, Some(185), "F15";
+    F16 = 0x7F, Some(186), "F16";
+    F17 = 0x80, Some(187), "F17";
+    F18 = 0x81, Some(188), "F18";
+    F19 = 0x82, Some(189), "F19";
+    F20 = 0x83, Some(190), "F20";
+    F21 = 0x84, Some(191), "F21";
+    F22 = 0x85, Some(192), "F22";
+    F23 = 0x86, Some(193), "F23";
+    F24 = 0x87, Some(194), "F24";
+    NUM_LOCK = 0x90, Some(69), "Num Lock";
+    SCROLL_LOCK = 0x91, Some(70), "Scroll Lock";
+    BROWSER_BACK = 0xA6, Some(158), "Browser Back";
+    BROWSER_FORWARD = 0xA7, Some(159), "Browser Forward";
+    BROWSER_REFRESH = 0xA8, Some(173), "Browser Refresh";
+    BROWSER_STOP = 0xA9, Some(128), "Browser Stop";
+    BROWSER_SEARCH = 0xAA, Some(217), "Browser Search";
+    BROWSER_FAVORITES = 0xAB, Some(156), "Browser Favorites";
+    BROWSER_HOME = 0xAC, Some(172), "Browser Home";
+    VOLUME_MUTE = 0xAD, Some(113), "Volume Mute";
+    VOLUME_DOWN = 0xAE, Some(114), "Volume Down";
+    VOLUME_UP = 0xAF, Some(115), "Volume Up";
+    MEDIA_NEXT_TRACK = 0xB0, Some(163), "Next Track";
+    MEDIA_PREV_TRACK = 0xB1, Some(165), "Previous Track";
+    MEDIA_STOP = 0xB2, Some(166), "Media Stop";
+    MEDIA_PLAY_PAUSE = 0xB3, Some(164), "Play/Pause";
+    LAUNCH_MAIL = 0xB4, Some(155), "Mail";
+    LAUNCH_MEDIA_SELECT = 0xB5, Some(226), "Media Select";
+    LAUNCH_APP1 = 0xB6, Some(157), "App 1";
+    LAUNCH_APP2 = 0xB7, Some(140), "App 2";
+    SEMICOLOMN = 0xBA, Some(39), ";";
+    EQUAL = 0xBB, Some(13), "=";
+    COMMA = 0xBC, Some(51), ",";
+    MINUS = 0xBD, Some(12), "-";
+    PERIOD = 0xBE, Some(52), ".";
+    FORSLASH = 0xBF, Some(53), "/";
+    ACCENT = 0xC0, Some(41), "`";
+    ABNT_C1 = 0xC1, Some(89), "ABNT C1";
+    ABNT_C2 = 0xC2, None, "ABNT C2";
+    OPEN_PAR = 0xDB, Some(26), "[";
+    BACKSLASH = 0xDC, Some(43), "\\";
+    CLOSE_PAR = 0xDD, Some(27), "]";
+    QUOTE = 0xDE, Some(40), "'";
+    OEM_8 = 0xDF, None, "OEM 8";
+    OEM_102 = 0xE2, Some(86), "OEM 102";
+    PROCESS = 0xE5, None, "Process";
+    ATTN = 0xF6, None, "Attn";
+    CRSEL = 0xF7, None, "CrSel";
+    EXSEL = 0xF8, None, "ExSel";
+    EREOF = 0xF9, None, "Erase EOF";
+    PLAY = 0xFA, Some(207), "Play";
+    ZOOM = 0xFB, Some(372), "Zoom";
+    PA1 = 0xFD, None, "PA1";
+    OEM_CLEAR = 0xFE, None, "OEM Clear";
+}
+
+//evdev codes reported for keys that share a virtual key with another one
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+const EVDEV_ALIASES: &[(u16, KeyCode)] = &[
+    //KEY_KPENTER
+    (96, KeyCode::ENTER),
+    //KEY_KATAKANAHIRAGANA
+    (93, KeyCode::KANA),
+    //KEY_KPJPCOMMA
+    (95, KeyCode::ABNT_C2),
+];
+
+impl KeyCode {
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub fn from_evdev(code: u16) -> Option<KeyCode> {
+        KeyCode::ALL.iter()
+            .find(|key| key.evdev() == Some(code))
+            .or_else(|| EVDEV_ALIASES.iter()
+                .find(|(alias, _)| *alias == code)
+                .map(|(_, key)| key))
+            .copied()
+    }
 }
 
 impl TryFrom<u32> for KeyCode {
     type Error = ();
 
     fn try_from(value: u32) -> Result<Self, Self::Error> {
-        match value {
-            0x20 => Ok(KeyCode::SPACE),
-            0x21 => Ok(KeyCode::PAGE_UP),
-            0x22 => Ok(KeyCode::PAGE_DOWN),
-            0x23 => Ok(KeyCode::END),
-            0x24 => Ok(KeyCode::HOME),
-            0x25 => Ok(KeyCode::LEFT_ARROW),
-            0x26 => Ok(KeyCode::UP_ARROW),
-            0x27 => Ok(KeyCode::RIGHT_ARROW),
-            0x28 => Ok(KeyCode::DOWN_ARROW),
-            0x29 => Ok(KeyCode::SELECT),
-            0x2A => Ok(KeyCode::PRINT),
-            0x2C => Ok(KeyCode::SNAPSHOT),
-            0x2D => Ok(KeyCode::INSERT),
-            0x2E => Ok(KeyCode::DELETE),
-            0xBA => Ok(KeyCode::SEMICOLOMN),
-            0xBB => Ok(KeyCode::EQUAL),
-            0xBC => Ok(KeyCode::COMMA),
-            0xBD => Ok(KeyCode::MINUS),
-            0xBF => Ok(KeyCode::FORSLASH),
-            0xC0 => Ok(KeyCode::ACCENT),
-            0xDB => Ok(KeyCode::OPEN_PAR),
-            0xDC => Ok(KeyCode::BACKSLASH),
-            0xDD => Ok(KeyCode::CLOSE_PAR),
-            0xDE => Ok(KeyCode::QUOTE),
-            0x30 => Ok(KeyCode::NUM_0),
-            0x31 => Ok(KeyCode::NUM_1),
-            0x32 => Ok(KeyCode::NUM_2),
-            0x33 => Ok(KeyCode::NUM_3),
-            0x34 => Ok(KeyCode::NUM_4),
-            0x35 => Ok(KeyCode::NUM_5),
-            0x36 => Ok(KeyCode::NUM_6),
-            0x37 => Ok(KeyCode::NUM_7),
-            0x38 => Ok(KeyCode::NUM_8),
-            0x39 => Ok(KeyCode::NUM_9),
-            0x41 => Ok(KeyCode::A),
-            0x42 => Ok(KeyCode::B),
-            0x43 => Ok(KeyCode::C),
-            0x44 => Ok(KeyCode::D),
-            0x45 => Ok(KeyCode::E),
-            0x46 => Ok(KeyCode::F),
-            0x47 => Ok(KeyCode::G),
-            0x48 => Ok(KeyCode::H),
-            0x49 => Ok(KeyCode::I),
-            0x4A => Ok(KeyCode::J),
-            0x4B => Ok(KeyCode::K),
-            0x4C => Ok(KeyCode::L),
-            0x4D => Ok(KeyCode::M),
-            0x4E => Ok(KeyCode::N),
-            0x4F => Ok(KeyCode::O),
-            0x50 => Ok(KeyCode::P),
-            0x51 => Ok(KeyCode::Q),
-            0x52 => Ok(KeyCode::R),
-            0x53 => Ok(KeyCode::S),
-            0x54 => Ok(KeyCode::T),
-            0x55 => Ok(KeyCode::U),
-            0x56 => Ok(KeyCode::V),
-            0x57 => Ok(KeyCode::W),
-            0x58 => Ok(KeyCode::X),
-            0x59 => Ok(KeyCode::Y),
-            0x5A => Ok(KeyCode::Z),
-            0x60 => Ok(KeyCode::NUMPAD_0),
-            0x61 => Ok(KeyCode::NUMPAD_1),
-            0x62 => Ok(KeyCode::NUMPAD_2),
-            0x63 => Ok(KeyCode::NUMPAD_3),
-            0x64 => Ok(KeyCode::NUMPAD_4),
-            0x65 => Ok(KeyCode::NUMPAD_5),
-            0x66 => Ok(KeyCode::NUMPAD_6),
-            0x67 => Ok(KeyCode::NUMPAD_7),
-            0x68 => Ok(KeyCode::NUMPAD_8),
-            0x69 => Ok(KeyCode::NUMPAD_9),
-            0x6A => Ok(KeyCode::MULTIPLY),
-            0x6B => Ok(KeyCode::ADD),
-            0x6C => Ok(KeyCode::SEPARATOR),
-            0x6D => Ok(KeyCode::SUBTRACT),
-            0x6E => Ok(KeyCode::DECIMAL),
-            0x6F => Ok(KeyCode::DIVIDE),
-            0x70 => Ok(KeyCode::F1),
-            0x71 => Ok(KeyCode::F2),
-            0x72 => Ok(KeyCode::F3),
-            0x73 => Ok(KeyCode::F4),
-            0x74 => Ok(KeyCode::F5),
-            0x75 => Ok(KeyCode::F6),
-            0x76 => Ok(KeyCode::F7),
-            0x77 => Ok(KeyCode::F8),
-            0x78 => Ok(KeyCode::F9),
-            0x79 => Ok(KeyCode::F10),
-            0x7A => Ok(KeyCode::F11),
-            0x7B => Ok(KeyCode::F12),
-            0x7C => Ok(KeyCode::F13),
-            0x7D => Ok(KeyCode::F14),
-            0x7E => Ok(KeyCode::F15),
-            0x7F => Ok(KeyCode::F16),
-            0x80 => Ok(KeyCode::F17),
-            0x81 => Ok(KeyCode::F18),
-            0x82 => Ok(KeyCode::F19),
-            0x83 => Ok(KeyCode::F20),
-            0x84 => Ok(KeyCode::F21),
-            0x85 => Ok(KeyCode::F22),
-            0x86 => Ok(KeyCode::F23),
-            0x87 => Ok(KeyCode::F24),
-            0x90 => Ok(KeyCode::NUM_LOCK),
-            0x91 => Ok(KeyCode::SCROLL_LOCK),
-            _ => Err(()),
-        }
+        KeyCode::from_vk(value).ok_or(())
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SysKeyCode {
     SHIFT = 0x10,
     CTRL = 0x11,
     ALT = 0x12,
+    LWIN = 0x5B,
+    RWIN = 0x5C,
     LSHIFT = 0xA0,
     RSHIFT = 0xA1,
     LCTRL = 0xA2,
@@ -240,27 +249,248 @@ pub enum SysKeyCode {
     RALT = 0xA5,
 }
 
+impl SysKeyCode {
+    pub const ALL: &'static [SysKeyCode] = &[
+        SysKeyCode::SHIFT,
+        SysKeyCode::CTRL,
+        SysKeyCode::ALT,
+        SysKeyCode::LWIN,
+        SysKeyCode::RWIN,
+        SysKeyCode::LSHIFT,
+        SysKeyCode::RSHIFT,
+        SysKeyCode::LCTRL,
+        SysKeyCode::RCTRL,
+        SysKeyCode::LALT,
+        SysKeyCode::RALT,
+    ];
+
+    pub fn vk(&self) -> u32 {
+        *self as u32
+    }
+
+    //evdev only reports the sided modifiers
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub fn evdev(&self) -> Option<u16> {
+        match self {
+            SysKeyCode::SHIFT | SysKeyCode::CTRL | SysKeyCode::ALT => None,
+            SysKeyCode::LWIN => Some(125),
+            SysKeyCode::RWIN => Some(126),
+            SysKeyCode::LSHIFT => Some(42),
+            SysKeyCode::RSHIFT => Some(54),
+            SysKeyCode::LCTRL => Some(29),
+            SysKeyCode::RCTRL => Some(97),
+            SysKeyCode::LALT => Some(56),
+            SysKeyCode::RALT => Some(100),
+        }
+    }
+
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub fn from_evdev(code: u16) -> Option<SysKeyCode> {
+        SysKeyCode::ALL.iter().find(|key| key.evdev() == Some(code)).copied()
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SysKeyCode::SHIFT => "Shift",
+            SysKeyCode::CTRL => "Ctrl",
+            SysKeyCode::ALT => "Alt",
+            SysKeyCode::LWIN => "Left Win",
+            SysKeyCode::RWIN => "Right Win",
+            SysKeyCode::LSHIFT => "Left Shift",
+            SysKeyCode::RSHIFT => "Right Shift",
+            SysKeyCode::LCTRL => "Left Ctrl",
+            SysKeyCode::RCTRL => "Right Ctrl",
+            SysKeyCode::LALT => "Left Alt",
+            SysKeyCode::RALT => "Right Alt",
+        }
+    }
+}
+
 impl TryFrom<u32> for SysKeyCode {
     type Error = ();
 
     fn try_from(value: u32) -> Result<Self, Self::Error> {
-        match value {
-            0x10 => Ok(SysKeyCode::SHIFT),
-            0x11 => Ok(SysKeyCode::CTRL),
-            0x12 => Ok(SysKeyCode::ALT),
-            0xA0 => Ok(SysKeyCode::LSHIFT),
-            0xA1 => Ok(SysKeyCode::RSHIFT),
-            0xA2 => Ok(SysKeyCode::LCTRL),
-            0xA3 => Ok(SysKeyCode::RCTRL),
-            0xA4 => Ok(SysKeyCode::LALT),
-            0xA5 => Ok(SysKeyCode::RALT),
-            _ => Err(()),
-        }
+        SysKeyCode::ALL.iter().find(|key| key.vk() == value).copied().ok_or(())
     }
 }
 
+//Modifier virtual key in the second byte, key virtual key in the first one
 pub type KeyTaskCode = u32;
 
+//Codes used to be packed as modifier << 2 | key, which collides once the
+// whole virtual key range is in use, e.g. Left Shift + Backspace and Left
+// Ctrl + Tab. Codes in between the two packings are converted. Plain keys
+// are the same in both and old chords that packed below 0x100 can't be
+// told apart from them, so they are kept as they are. Codes more than one
+// chord packed to give None, their bindings have to be made again
+pub fn migrate_keycode(code: KeyTaskCode) -> Option<KeyTaskCode> {
+    if !(0x100..0x1000).contains(&code) {
+        return Some(code);
+    }
+
+    let chords: Vec<KeyTask> = SysKeyCode::ALL.iter()
+        .flat_map(|sys_key| KeyCode::ALL.iter()
+            .filter(move |key| sys_key.vk() << 2 | key.vk() == code)
+            .map(|key| KeyTask::new(*key, Some(*sys_key))))
+        .collect();
+
+    match chords.as_slice() {
+        [chord] => Some(chord.get_code()),
+        _ => {
+            warn!("Unable to migrate key code {:#06X}, {} chords match it", code, chords.len());
+            None
+        },
+    }
+}
+
+//Binding left out at load as its old key code can't be migrated
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DroppedBinding {
+    pub keycode: KeyTaskCode,
+    //What it triggered, e.g. a soundbite name or pausing hotkeys
+    pub bound_to: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TriggerMode {
+    #[default]
+    Press,
+    Release,
+    //Plays while the chord is held down, stops on release
+    Hold,
+    //Plays on the second press within DOUBLE_TAP_WINDOW
+    DoubleTap,
+}
+
+//What a binding plays
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum BindingTarget {
+    //Index in Soundbites
+    Soundbite(usize),
+    //Name of a variant set
+    VariantSet(String),
+    //Name of a macro
+    Macro(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "StoredKeyBinding")]
+pub struct KeyBinding {
+    pub target: BindingTarget,
+    //Blocks the chord from reaching the focused application
+    pub swallow: bool,
+    pub trigger: TriggerMode,
+}
+
+impl KeyBinding {
+    pub fn new(target: BindingTarget) -> KeyBinding {
+        KeyBinding {
+            target,
+            swallow: false,
+            trigger: TriggerMode::default(),
+        }
+    }
+
+    pub fn soundbite(&self) -> Option<usize> {
+        match self.target {
+            BindingTarget::Soundbite(index) => Some(index),
+            _ => None,
+        }
+    }
+
+    pub fn is_soundbite(&self, index: usize) -> bool {
+        self.soundbite() == Some(index)
+    }
+
+    //Follows the removal of the soundbite at index, false when bound to it
+    pub fn soundbite_removed(&mut self, index: usize) -> bool {
+        match &mut self.target {
+            BindingTarget::Soundbite(soundbite) if *soundbite == index => false,
+            BindingTarget::Soundbite(soundbite) => {
+                if *soundbite > index {
+                    *soundbite -= 1;
+                }
+                true
+            },
+            BindingTarget::VariantSet(_) | BindingTarget::Macro(_) => true,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum ChordConflict {
+    Soundbite {
+        name: String,
+    },
+    VariantSet {
+        name: String,
+    },
+    Macro {
+        name: String,
+    },
+    ProfileSwitch {
+        profile: String,
+    },
+    PauseToggle,
+    SwallowEscape,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CapturedChord {
+    pub keycode: KeyTaskCode,
+    //Human readable chord, e.g. Left Ctrl + A
+    pub display: String,
+    pub conflict: Option<ChordConflict>,
+}
+
+//Bindings used to be stored as a bare soundbite index
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredKeyBinding {
+    Index(usize),
+    Binding {
+        #[serde(alias = "soundbite")]
+        target: StoredBindingTarget,
+        #[serde(default)]
+        swallow: bool,
+        #[serde(default)]
+        trigger: TriggerMode,
+    },
+}
+
+//Targets used to be a soundbite index as well
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredBindingTarget {
+    Index(usize),
+    Target(BindingTarget),
+}
+
+impl From<StoredBindingTarget> for BindingTarget {
+    fn from(stored: StoredBindingTarget) -> Self {
+        match stored {
+            StoredBindingTarget::Index(index) => BindingTarget::Soundbite(index),
+            StoredBindingTarget::Target(target) => target,
+        }
+    }
+}
+
+impl From<StoredKeyBinding> for KeyBinding {
+    fn from(stored: StoredKeyBinding) -> Self {
+        match stored {
+            StoredKeyBinding::Index(soundbite) => KeyBinding::new(BindingTarget::Soundbite(soundbite)),
+            StoredKeyBinding::Binding { target, swallow, trigger } => KeyBinding {
+                target: target.into(),
+                swallow,
+                trigger,
+            },
+        }
+    }
+}
+
 #[derive(Default, Debug)]
 pub struct KeyTask {
     pub key: Option<KeyCode>,
@@ -292,23 +522,133 @@ impl KeyTask {
     }
 
     pub fn try_from(key_code: KeyTaskCode) -> Result<KeyTask> {
-        let sys_key = key_code >> 2;
-        let key = key_code & 0x0F;
+        let sys_key = key_code >> 8;
+        let key = key_code & 0xFF;
+        if sys_key == 0 {
+            return match KeyCode::try_from(key) {
+                Ok(key) => Ok(KeyTask::new(key, None)),
+                Err(_) => bail!("Unable to generate KeyCode from key {} ({:#04X})", key, key),
+            };
+        }
 
         KeyTask::try_new(key, sys_key)
     }
 
+    pub fn label(&self) -> String {
+        match (&self.sys_key, &self.key) {
+            (Some(sys_key), Some(key)) => format!("{} + {}", sys_key.label(), key.label()),
+            (None, Some(key)) => key.label().to_string(),
+            (Some(sys_key), None) => sys_key.label().to_string(),
+            (None, None) => String::new(),
+        }
+    }
+
     pub fn get_code(&self) -> KeyTaskCode {
         if let Some(key) = &self.key {
             let mut code = if let Some(sys_key) = &self.sys_key {
-                (sys_key.clone() as u32) << 2
+                sys_key.vk() << 8
             } else {
                 0
             };
-            code |= key.clone() as u32;
+            code |= key.vk();
             code
         } else {
             0
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn every_key_round_trips() {
+        let mut evdev_codes = HashSet::new();
+        for key in KeyCode::ALL {
+            assert_eq!(KeyCode::from_vk(key.vk()), Some(*key), "{:?}", key);
+            assert_eq!(KeyCode::try_from(key.vk()), Ok(*key), "{:?}", key);
+            assert!(!key.label().is_empty(), "{:?}", key);
+            assert!(SysKeyCode::try_from(key.vk()).is_err(), "{:?}", key);
+
+            if let Some(evdev) = key.evdev() {
+                assert!(evdev_codes.insert(evdev), "{:?} shares evdev code {}", key, evdev);
+                assert_eq!(KeyCode::from_evdev(evdev), Some(*key), "{:?}", key);
+            }
+        }
+    }
+
+    #[test]
+    fn every_modifier_round_trips() {
+        for sys_key in SysKeyCode::ALL {
+            assert_eq!(SysKeyCode::try_from(sys_key.vk()), Ok(*sys_key), "{:?}", sys_key);
+            assert!(KeyCode::from_vk(sys_key.vk()).is_none(), "{:?}", sys_key);
+            assert!(!sys_key.label().is_empty(), "{:?}", sys_key);
+
+            if let Some(evdev) = sys_key.evdev() {
+                assert_eq!(SysKeyCode::from_evdev(evdev), Some(*sys_key), "{:?}", sys_key);
+                assert_eq!(KeyCode::from_evdev(evdev), None, "{:?}", sys_key);
+            }
+        }
+    }
+
+    #[test]
+    fn evdev_aliases() {
+        for (evdev, key) in EVDEV_ALIASES {
+            assert!(KeyCode::ALL.iter().all(|other| other.evdev() != Some(*evdev)));
+            assert_eq!(KeyCode::from_evdev(*evdev), Some(*key));
+        }
+    }
+
+    #[test]
+    fn chord_codes_never_collide() {
+        let mut codes = HashSet::new();
+        let modifiers = SysKeyCode::ALL.iter().map(Some).chain([None]);
+        for sys_key in modifiers {
+            for key in KeyCode::ALL {
+                let key_task = KeyTask::new(*key, sys_key.copied());
+                let code = key_task.get_code();
+                assert!(codes.insert(code), "{} collides", key_task.label());
+
+                let decoded = KeyTask::try_from(code).unwrap();
+                assert_eq!(decoded.key, Some(*key));
+                assert_eq!(decoded.sys_key, sys_key.copied());
+            }
+        }
+    }
+
+    #[test]
+    fn migrates_old_codes() {
+        let old = |key: KeyCode, sys_key: SysKeyCode| sys_key.vk() << 2 | key.vk();
+        let new = |key: KeyCode, sys_key: SysKeyCode| KeyTask::new(key, Some(sys_key)).get_code();
+
+        let mut migrated = 0;
+        for sys_key in SysKeyCode::ALL {
+            for key in KeyCode::ALL {
+                let code = old(*key, *sys_key);
+                if code < 0x100 {
+                    continue;
+                }
+
+                let ambiguous = SysKeyCode::ALL.iter()
+                    .flat_map(|other_sys| KeyCode::ALL.iter().map(move |other| old(*other, *other_sys)))
+                    .filter(|other| *other == code)
+                    .count() > 1;
+                if ambiguous {
+                    assert_eq!(migrate_keycode(code), None);
+                } else {
+                    assert_eq!(migrate_keycode(code), Some(new(*key, *sys_key)));
+                    migrated += 1;
+                }
+            }
+        }
+        assert!(migrated > 0);
+        //Plain keys and new codes stay as they are
+        assert_eq!(migrate_keycode(KeyCode::H.vk()), Some(KeyCode::H.vk()));
+        let code = new(KeyCode::A, SysKeyCode::LSHIFT);
+        assert_eq!(migrate_keycode(code), Some(code));
+        assert_eq!(migrate_keycode(code).and_then(migrate_keycode), Some(code));
+    }
+}
@@ -0,0 +1,141 @@
+use std::time::{Duration, Instant};
+
+use serde::{
+    Serialize,
+    Deserialize
+};
+
+//Longest wait or fade, in seconds
+const MAX_STEP_DURATION: f32 = 3600f32;
+
+//Soundbites and profiles are referenced by name
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub enum MacroStep {
+    Play {
+        soundbite: String,
+    },
+    //Every playing soundbite when None
+    Stop {
+        soundbite: Option<String>,
+    },
+    Wait {
+        seconds: f32,
+    },
+    //Factor of the soundbite own volume until it's played again, e.g. to
+    // duck a music bed
+    SetVolume {
+        soundbite: String,
+        volume: f32,
+    },
+    //From the current volume factor to the given one
+    Fade {
+        soundbite: String,
+        volume: f32,
+        seconds: f32,
+    },
+    SwitchProfile {
+        profile: String,
+    },
+}
+
+impl MacroStep {
+    pub fn is_valid(&self) -> bool {
+        let duration = |seconds: f32| (0f32..=MAX_STEP_DURATION).contains(&seconds);
+        let volume = |volume: f32| (0f32..=1f32).contains(&volume);
+        match self {
+            MacroStep::Wait { seconds } => duration(*seconds),
+            MacroStep::SetVolume { volume: value, .. } => volume(*value),
+            MacroStep::Fade { volume: value, seconds, .. } => volume(*value) && duration(*seconds),
+            _ => true,
+        }
+    }
+
+    pub fn soundbite(&self) -> Option<&String> {
+        match self {
+            MacroStep::Play { soundbite }
+            | MacroStep::SetVolume { soundbite, .. }
+            | MacroStep::Fade { soundbite, .. } => Some(soundbite),
+            MacroStep::Stop { soundbite } => soundbite.as_ref(),
+            _ => None,
+        }
+    }
+
+    fn soundbite_mut(&mut self) -> Option<&mut String> {
+        match self {
+            MacroStep::Play { soundbite }
+            | MacroStep::SetVolume { soundbite, .. }
+            | MacroStep::Fade { soundbite, .. } => Some(soundbite),
+            MacroStep::Stop { soundbite } => soundbite.as_mut(),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Macro {
+    pub name: String,
+    pub steps: Vec<MacroStep>,
+}
+
+pub type Macros = Vec<Macro>;
+
+impl Macro {
+    pub fn rename_soundbite(&mut self, name: &str, new_name: &str) {
+        self.steps.iter_mut()
+            .filter_map(|step| step.soundbite_mut())
+            .filter(|soundbite| *soundbite == name)
+            .for_each(|soundbite| *soundbite = new_name.to_string());
+    }
+}
+
+//Progress of a running macro, stepped by the manager
+pub struct MacroRun {
+    pub name: String,
+    steps: Vec<MacroStep>,
+    next: usize,
+    //End of the wait in progress
+    resume_at: Option<Instant>,
+}
+
+impl MacroRun {
+    pub fn new(macro_: &Macro) -> MacroRun {
+        MacroRun {
+            name: macro_.name.clone(),
+            steps: macro_.steps.clone(),
+            next: 0,
+            resume_at: None,
+        }
+    }
+
+    //Steps due now, up to the next wait
+    pub fn due_steps(&mut self, now: Instant) -> Vec<MacroStep> {
+        //Waits are counted from when they were due, not to drift
+        let from = match self.resume_at {
+            Some(resume_at) if now < resume_at => return Vec::new(),
+            Some(resume_at) => resume_at,
+            None => now,
+        };
+
+        self.resume_at = None;
+        self.steps_until_wait(from)
+    }
+
+    fn steps_until_wait(&mut self, from: Instant) -> Vec<MacroStep> {
+        let mut steps = Vec::new();
+        while let Some(step) = self.steps.get(self.next) {
+            self.next += 1;
+            if let MacroStep::Wait { seconds } = step {
+                self.resume_at = Some(from + Duration::from_secs_f32(*seconds));
+                break;
+            }
+            steps.push(step.clone());
+        }
+
+        steps
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.next >= self.steps.len() && self.resume_at.is_none()
+    }
+}
@@ -1,49 +1,244 @@
 use std::{
     sync::{Arc, Mutex},
     collections::HashMap,
-    thread,
-    time::Duration,
-    path::PathBuf,
+    time::{Duration, Instant},
+    path::{Path, PathBuf},
 };
 
-use anyhow::{Result, bail};
-use crossbeam::channel::{Receiver, Sender};
-use rodio::{OutputStreamHandle, OutputStream};
+use anyhow::Result;
+use crossbeam::{
+    channel::{Receiver, Sender},
+    select,
+};
+use rodio::{
+    OutputStreamHandle,
+    OutputStream,
+    DeviceTrait,
+    cpal::traits::HostTrait,
+};
 use log::{trace, error};
 use once_cell::sync::Lazy;
 
 
+pub mod api;
+pub mod control;
+pub mod events;
+pub mod export;
+pub mod fade;
+pub mod focus;
+pub mod group;
+pub mod import;
+pub mod instance;
+pub mod ipc;
 pub mod key_hook;
+pub mod key_state;
 pub mod key_task;
+pub mod macros;
+#[cfg(target_os = "linux")]
+pub mod mpris;
+pub mod osc;
+pub mod pack;
+pub mod profile;
+pub mod queue;
+pub mod schedule;
+pub mod search;
+pub mod settings;
 pub mod soundbite;
 pub mod soundstate;
+pub mod trigger;
 pub mod utils;
+pub mod variant;
+pub mod watcher;
 
+use events::{Event, EventBus};
+use fade::Fade;
+use group::ChokeGroups;
+use macros::{Macros, MacroRun, MacroStep};
+use pack::{Pack, PackConflict, PackImportReport};
+use profile::Profiles;
+use queue::{Queue, Playlists, Transition};
+use schedule::Scheduler;
+use settings::Settings;
 use soundbite::{Soundbite, SoundbiteData};
 use utils::{NewSoundbiteError, SoundManagerError};
+use variant::VariantSets;
+use watcher::FolderWatcher;
 
-use key_hook::{KEY_TASK, init_key_hook};
-use key_task::KeyTaskCode;
+use key_hook::{
+    HOTKEYS_ENABLED,
+    init_key_hook,
+    set_activation_rules,
+    hotkeys_enabled,
+};
+use key_state::{DoubleTaps, KeyEvent};
+use trigger::{TriggerBindings, TriggerCode, TriggerEvent};
+use key_task::{KeyTaskCode, KeyBinding, BindingTarget, TriggerMode};
 
-use self::soundstate::Message;
+use self::soundstate::{Message, SoundState};
 
 pub type Soundbites = Vec<Soundbite>;
-pub type SoundbitesKeyTasks = HashMap<KeyTaskCode, usize>;
+pub type SoundbitesKeyTasks = HashMap<KeyTaskCode, KeyBinding>;
+//Category names in display order
+pub type Categories = Vec<String>;
+
+//Time between two position events of a playing soundbite
+const POSITION_INTERVAL: Duration = Duration::from_millis(200);
 
 pub const SOUNDBITES_FILE: &str = "sdata.dat";
 pub const KEYTASKS_FILE: &str = "kdata.dat";
+pub const SETTINGS_FILE: &str = "settings.dat";
+pub const CATEGORIES_FILE: &str = "cdata.dat";
+pub const PROFILES_FILE: &str = "pdata.dat";
+pub const TRIGGERS_FILE: &str = "tdata.dat";
+pub const PLAYLISTS_FILE: &str = "ldata.dat";
+pub const CHOKE_GROUPS_FILE: &str = "gdata.dat";
+pub const VARIANT_SETS_FILE: &str = "vdata.dat";
+pub const SCHEDULES_FILE: &str = "edata.dat";
+pub const MACROS_FILE: &str = "mdata.dat";
 pub static ROOT_FOLDER: Lazy<Mutex<PathBuf>> = Lazy::new(|| Mutex::new(PathBuf::from("")));
 
+//Removes the soundbite at index together with its key tasks in every
+// profile and its triggers, shifting the bindings of the soundbites after it
+pub fn remove_soundbite(
+    soundbites: &mut Soundbites,
+    soundbites_keytasks: &mut SoundbitesKeyTasks,
+    trigger_bindings: &mut TriggerBindings,
+    profiles: &mut Profiles,
+    index: usize,
+) -> Soundbite {
+    soundbites_keytasks.retain(|_, binding| binding.soundbite_removed(index));
+    trigger_bindings.retain(|_, binding| binding.soundbite_removed(index));
+    profiles.remove_soundbite(index);
+
+    soundbites.remove(index)
+}
+
+//Follows a variant set or macro rename in every profile and trigger, its
+// bindings are removed when new_target is None
+pub fn retarget_bindings(
+    soundbites_keytasks: &mut SoundbitesKeyTasks,
+    trigger_bindings: &mut TriggerBindings,
+    profiles: &mut Profiles,
+    target: &BindingTarget,
+    new_target: Option<&BindingTarget>,
+) {
+    let retarget = |binding: &mut KeyBinding| {
+        if binding.target != *target {
+            return true;
+        }
+
+        match new_target {
+            Some(new_target) => {
+                binding.target = new_target.clone();
+                true
+            },
+            None => false,
+        }
+    };
+
+    soundbites_keytasks.retain(|_, binding| retarget(binding));
+    trigger_bindings.retain(|_, binding| retarget(binding));
+    profiles.retain_bindings(retarget);
+}
+
+//Soundbite, variant set or macro name, for display. A soundbite index out of
+// bound shows as the index
+pub fn binding_target_name(soundbites: &Soundbites, target: &BindingTarget) -> String {
+    match target {
+        BindingTarget::Soundbite(index) => soundbites.get(*index)
+            .map_or_else(|| format!("#{}", index), |soundbite| soundbite.data.name.clone()),
+        BindingTarget::VariantSet(name) | BindingTarget::Macro(name) => name.clone(),
+    }
+}
+
+//Only set outright over D-Bus, everything else toggles
+#[cfg(target_os = "linux")]
+pub fn set_hotkeys_enabled(events: &EventBus, enabled: bool) {
+    *HOTKEYS_ENABLED.lock().unwrap() = enabled;
+    trace!("Hotkeys {}", if enabled { "enabled" } else { "paused" });
+    events.publish(Event::HotkeysToggled { enabled });
+}
+
+//Flipped under a single lock, so concurrent toggles from the UI, D-Bus and
+// the pause chord never cancel out
+pub fn toggle_hotkeys(events: &EventBus) -> bool {
+    let enabled = {
+        let mut hotkeys_enabled = HOTKEYS_ENABLED.lock().unwrap();
+        *hotkeys_enabled = !*hotkeys_enabled;
+        *hotkeys_enabled
+    };
+    trace!("Hotkeys {}", if enabled { "enabled" } else { "paused" });
+    events.publish(Event::HotkeysToggled { enabled });
+    enabled
+}
+
+//Falls back to the default device when the named one is gone
+pub fn open_output_stream(
+    device: Option<&str>
+) -> Result<(OutputStream, OutputStreamHandle)> {
+    if let Some(name) = device {
+        let host = rodio::cpal::default_host();
+        let found = host.output_devices()?.find(
+            |d| d.name().map(|n| n == name).unwrap_or(false)
+        );
+        match found {
+            Some(device) => return Ok(OutputStream::try_from_device(&device)?),
+            None => error!("Output device {} not found, using default", name),
+        }
+    }
+
+    Ok(OutputStream::try_default()?)
+}
+
+pub fn output_devices() -> Vec<String> {
+    match rodio::cpal::default_host().output_devices() {
+        Ok(devices) => devices.filter_map(|device| device.name().ok()).collect(),
+        Err(err) => {
+            error!("Unable to list output devices [[{:?}]]", err);
+            Vec::new()
+        }
+    }
+}
+
+//What fired a binding, double taps are tracked per source
+#[derive(PartialEq, Eq, Hash)]
+enum BindingSource {
+    Key(KeyTaskCode),
+    Trigger(TriggerCode),
+}
+
 pub struct SoundManager {
     messages: Receiver<Message>,
     responses: Sender<Result<String, SoundManagerError>>,
+    key_events: Receiver<KeyEvent>,
+    trigger_events: Receiver<TriggerEvent>,
+    double_taps: DoubleTaps<BindingSource>,
+    //Start time of the soundbites playing, by name
+    playing: HashMap<String, Instant>,
+    last_position_update: Instant,
+    fades: Vec<Fade>,
+    macro_runs: Vec<MacroRun>,
 
     stream_handle: OutputStreamHandle,
     //OutputStream needs to be kept alive in order for the sound to be played
     _stream: OutputStream,
+    output_device: Option<String>,
 
     soundbites: Arc<Mutex<Soundbites>>,
     soundbites_keytasks: Arc<Mutex<SoundbitesKeyTasks>>,
+    trigger_bindings: Arc<Mutex<TriggerBindings>>,
+    categories: Arc<Mutex<Categories>>,
+    choke_groups: Arc<Mutex<ChokeGroups>>,
+    variant_sets: Arc<Mutex<VariantSets>>,
+    macros: Arc<Mutex<Macros>>,
+    profiles: Arc<Mutex<Profiles>>,
+    settings: Arc<Mutex<Settings>>,
+    queue: Arc<Mutex<Queue>>,
+    playlists: Arc<Mutex<Playlists>>,
+    scheduler: Arc<Mutex<Scheduler>>,
+    events: EventBus,
+
+    watcher: Option<FolderWatcher>,
 }
 
 impl SoundManager {
@@ -51,17 +246,20 @@ impl SoundManager {
         messages: Receiver<Message>,
         responses: Sender<Result<String, SoundManagerError>>,
 
-        soundbites: Arc<Mutex<Soundbites>>,
-        soundbites_keytasks: Arc<Mutex<SoundbitesKeyTasks>>,
+        state: &SoundState,
     ) -> SoundManager {
-        init_key_hook();
+        let settings = state.settings.lock().unwrap().clone();
+        set_activation_rules(settings.activation_rules);
+        let key_events = init_key_hook(state.soundbites_keytasks.clone(), settings.swallow_escape);
+        let trigger_events = trigger::start_sources();
 
-        let (_stream, stream_handle) = match OutputStream::try_default() {
+        let output_device = state.profiles.lock().unwrap().active().output_device.clone();
+        let (_stream, stream_handle) = match open_output_stream(output_device.as_deref()) {
             Ok(output) => output,
             Err(err) => panic!("Unable to get default output stream [[{:?}]]", err),
         };
 
-        for soundbite in soundbites.lock().unwrap().iter_mut() {
+        for soundbite in state.soundbites.lock().unwrap().iter_mut() {
             soundbite.init_sink(&stream_handle).map_err(|err| error!(
                 "Unable to init sink for soundbite named {} [[{:?}]]",
                 soundbite.data.name.clone(),
@@ -72,64 +270,779 @@ impl SoundManager {
         SoundManager {
             messages,
             responses,
+            key_events,
+            trigger_events,
+            double_taps: DoubleTaps::new(),
+            playing: HashMap::new(),
+            last_position_update: Instant::now(),
+            fades: Vec::new(),
+            macro_runs: Vec::new(),
 
             stream_handle,
             _stream,
+            output_device,
+
+            soundbites: state.soundbites.clone(),
+            soundbites_keytasks: state.soundbites_keytasks.clone(),
+            trigger_bindings: state.trigger_bindings.clone(),
+            categories: state.categories.clone(),
+            choke_groups: state.choke_groups.clone(),
+            variant_sets: state.variant_sets.clone(),
+            macros: state.macros.clone(),
+            profiles: state.profiles.clone(),
+            settings: state.settings.clone(),
+            queue: state.queue.clone(),
+            playlists: state.playlists.clone(),
+            scheduler: state.scheduler.clone(),
+            events: state.events.clone(),
 
-            soundbites,
-            soundbites_keytasks,
+            watcher: None,
         }
     }
 
-    pub fn run(&self) {
+    pub fn run(&mut self) {
+        let watch_folder = self.settings.lock().unwrap().watch_folder.clone();
+        self.watch_folder(watch_folder);
+
+        let key_events = self.key_events.clone();
+        let trigger_events = self.trigger_events.clone();
+        let messages = self.messages.clone();
         loop {
-            if let Ok(key_task) = KEY_TASK.try_lock().as_deref_mut() {
-                if let Some(_) = &key_task.key {
-                    if let Ok(_) = self.play_soundbite(key_task.get_code()) {
-                        key_task.key = None;
-                    }
-                }
+            select! {
+                recv(key_events) -> event => match event {
+                    Ok(event) => self.handle_key_event(event),
+                    Err(err) => {
+                        error!("Key events channel closed [[{:?}]]", err);
+                        return;
+                    },
+                },
+                recv(trigger_events) -> event => match event {
+                    Ok(event) => self.handle_trigger_event(event),
+                    Err(err) => {
+                        error!("Trigger events channel closed [[{:?}]]", err);
+                        return;
+                    },
+                },
+                recv(messages) -> message => match message {
+                    Ok(message) => self.handle_message(message),
+                    Err(err) => {
+                        error!("Messages channel closed [[{:?}]]", err);
+                        return;
+                    },
+                },
+                default(Duration::from_millis(100)) => {},
             }
 
-            if let Ok(message) = self.messages.try_recv() {
-                match message {
-                    Message::NewSoundbite(data) => {
-                        self.responses.send(
-                            self.add_soundbite(data)
-                        ).map_err(
-                            |err| error!("Unable to send ack for new soundbite [[{:?}]]", err)
-                        ).unwrap();
+            self.process_watched_changes();
+            self.update_playback();
+            self.update_fades();
+            self.update_macros();
+            self.update_queue();
+            self.update_schedules();
+        }
+    }
+
+    fn handle_message(&mut self, message: Message) {
+        match message {
+            Message::NewSoundbite(data) => {
+                self.responses.send(
+                    self.add_soundbite(data)
+                ).map_err(
+                    |err| error!("Unable to send ack for new soundbite [[{:?}]]", err)
+                ).unwrap();
+            },
+            Message::ImportSoundbite { name, path } => {
+                let _ = self.import_soundbite(name, path);
+            },
+            Message::ImportFolder(folder) => {
+                let imported = self.import_folder(&folder);
+                self.events.publish(Event::FolderImportFinished {
+                    path: folder.display().to_string(),
+                    imported,
+                });
+            },
+            Message::WatchFolder(folder) => self.watch_folder(folder),
+            Message::SwitchProfile(name) => self.switch_profile(&name),
+            Message::PlaySoundbite(name) => match self.find_by_name(&name) {
+                Some(index) => self.play_soundbite(index),
+                None => error!("Soundbite named {} not found", name),
+            },
+            Message::StopSoundbite(Some(name)) => match self.find_by_name(&name) {
+                Some(index) => self.stop_soundbite(index),
+                None => error!("Soundbite named {} not found", name),
+            },
+            Message::StopSoundbite(None) => {
+                let count = self.soundbites.lock().unwrap().len();
+                for index in 0..count {
+                    self.stop_soundbite(index);
+                }
+                self.queue.lock().unwrap().stop();
+                self.publish_queue();
+                self.macro_runs.clear();
+            },
+            Message::PlayVariantSet(name) => self.play_target(&BindingTarget::VariantSet(name)),
+            Message::RunMacro(name) => self.run_macro(&name),
+            Message::StopMacro(name) => self.stop_macro(&name),
+            Message::Enqueue(name) => self.enqueue(name),
+            Message::QueueNext => {
+                let next = self.queue.lock().unwrap().current.map_or(0, |index| index + 1);
+                self.play_queue_entry(next, None);
+            },
+            Message::QueuePrevious => {
+                let previous = self.queue.lock().unwrap().current.map_or(0, |index| index.saturating_sub(1));
+                self.play_queue_entry(previous, None);
+            },
+            Message::ClearQueue => {
+                self.stop_queue_entry();
+                self.queue.lock().unwrap().clear();
+                self.publish_queue();
+            },
+            Message::PlayPlaylist(name) => self.play_playlist(&name),
+            Message::RefreshOutputDevice => self.refresh_output_device(),
+            Message::RenameSoundbite { name, new_name, done } => {
+                let result = self.rename_soundbite(name, new_name);
+                if let Err(err) = done.send(result) {
+                    error!("Unable to send rename result [[{:?}]]", err);
+                }
+            },
+            Message::ImportPack(path) => {
+                let event = match self.import_pack(&path) {
+                    Ok(report) => Event::PackImported {
+                        path: path.display().to_string(),
+                        report,
+                    },
+                    Err(err) => {
+                        error!("Unable to import pack {} [[{:?}]]", path.display(), err);
+                        Event::PackImportFailed {
+                            path: path.display().to_string(),
+                            error: err,
+                        }
                     },
+                };
+                self.events.publish(event);
+            },
+        }
+    }
+
+    fn handle_key_event(&mut self, event: KeyEvent) {
+        match event {
+            KeyEvent::Pressed(code) => {
+                let pause_keycode = self.settings.lock().unwrap().pause_keycode;
+                if pause_keycode == Some(code) {
+                    toggle_hotkeys(&self.events);
+                    return;
+                }
+
+                if !hotkeys_enabled() {
+                    return;
+                }
+
+                let switch_to = self.profiles.lock().unwrap()
+                    .find_by_switch_keycode(code)
+                    .map(|profile| profile.name.clone());
+                match switch_to {
+                    Some(profile) => self.switch_profile(&profile),
+                    None => self.trigger_binding(code, true),
                 }
+            },
+            KeyEvent::Released(code) => self.trigger_binding(code, false),
+        }
+    }
+
+    fn trigger_binding(&mut self, code: KeyTaskCode, pressed: bool) {
+        let binding = match self.soundbites_keytasks.lock().unwrap().get(&code) {
+            Some(binding) => binding.clone(),
+            None => {
+                if pressed {
+                    trace!("No soundbite linked to key code {}", code);
+                }
+                return;
+            },
+        };
+
+        self.fire_binding(BindingSource::Key(code), binding, pressed);
+    }
+
+    fn handle_trigger_event(&mut self, event: TriggerEvent) {
+        let (trigger, pressed) = match event {
+            TriggerEvent::Pressed(trigger) => (trigger, true),
+            TriggerEvent::Released(trigger) => (trigger, false),
+        };
+
+        if pressed && !hotkeys_enabled() {
+            return;
+        }
+
+        let binding = match self.trigger_bindings.lock().unwrap().get(&trigger) {
+            Some(binding) => binding.clone(),
+            None => return,
+        };
+
+        self.fire_binding(BindingSource::Trigger(trigger), binding, pressed);
+    }
+
+    fn fire_binding(&mut self, source: BindingSource, binding: KeyBinding, pressed: bool) {
+        match (binding.trigger, pressed) {
+            (TriggerMode::Press, true) | (TriggerMode::Hold, true) => {
+                self.play_target(&binding.target);
+            },
+            (TriggerMode::Release, false) if hotkeys_enabled() => {
+                self.play_target(&binding.target);
+            },
+            //Stops even when paused in between, not to leave it playing
+            (TriggerMode::Hold, false) => self.stop_target(&binding.target),
+            (TriggerMode::DoubleTap, true) if self.double_taps.tap(source, Instant::now()) => {
+                self.play_target(&binding.target);
+            },
+            _ => {},
+        }
+    }
+
+    fn play_target(&mut self, target: &BindingTarget) {
+        match target {
+            BindingTarget::Soundbite(index) => self.play_soundbite(*index),
+            BindingTarget::VariantSet(set) => {
+                let picked = {
+                    let soundbites = self.soundbites.lock().unwrap();
+                    self.variant_sets.lock().unwrap()
+                        .iter_mut()
+                        .find(|variant_set| variant_set.name == *set)
+                        .and_then(|variant_set| variant_set.pick(
+                            |name| soundbites.iter().any(|soundbite| soundbite.data.name == name)
+                        ))
+                };
+                match picked {
+                    Some(name) => match self.find_by_name(&name) {
+                        Some(index) => self.play_soundbite(index),
+                        None => error!("Soundbite named {} of variant set {} not found", name, set),
+                    },
+                    None => error!("Variant set named {} not found or without soundbites", set),
+                }
+            },
+            BindingTarget::Macro(name) => self.run_macro(name),
+        }
+    }
+
+    //Variant sets stop the variant picked last
+    fn stop_target(&mut self, target: &BindingTarget) {
+        match target {
+            BindingTarget::Soundbite(index) => self.stop_soundbite(*index),
+            BindingTarget::VariantSet(set) => {
+                let last = self.variant_sets.lock().unwrap()
+                    .iter()
+                    .find(|variant_set| variant_set.name == *set)
+                    .and_then(|variant_set| variant_set.last_picked().cloned());
+                if let Some(index) = last.and_then(|name| self.find_by_name(&name)) {
+                    self.stop_soundbite(index);
+                }
+            },
+            BindingTarget::Macro(name) => self.stop_macro(name),
+        }
+    }
+
+    //Starts the macro over when it's running already
+    fn run_macro(&mut self, name: &str) {
+        let run = self.macros.lock().unwrap()
+            .iter()
+            .find(|macro_| macro_.name == name)
+            .map(MacroRun::new);
+        match run {
+            Some(run) => {
+                self.macro_runs.retain(|other| other.name != name);
+                self.macro_runs.push(run);
+                trace!("Macro named {} started", name);
+                self.events.publish(Event::MacroStarted { name: name.to_string() });
+                self.update_macros();
+            },
+            None => error!("Macro named {} not found", name),
+        }
+    }
+
+    //Steps already run are not undone
+    fn stop_macro(&mut self, name: &str) {
+        self.macro_runs.retain(|run| run.name != name);
+    }
+
+    fn update_macros(&mut self) {
+        if self.macro_runs.is_empty() {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut runs = std::mem::take(&mut self.macro_runs);
+        for run in runs.iter_mut() {
+            for step in run.due_steps(now) {
+                self.run_macro_step(&run.name, step);
             }
+        }
+
+        runs.retain(|run| {
+            if !run.is_done() {
+                return true;
+            }
+            trace!("Macro named {} finished", run.name);
+            self.events.publish(Event::MacroFinished { name: run.name.clone() });
+            false
+        });
+        self.macro_runs = runs;
+    }
 
-            thread::sleep(Duration::from_millis(100));
+    fn run_macro_step(&mut self, name: &str, step: MacroStep) {
+        let index = step.soundbite().map(|soundbite| (soundbite.clone(), self.find_by_name(soundbite)));
+        if let Some((soundbite, None)) = index {
+            error!("Soundbite named {} of macro {} not found", soundbite, name);
+            return;
+        }
+        let index = index.and_then(|(_, index)| index);
+
+        match (step, index) {
+            (MacroStep::Play { .. }, Some(index)) => self.play_soundbite(index),
+            (MacroStep::Stop { .. }, Some(index)) => self.stop_soundbite(index),
+            (MacroStep::Stop { soundbite: None }, None) => {
+                let count = self.soundbites.lock().unwrap().len();
+                for index in 0..count {
+                    self.stop_soundbite(index);
+                }
+            },
+            //Looked up again under the lock, the index may be stale by now
+            (MacroStep::SetVolume { soundbite, volume }, Some(_)) => {
+                self.fades.retain(|fade| fade.name != soundbite);
+                let master_volume = self.profiles.lock().unwrap().master_volume();
+                match self.soundbites.lock().unwrap().iter().find(|s| s.data.name == soundbite) {
+                    Some(found) => found.set_gain(master_volume, volume),
+                    None => error!("Soundbite named {} of macro {} not found", soundbite, name),
+                }
+            },
+            (MacroStep::Fade { soundbite, volume, seconds }, Some(_)) => {
+                let master_volume = self.profiles.lock().unwrap().master_volume();
+                let gain = self.soundbites.lock().unwrap()
+                    .iter()
+                    .find(|s| s.data.name == soundbite)
+                    .map(|found| found.gain(master_volume));
+                match gain {
+                    Some(gain) => self.start_fade(
+                        Fade::new(soundbite, gain, volume, Duration::from_secs_f32(seconds), false)
+                    ),
+                    None => error!("Soundbite named {} of macro {} not found", soundbite, name),
+                }
+            },
+            (MacroStep::SwitchProfile { profile }, _) => self.switch_profile(&profile),
+            _ => {},
         }
     }
 
-    fn play_soundbite(&self, key_task_code: KeyTaskCode) -> Result<()> {
-        if let (Ok(soundbites), Ok(soundbites_keytasks)) = (
-            self.soundbites.try_lock(),
-            self.soundbites_keytasks.try_lock()
-        ) {
-            match soundbites_keytasks.get(&key_task_code) {
-                Some(index) => {
-                    if let Some(soundbite) = soundbites.get(*index) {
-                        soundbite.play();
-                        trace!("Soundbite linked to key code {} played", key_task_code);
-                        return Ok(());
-                    } else {
-                        bail!("Soundbites index out of bound")
+    fn play_soundbite(&mut self, index: usize) {
+        let choked = self.choke(index);
+
+        let soundbites = self.soundbites.lock().unwrap();
+        match soundbites.get(index) {
+            Some(soundbite) => {
+                soundbite.play(self.profiles.lock().unwrap().master_volume());
+                trace!("Soundbite named {} played", soundbite.data.name);
+                self.fades.retain(|fade| fade.name != soundbite.data.name);
+                self.playing.insert(soundbite.data.name.clone(), Instant::now());
+                self.events.publish(Event::PlaybackStarted { name: soundbite.data.name.clone() });
+            },
+            None => error!("Soundbites index {} out of bound", index),
+        }
+        drop(soundbites);
+
+        if let Some(fade_in) = choked {
+            self.start_fade(fade_in);
+        }
+    }
+
+    //Stops or fades out the other members of the soundbite choke group,
+    // returns the fade in of the soundbite when crossfading
+    fn choke(&mut self, index: usize) -> Option<Fade> {
+        let (name, others, crossfade) = {
+            let soundbites = self.soundbites.lock().unwrap();
+            let soundbite = soundbites.get(index)?;
+            let group = soundbite.data.choke_group.as_ref()?;
+            let others: Vec<String> = soundbites.iter()
+                .filter(|other| other.data.name != soundbite.data.name)
+                .filter(|other| other.data.choke_group.as_ref() == Some(group))
+                .filter(|other| other.is_playing())
+                .map(|other| other.data.name.clone())
+                .collect();
+            if others.is_empty() {
+                return None;
+            }
+
+            let crossfade = self.choke_groups.lock().unwrap()
+                .iter()
+                .find(|choke_group| choke_group.name == *group)
+                .and_then(|choke_group| choke_group.crossfade());
+            (soundbite.data.name.clone(), others, crossfade)
+        };
+
+        for other in others {
+            trace!("Soundbite named {} choked by {}", other, name);
+            match crossfade {
+                Some(duration) => self.start_fade(Fade::fade_out(other, duration)),
+                None => if let Some(other) = self.find_by_name(&other) {
+                    self.stop_soundbite(other);
+                },
+            }
+        }
+
+        crossfade.map(|duration| Fade::fade_in(name, duration))
+    }
+
+    fn stop_soundbite(&mut self, index: usize) {
+        if let Some(soundbite) = self.soundbites.lock().unwrap().get(index) {
+            if !soundbite.is_playing() {
+                return;
+            }
+
+            soundbite.stop();
+            trace!("Soundbite named {} stopped", soundbite.data.name);
+            self.playing.remove(&soundbite.data.name);
+            self.fades.retain(|fade| fade.name != soundbite.data.name);
+
+            //Stopping what the queue plays stops the queue
+            let mut queue = self.queue.lock().unwrap();
+            if queue.playing() == Some(&soundbite.data.name) {
+                queue.stop();
+            }
+            self.events.publish(Event::PlaybackStopped { name: soundbite.data.name.clone() });
+        }
+    }
+
+    //Publishes the position of the soundbites playing and notices the ones
+    // which got to the end
+    fn update_playback(&mut self) {
+        if self.playing.is_empty() || self.last_position_update.elapsed() < POSITION_INTERVAL {
+            return;
+        }
+        self.last_position_update = Instant::now();
+
+        let soundbites = self.soundbites.lock().unwrap();
+        let events = &self.events;
+        self.playing.retain(|name, started| {
+            let soundbite = match soundbites.iter().find(|soundbite| soundbite.data.name == *name) {
+                Some(soundbite) => soundbite,
+                //Removed or renamed while playing
+                None => return false,
+            };
+
+            if !soundbite.is_playing() {
+                trace!("Soundbite named {} finished", name);
+                events.publish(Event::PlaybackFinished { name: name.clone() });
+                return false;
+            }
+
+            let duration = soundbite.data.duration();
+            events.publish(Event::PlaybackPosition {
+                name: name.clone(),
+                position: (started.elapsed().as_secs_f32() * soundbite.data.speed).min(duration),
+                duration,
+            });
+            true
+        });
+    }
+
+    fn start_fade(&mut self, fade: Fade) {
+        self.fades.retain(|other| other.name != fade.name);
+        self.fades.push(fade);
+        self.update_fades();
+    }
+
+    fn update_fades(&mut self) {
+        if self.fades.is_empty() {
+            return;
+        }
+
+        let master_volume = self.profiles.lock().unwrap().master_volume();
+        let mut faded_out = Vec::new();
+        {
+            let soundbites = self.soundbites.lock().unwrap();
+            self.fades.retain(|fade| {
+                match soundbites.iter().find(|soundbite| soundbite.data.name == fade.name) {
+                    Some(soundbite) => soundbite.set_gain(master_volume, fade.gain()),
+                    None => return false,
+                }
+
+                if !fade.is_done() {
+                    return true;
+                }
+                if fade.stop {
+                    faded_out.push(fade.name.clone());
+                }
+                false
+            });
+        }
+
+        for name in faded_out {
+            if let Some(index) = self.find_by_name(&name) {
+                self.stop_soundbite(index);
+            }
+        }
+    }
+
+    //Starts the queue with the new entry when it was idle
+    fn enqueue(&mut self, name: String) {
+        let idle_at = {
+            let mut queue = self.queue.lock().unwrap();
+            queue.soundbites.push(name);
+            if queue.active {
+                None
+            } else {
+                Some(queue.soundbites.len() - 1)
+            }
+        };
+
+        match idle_at {
+            Some(index) => self.play_queue_entry(index, None),
+            None => self.publish_queue(),
+        }
+    }
+
+    fn play_playlist(&mut self, name: &str) {
+        let playlist = self.playlists.lock().unwrap()
+            .iter()
+            .find(|playlist| playlist.name == name)
+            .cloned();
+        let playlist = match playlist {
+            Some(playlist) => playlist,
+            None => {
+                error!("Playlist named {} not found", name);
+                return;
+            }
+        };
+
+        self.stop_queue_entry();
+        {
+            let mut queue = self.queue.lock().unwrap();
+            queue.clear();
+            queue.soundbites = playlist.soundbites;
+            queue.transition = playlist.transition;
+        }
+        trace!("Playlist named {} queued", name);
+        self.play_queue_entry(0, None);
+    }
+
+    fn stop_queue_entry(&mut self) {
+        let playing = self.queue.lock().unwrap().playing().cloned();
+        if let Some(index) = playing.and_then(|name| self.find_by_name(&name)) {
+            self.stop_soundbite(index);
+        }
+    }
+
+    //Plays the queue entry at index, fading out the entry playing when
+    // crossfading and stopping it otherwise. Past the end the queue stops
+    fn play_queue_entry(&mut self, index: usize, crossfade: Option<Duration>) {
+        let (name, previous) = {
+            let queue = self.queue.lock().unwrap();
+            (queue.soundbites.get(index).cloned(), queue.playing().cloned())
+        };
+
+        match (previous, crossfade) {
+            (Some(previous), Some(duration)) => self.start_fade(Fade::fade_out(previous, duration)),
+            (Some(_), None) => self.stop_queue_entry(),
+            (None, _) => {},
+        }
+
+        let name = match name {
+            Some(name) => name,
+            None => {
+                self.queue.lock().unwrap().stop();
+                self.publish_queue();
+                return;
+            }
+        };
+
+        {
+            let mut queue = self.queue.lock().unwrap();
+            queue.current = Some(index);
+            queue.active = true;
+            queue.next_at = None;
+        }
+
+        //A missing soundbite isn't playing, the next update moves on
+        match self.find_by_name(&name) {
+            Some(soundbite) => {
+                self.play_soundbite(soundbite);
+                if let Some(duration) = crossfade {
+                    self.start_fade(Fade::fade_in(name, duration));
+                }
+            },
+            None => error!("Queued soundbite named {} not found", name),
+        }
+        self.publish_queue();
+    }
+
+    //Moves the queue on once its entry finished, or before when crossfading
+    fn update_queue(&mut self) {
+        let (index, name, transition, next_at, is_last) = {
+            let queue = self.queue.lock().unwrap();
+            let (index, name) = match (queue.current, queue.playing()) {
+                (Some(index), Some(name)) => (index, name.clone()),
+                _ => return,
+            };
+            (index, name, queue.transition, queue.next_at, index + 1 >= queue.soundbites.len())
+        };
+
+        if let Some(next_at) = next_at {
+            if Instant::now() >= next_at {
+                self.play_queue_entry(index + 1, None);
+            }
+            return;
+        }
+
+        match (self.playing.get(&name), transition) {
+            (Some(started), Some(transition @ Transition::Crossfade { .. })) if !is_last => {
+                let remaining = match self.soundbites.lock().unwrap()
+                    .iter()
+                    .find(|soundbite| soundbite.data.name == name)
+                {
+                    Some(soundbite) => {
+                        let length = soundbite.data.duration() / soundbite.data.speed;
+                        Some((length - started.elapsed().as_secs_f32(), length))
+                    },
+                    None => None,
+                };
+
+                //Never longer than half of it, not to skip short ones
+                if let Some((remaining, length)) = remaining {
+                    let crossfade = transition.duration().min(Duration::from_secs_f32(length / 2f32));
+                    if remaining <= crossfade.as_secs_f32() {
+                        self.play_queue_entry(index + 1, Some(crossfade));
                     }
+                }
+            },
+            (Some(_), _) => {},
+            (None, Some(transition @ Transition::Gap { .. })) if !is_last => {
+                self.queue.lock().unwrap().next_at = Some(Instant::now() + transition.duration());
+            },
+            (None, _) => self.play_queue_entry(index + 1, None),
+        }
+    }
+
+    fn update_schedules(&mut self) {
+        let due = self.scheduler.lock().unwrap().take_due();
+        for schedule in due {
+            match self.find_by_name(&schedule.soundbite) {
+                Some(index) => {
+                    trace!("Schedule named {} fired", schedule.name);
+                    self.play_soundbite(index);
+                    self.events.publish(Event::ScheduleFired {
+                        name: schedule.name,
+                        soundbite: schedule.soundbite,
+                    });
                 },
+                None => error!(
+                    "Soundbite named {} of schedule {} not found",
+                    schedule.soundbite,
+                    schedule.name
+                ),
+            }
+        }
+    }
+
+    fn publish_queue(&self) {
+        let queue = self.queue.lock().unwrap().info();
+        self.events.publish(Event::QueueChanged { queue });
+    }
+
+    fn rename_soundbite(&mut self, name: String, new_name: String) -> Result<(), SoundManagerError> {
+        let mut soundbites = self.soundbites.lock().unwrap();
+        if soundbites.iter().any(|soundbite| soundbite.data.name == new_name) {
+            return Err(SoundManagerError::SoundbiteAlreadyExists(new_name));
+        }
+        let index = soundbites.iter()
+            .position(|soundbite| soundbite.data.name == name)
+            .ok_or_else(|| SoundManagerError::SoundbiteNotFound(name.clone()))?;
+
+        self.queue.lock().unwrap().rename(&name, &new_name);
+        for variant_set in self.variant_sets.lock().unwrap().iter_mut() {
+            variant_set.rename_soundbite(&name, &new_name);
+        }
+        for macro_ in self.macros.lock().unwrap().iter_mut() {
+            macro_.rename_soundbite(&name, &new_name);
+        }
+        self.scheduler.lock().unwrap().schedules.iter_mut()
+            .filter(|schedule| schedule.soundbite == name)
+            .for_each(|schedule| schedule.soundbite = new_name.clone());
+        for playlist in self.playlists.lock().unwrap().iter_mut() {
+            playlist.soundbites.iter_mut()
+                .filter(|soundbite| **soundbite == name)
+                .for_each(|soundbite| *soundbite = new_name.clone());
+        }
+
+        if let Some(started) = self.playing.remove(&name) {
+            self.playing.insert(new_name.clone(), started);
+        }
+        self.fades.iter_mut()
+            .filter(|fade| fade.name == name)
+            .for_each(|fade| fade.name = new_name.clone());
+
+        soundbites[index].data.name = new_name;
+        Ok(())
+    }
+
+    fn find_by_name(&self, name: &str) -> Option<usize> {
+        self.soundbites.lock().unwrap().iter().position(|soundbite| soundbite.data.name == name)
+    }
+
+    fn switch_profile(&mut self, name: &str) {
+        {
+            let mut soundbites_keytasks = self.soundbites_keytasks.lock().unwrap();
+            let mut profiles = self.profiles.lock().unwrap();
+            if profiles.active == name {
+                return;
+            }
+
+            let keytasks = match profiles.get(name) {
+                Some(profile) => profile.keytasks.clone(),
                 None => {
-                    error!("No soundbite linked to key code {}", key_task_code);
-                    bail!("No soundbite linked to key code {}", key_task_code)
-                },
+                    error!("Profile named {} not found", name);
+                    return;
+                }
+            };
+
+            profiles.active_mut().keytasks = std::mem::replace(
+                &mut *soundbites_keytasks,
+                keytasks
+            );
+            profiles.active = name.to_string();
+            self.settings.lock().unwrap().active_profile = Some(name.to_string());
+        }
+
+        self.refresh_output_device();
+
+        trace!("Switched to profile {}", name);
+        self.events.publish(Event::ProfileSwitched { name: name.to_string() });
+    }
+
+    //Reopens the output stream when the active profile wants another device
+    fn refresh_output_device(&mut self) {
+        let output_device = self.profiles.lock().unwrap().active().output_device.clone();
+        if output_device == self.output_device {
+            return;
+        }
+
+        let (stream, stream_handle) = match open_output_stream(output_device.as_deref()) {
+            Ok(output) => output,
+            Err(err) => {
+                error!("Unable to open output stream [[{:?}]]", err);
+                return;
+            }
+        };
+
+        for soundbite in self.soundbites.lock().unwrap().iter_mut() {
+            if let Err(err) = soundbite.init_sink(&stream_handle) {
+                error!(
+                    "Unable to init sink for soundbite named {} [[{:?}]]",
+                    soundbite.data.name,
+                    err,
+                );
             }
-        } else {
-            bail!("Locked")
         }
+
+        self._stream = stream;
+        self.stream_handle = stream_handle;
+        self.output_device = output_device;
     }
 
     fn add_soundbite(
@@ -164,4 +1077,282 @@ impl SoundManager {
         soundbites.push(soundbite);
         Ok(soundbite_name)
     }
+
+    fn import_soundbite(
+        &self,
+        name: String,
+        path: PathBuf
+    ) -> Result<String, SoundManagerError> {
+        self.events.publish(Event::ImportStarted {
+            name: name.clone(),
+            path: path.display().to_string(),
+        });
+
+        let result = self.decode_soundbite(name.clone(), &path)
+            .and_then(|mut data| {
+                data.source = Some(path.clone());
+                self.add_soundbite(data)
+            });
+
+        match &result {
+            Ok(name) => {
+                trace!("Soundbite named {} imported from {}", name, path.display());
+                self.events.publish(Event::ImportFinished { name: name.clone() });
+            },
+            Err(err) => {
+                error!(
+                    "Unable to import soundbite named {} from {} [[{:?}]]",
+                    name,
+                    path.display(),
+                    err
+                );
+                self.events.publish(Event::ImportFailed {
+                    name,
+                    error: err.clone()
+                });
+            }
+        }
+
+        result
+    }
+
+    //Imports every supported file not imported yet, names colliding with
+    // existing soundbites get a numeric suffix
+    fn import_folder(&self, folder: &Path) -> Vec<String> {
+        let files = match import::list_folder(folder) {
+            Ok(files) => files,
+            Err(err) => {
+                error!("Unable to list folder {} [[{:?}]]", folder.display(), err);
+                return Vec::new();
+            }
+        };
+
+        let mut imported = Vec::new();
+        for path in files {
+            if self.find_by_source(&path).is_some() {
+                continue;
+            }
+
+            if let Some(name) = self.unique_name_for(&path) {
+                if let Ok(name) = self.import_soundbite(name, path) {
+                    imported.push(name);
+                }
+            }
+        }
+
+        imported
+    }
+
+    //Merges the pack into the library, nothing already there gets replaced:
+    // clashing names and key codes are reported as conflicts instead
+    fn import_pack(&self, path: &Path) -> Result<PackImportReport, SoundManagerError> {
+        let mut pack = Pack::open(path)?;
+        let mut report = PackImportReport::default();
+        let limits = self.settings.lock().unwrap().import_limits.clone();
+
+        {
+            let mut categories = self.categories.lock().unwrap();
+            for category in pack.manifest.categories.iter() {
+                if !categories.contains(category) {
+                    categories.push(category.clone());
+                }
+            }
+        }
+
+        for entry in pack.manifest.soundbites.clone() {
+            if self.soundbites.lock().unwrap().iter().any(|s| s.data.name == entry.name) {
+                report.conflicts.push(PackConflict::NameUsed { name: entry.name });
+                continue;
+            }
+
+            match pack.read_soundbite(&entry, &limits).and_then(|data| self.add_soundbite(data)) {
+                Ok(name) => report.imported.push(name),
+                Err(err) => {
+                    error!("Unable to import {} from pack [[{:?}]]", entry.name, err);
+                    report.conflicts.push(PackConflict::rejected(&entry, err));
+                }
+            }
+        }
+
+        let soundbites = self.soundbites.lock().unwrap();
+        let mut soundbites_keytasks = self.soundbites_keytasks.lock().unwrap();
+        for binding in pack.manifest.bindings.iter() {
+            if !report.imported.contains(&binding.soundbite) {
+                continue;
+            }
+
+            if let Some(used) = soundbites_keytasks.get(&binding.keycode) {
+                report.conflicts.push(PackConflict::KeyTaskUsed {
+                    soundbite: binding.soundbite.clone(),
+                    keycode: binding.keycode,
+                    used_by: binding_target_name(&soundbites, &used.target),
+                });
+            } else if let Some(index) = soundbites.iter().position(
+                |s| s.data.name == binding.soundbite
+            ) {
+                soundbites_keytasks.insert(binding.keycode, KeyBinding {
+                    target: BindingTarget::Soundbite(index),
+                    swallow: binding.swallow,
+                    trigger: binding.trigger,
+                });
+            }
+        }
+
+        for binding in pack.dropped_bindings.iter() {
+            if report.imported.contains(&binding.soundbite) {
+                report.conflicts.push(PackConflict::KeyCodeOutdated {
+                    soundbite: binding.soundbite.clone(),
+                    keycode: binding.keycode,
+                });
+            }
+        }
+
+        trace!(
+            "Pack {} imported, {} soundbites, {} conflicts",
+            path.display(),
+            report.imported.len(),
+            report.conflicts.len()
+        );
+        Ok(report)
+    }
+
+    fn unique_name_for(&self, path: &Path) -> Option<String> {
+        let name = import::name_from_path(path)?;
+        let soundbites = self.soundbites.lock().unwrap();
+        Some(import::unique_name(name, |candidate| {
+            soundbites.iter().any(|s| s.data.name == candidate)
+        }))
+    }
+
+    fn find_by_source(&self, path: &Path) -> Option<usize> {
+        self.soundbites.lock().unwrap().iter().position(
+            |s| s.data.source.as_deref() == Some(path)
+        )
+    }
+
+    fn watch_folder(&mut self, folder: Option<PathBuf>) {
+        self.watcher = None;
+
+        if let Some(folder) = folder {
+            match FolderWatcher::new(folder.clone()) {
+                Ok(watcher) => {
+                    trace!("Watching folder {}", folder.display());
+                    self.watcher = Some(watcher);
+                },
+                Err(err) => {
+                    error!("Unable to watch folder {} [[{:?}]]", folder.display(), err);
+                    return;
+                }
+            }
+
+            //Catch up with what changed while the folder wasn't watched
+            let vanished: Vec<PathBuf> = self.soundbites.lock().unwrap()
+                .iter()
+                .filter_map(|s| s.data.source.clone())
+                .filter(|source| source.parent() == Some(folder.as_path()) && !source.exists())
+                .collect();
+            for path in vanished {
+                self.remove_by_source(&path);
+            }
+
+            let imported = self.import_folder(&folder);
+            self.events.publish(Event::FolderImportFinished {
+                path: folder.display().to_string(),
+                imported,
+            });
+        }
+    }
+
+    fn process_watched_changes(&mut self) {
+        let changes = match self.watcher.as_mut() {
+            Some(watcher) => watcher.poll(),
+            None => return,
+        };
+
+        for path in changes {
+            if path.is_file() && import::is_supported(&path) {
+                if self.find_by_source(&path).is_some() {
+                    self.reload_soundbite(&path);
+                } else if let Some(name) = self.unique_name_for(&path) {
+                    let _ = self.import_soundbite(name, path);
+                }
+            } else if !path.exists() {
+                self.remove_by_source(&path);
+            }
+        }
+    }
+
+    fn reload_soundbite(&self, path: &Path) {
+        let limits = self.settings.lock().unwrap().import_limits.clone();
+        let audio = match import::decode_file(path, &limits, |_, _| {}) {
+            Ok(audio) => audio,
+            Err(err) => {
+                error!("Unable to reload soundbite from {} [[{:?}]]", path.display(), err);
+                return;
+            }
+        };
+
+        let mut soundbites = self.soundbites.lock().unwrap();
+        if let Some(soundbite) = soundbites.iter_mut().find(
+            |s| s.data.source.as_deref() == Some(path)
+        ) {
+            soundbite.data.set_samples(audio.buffer, audio.channels, audio.sample_rate);
+            trace!("Soundbite named {} reloaded from {}", soundbite.data.name, path.display());
+            self.events.publish(Event::SoundbiteUpdated {
+                name: soundbite.data.name.clone()
+            });
+        }
+    }
+
+    fn remove_by_source(&self, path: &Path) {
+        let mut soundbites = self.soundbites.lock().unwrap();
+        if let Some(index) = soundbites.iter().position(
+            |s| s.data.source.as_deref() == Some(path)
+        ) {
+            let mut soundbites_keytasks = self.soundbites_keytasks.lock().unwrap();
+            let mut trigger_bindings = self.trigger_bindings.lock().unwrap();
+            let mut profiles = self.profiles.lock().unwrap();
+            let soundbite = remove_soundbite(
+                &mut soundbites,
+                &mut soundbites_keytasks,
+                &mut trigger_bindings,
+                &mut profiles,
+                index
+            );
+            trace!("Soundbite named {} removed, {} is gone", soundbite.data.name, path.display());
+            self.events.publish(Event::SoundbiteRemoved {
+                name: soundbite.data.name
+            });
+        }
+    }
+
+    fn decode_soundbite(
+        &self,
+        name: String,
+        path: &Path
+    ) -> Result<SoundbiteData, SoundManagerError> {
+        if self.soundbites.lock().unwrap().iter().any(|s| s.data.name == name) {
+            return Err(SoundManagerError::NewSoundbiteError(
+                NewSoundbiteError::NameUsed(name)
+            ));
+        }
+
+        let limits = self.settings.lock().unwrap().import_limits.clone();
+        let audio = import::decode_file(path, &limits, |decoded, progress| {
+            self.events.publish(Event::ImportProgress {
+                name: name.clone(),
+                decoded,
+                progress,
+            });
+        }).map_err(SoundManagerError::NewSoundbiteError)?;
+
+        Ok(SoundbiteData::from_samples(
+            name,
+            audio.buffer,
+            audio.channels,
+            audio.sample_rate,
+            1f32,
+            1f32
+        ))
+    }
 }
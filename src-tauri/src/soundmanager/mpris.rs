@@ -0,0 +1,549 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use anyhow::Result;
+use log::{trace, error};
+use zbus::{
+    blocking::{Connection, ConnectionBuilder},
+    dbus_interface,
+    fdo,
+    names::BusName,
+    zvariant::{ObjectPath, OwnedValue, Value},
+};
+
+use super::{
+    control,
+    events::Event,
+    key_hook,
+    soundstate::SoundState,
+    utils::SoundManagerError,
+};
+
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.soundbar";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+const CONTROL_INTERFACE: &str = "org.soundbar.Control";
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+//MPRIS mutes at 0 which isn't a valid master volume, the lowest one that is
+// stands in for it
+const MIN_VOLUME: f64 = 0.001;
+const MAX_VOLUME: f64 = 200f64;
+
+//Soundbites playing as far as playback events tell
+#[derive(Default)]
+struct NowPlaying {
+    //Most recently started last
+    playing: Vec<String>,
+    //Replayed by Play, e.g. from a media key
+    last: Option<String>,
+}
+
+impl NowPlaying {
+    fn status(&self) -> &'static str {
+        if self.playing.is_empty() {
+            "Stopped"
+        } else {
+            "Playing"
+        }
+    }
+
+    fn metadata(&self, state: &SoundState) -> HashMap<String, OwnedValue> {
+        let mut metadata = HashMap::new();
+        let title = self.playing.last().or(self.last.as_ref());
+        let index = title.and_then(|title| {
+            state.soundbites.lock().unwrap()
+                .iter()
+                .position(|soundbite| soundbite.data.name == *title)
+        });
+
+        let track_id = match index {
+            Some(index) => format!("/org/soundbar/soundbite/{}", index),
+            None => NO_TRACK.to_string(),
+        };
+        if let Ok(track_id) = ObjectPath::try_from(track_id) {
+            metadata.insert("mpris:trackid".to_string(), Value::from(track_id).into());
+        }
+        if let Some(title) = title {
+            metadata.insert("xesam:title".to_string(), Value::from(title.clone()).into());
+        }
+
+        metadata
+    }
+}
+
+fn to_fdo(err: SoundManagerError) -> fdo::Error {
+    fdo::Error::Failed(err.to_string())
+}
+
+struct MediaPlayer;
+
+#[dbus_interface(name = "org.mpris.MediaPlayer2")]
+impl MediaPlayer {
+    fn raise(&self) {}
+
+    fn quit(&self) {}
+
+    #[dbus_interface(property)]
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    #[dbus_interface(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[dbus_interface(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[dbus_interface(property)]
+    fn identity(&self) -> &str {
+        "Soundbar"
+    }
+
+    #[dbus_interface(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[dbus_interface(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+//Soundbites can't be paused, pausing stops them
+struct Player {
+    state: SoundState,
+    now_playing: Arc<Mutex<NowPlaying>>,
+}
+
+#[dbus_interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    fn play(&self) -> fdo::Result<()> {
+        let last = self.now_playing.lock().unwrap().last.clone();
+        match last {
+            Some(name) => control::play(&self.state, &name).map_err(to_fdo),
+            None => Ok(()),
+        }
+    }
+
+    fn pause(&self) -> fdo::Result<()> {
+        self.stop()
+    }
+
+    fn play_pause(&self) -> fdo::Result<()> {
+        if self.now_playing.lock().unwrap().playing.is_empty() {
+            self.play()
+        } else {
+            self.stop()
+        }
+    }
+
+    fn stop(&self) -> fdo::Result<()> {
+        control::stop(&self.state, None).map_err(to_fdo)
+    }
+
+    //Move through the queue
+    fn next(&self) -> fdo::Result<()> {
+        control::queue_next(&self.state).map_err(to_fdo)
+    }
+
+    fn previous(&self) -> fdo::Result<()> {
+        control::queue_previous(&self.state).map_err(to_fdo)
+    }
+
+    fn seek(&self, _offset: i64) {}
+
+    fn set_position(&self, _track_id: ObjectPath<'_>, _position: i64) {}
+
+    fn open_uri(&self, _uri: &str) -> fdo::Result<()> {
+        Err(fdo::Error::NotSupported("Soundbites are imported from the app".to_string()))
+    }
+
+    #[dbus_interface(property)]
+    fn playback_status(&self) -> &str {
+        self.now_playing.lock().unwrap().status()
+    }
+
+    #[dbus_interface(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        self.now_playing.lock().unwrap().metadata(&self.state)
+    }
+
+    //Master volume of the active profile
+    #[dbus_interface(property)]
+    fn volume(&self) -> f64 {
+        control::master_volume(&self.state).unwrap_or(1f32) as f64
+    }
+
+    #[dbus_interface(property)]
+    fn set_volume(&self, volume: f64) {
+        if volume.is_nan() {
+            error!("Unable to set volume {} from MPRIS", volume);
+            return;
+        }
+
+        let volume = volume.clamp(MIN_VOLUME, MAX_VOLUME);
+        if let Err(err) = control::set_master_volume(&self.state, Some(volume as f32)) {
+            error!("Unable to set volume {} from MPRIS [[{:?}]]", volume, err);
+        }
+    }
+
+    #[dbus_interface(property)]
+    fn rate(&self) -> f64 {
+        1f64
+    }
+
+    #[dbus_interface(property)]
+    fn minimum_rate(&self) -> f64 {
+        1f64
+    }
+
+    #[dbus_interface(property)]
+    fn maximum_rate(&self) -> f64 {
+        1f64
+    }
+
+    #[dbus_interface(property)]
+    fn position(&self) -> i64 {
+        0
+    }
+
+    #[dbus_interface(property)]
+    fn can_go_next(&self) -> bool {
+        true
+    }
+
+    #[dbus_interface(property)]
+    fn can_go_previous(&self) -> bool {
+        true
+    }
+
+    #[dbus_interface(property)]
+    fn can_play(&self) -> bool {
+        self.now_playing.lock().unwrap().last.is_some()
+    }
+
+    #[dbus_interface(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[dbus_interface(property)]
+    fn can_seek(&self) -> bool {
+        false
+    }
+
+    #[dbus_interface(property)]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+//What MPRIS can't express, soundbites by name and hotkeys
+struct Control {
+    state: SoundState,
+}
+
+#[dbus_interface(name = "org.soundbar.Control")]
+impl Control {
+    fn play(&self, name: &str) -> fdo::Result<()> {
+        control::play(&self.state, name).map_err(to_fdo)
+    }
+
+    //Every playing soundbite when name is empty
+    fn stop(&self, name: &str) -> fdo::Result<()> {
+        let name = Some(name).filter(|name| !name.is_empty());
+        control::stop(&self.state, name).map_err(to_fdo)
+    }
+
+    fn list(&self) -> Vec<String> {
+        control::list(&self.state)
+            .into_iter()
+            .map(|soundbite| soundbite.info.name)
+            .collect()
+    }
+
+    //Returns the new state
+    fn toggle_hotkeys(&self) -> bool {
+        super::toggle_hotkeys(&self.state.events)
+    }
+
+    #[dbus_interface(property)]
+    fn hotkeys_enabled(&self) -> bool {
+        key_hook::hotkeys_enabled()
+    }
+
+    #[dbus_interface(property)]
+    fn set_hotkeys_enabled(&self, enabled: bool) {
+        super::set_hotkeys_enabled(&self.state.events, enabled);
+    }
+}
+
+//Owns the bus name on the session bus, properties changes are signalled
+// from playback events
+pub fn start(state: &SoundState) -> Result<()> {
+    serve(ConnectionBuilder::session()?, state)
+}
+
+fn serve(builder: ConnectionBuilder<'_>, state: &SoundState) -> Result<()> {
+    let now_playing = Arc::new(Mutex::new(NowPlaying::default()));
+    let connection = builder
+        .name(BUS_NAME)?
+        .serve_at(OBJECT_PATH, MediaPlayer)?
+        .serve_at(OBJECT_PATH, Player {
+            state: state.clone(),
+            now_playing: now_playing.clone(),
+        })?
+        .serve_at(OBJECT_PATH, Control {
+            state: state.clone(),
+        })?
+        .build()?;
+
+    let events = state.events.subscribe();
+    let state = state.clone();
+    thread::spawn(move || {
+        for event in events.iter() {
+            let result = match event {
+                Event::PlaybackStarted { name } => {
+                    let mut now_playing = now_playing.lock().unwrap();
+                    now_playing.playing.retain(|playing| *playing != name);
+                    now_playing.playing.push(name.clone());
+                    now_playing.last = Some(name);
+                    player_changed(&connection, &now_playing, &state)
+                },
+                Event::PlaybackStopped { name } | Event::PlaybackFinished { name } => {
+                    let mut now_playing = now_playing.lock().unwrap();
+                    now_playing.playing.retain(|playing| *playing != name);
+                    player_changed(&connection, &now_playing, &state)
+                },
+                Event::HotkeysToggled { enabled } => properties_changed(
+                    &connection,
+                    CONTROL_INTERFACE,
+                    HashMap::from([("HotkeysEnabled", Value::from(enabled))])
+                ),
+                _ => Ok(()),
+            };
+
+            if let Err(err) = result {
+                error!("Unable to signal MPRIS properties change [[{:?}]]", err);
+            }
+        }
+    });
+
+    trace!("MPRIS interface registered as {}", BUS_NAME);
+    Ok(())
+}
+
+fn player_changed(
+    connection: &Connection,
+    now_playing: &NowPlaying,
+    state: &SoundState
+) -> zbus::Result<()> {
+    properties_changed(connection, PLAYER_INTERFACE, HashMap::from([
+        ("PlaybackStatus", Value::from(now_playing.status())),
+        ("Metadata", Value::from(now_playing.metadata(state))),
+        ("CanPlay", Value::from(now_playing.last.is_some())),
+    ]))
+}
+
+fn properties_changed(
+    connection: &Connection,
+    interface: &str,
+    changed: HashMap<&str, Value<'_>>
+) -> zbus::Result<()> {
+    connection.emit_signal(
+        None::<BusName<'_>>,
+        OBJECT_PATH,
+        "org.freedesktop.DBus.Properties",
+        "PropertiesChanged",
+        &(interface, changed, Vec::<&str>::new()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader},
+        process::{Child, Command, Stdio},
+        time::{Duration, Instant},
+    };
+
+    use crossbeam::channel::Receiver;
+    use zbus::blocking::Proxy;
+
+    use super::*;
+    use super::super::{
+        soundbite::{Soundbite, SoundbiteData},
+        soundstate::Message,
+    };
+
+    const TIMEOUT: Duration = Duration::from_secs(2);
+
+    //Private session bus, killed with the test
+    struct Bus {
+        daemon: Child,
+        address: String,
+    }
+
+    impl Bus {
+        fn start() -> Bus {
+            let mut daemon = Command::new("dbus-daemon")
+                .args(["--session", "--print-address", "--nofork"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .expect("dbus-daemon not available");
+
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.take().unwrap()).read_line(&mut address).unwrap();
+            Bus {
+                daemon,
+                address: address.trim().to_string(),
+            }
+        }
+
+        fn connect(&self) -> Connection {
+            ConnectionBuilder::address(self.address.as_str()).unwrap().build().unwrap()
+        }
+    }
+
+    impl Drop for Bus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    struct Server {
+        _bus: Bus,
+        state: SoundState,
+        messages: Receiver<Message>,
+        client: Connection,
+    }
+
+    impl Server {
+        fn start() -> Server {
+            let data = SoundbiteData::from_samples("airhorn".to_string(), vec![0; 10], 1, 44100, 1f32, 1f32);
+            let (state, messages) = SoundState::for_tests(vec![Soundbite::from_data(data)]);
+
+            let bus = Bus::start();
+            let builder = ConnectionBuilder::address(bus.address.as_str()).unwrap();
+            serve(builder, &state).unwrap();
+
+            Server {
+                client: bus.connect(),
+                _bus: bus,
+                state,
+                messages,
+            }
+        }
+
+        fn proxy(&self, interface: &'static str) -> Proxy<'_> {
+            Proxy::new(&self.client, BUS_NAME, OBJECT_PATH, interface).unwrap()
+        }
+
+        fn message(&self) -> Message {
+            self.messages.recv_timeout(TIMEOUT).expect("no message")
+        }
+
+        //Properties follow events from another thread
+        fn wait_for_status(&self, status: &str) {
+            let player = self.proxy(PLAYER_INTERFACE);
+            let deadline = Instant::now() + TIMEOUT;
+            while player.get_property::<String>("PlaybackStatus").unwrap() != status {
+                assert!(Instant::now() < deadline, "playback status never became {}", status);
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+    }
+
+    #[test]
+    fn control_plays_and_stops_by_name() {
+        let server = Server::start();
+        let control = server.proxy(CONTROL_INTERFACE);
+
+        control.call_method("Play", &("airhorn",)).unwrap();
+        assert!(matches!(server.message(), Message::PlaySoundbite(name) if name == "airhorn"));
+
+        control.call_method("Stop", &("airhorn",)).unwrap();
+        assert!(matches!(server.message(), Message::StopSoundbite(Some(name)) if name == "airhorn"));
+
+        control.call_method("Stop", &("",)).unwrap();
+        assert!(matches!(server.message(), Message::StopSoundbite(None)));
+    }
+
+    #[test]
+    fn control_rejects_unknown_soundbites() {
+        let server = Server::start();
+        let control = server.proxy(CONTROL_INTERFACE);
+
+        assert!(control.call_method("Play", &("foghorn",)).is_err());
+        assert!(server.messages.try_recv().is_err());
+    }
+
+    #[test]
+    fn control_lists_soundbites() {
+        let server = Server::start();
+        let names: Vec<String> = server.proxy(CONTROL_INTERFACE).call("List", &()).unwrap();
+        assert_eq!(names, vec!["airhorn".to_string()]);
+    }
+
+    #[test]
+    fn control_toggles_hotkeys() {
+        let server = Server::start();
+        let control = server.proxy(CONTROL_INTERFACE);
+
+        let enabled = key_hook::hotkeys_enabled();
+        let toggled: bool = control.call("ToggleHotkeys", &()).unwrap();
+        assert_eq!(toggled, !enabled);
+        assert_eq!(key_hook::hotkeys_enabled(), !enabled);
+
+        let toggled: bool = control.call("ToggleHotkeys", &()).unwrap();
+        assert_eq!(toggled, enabled);
+    }
+
+    #[test]
+    fn player_follows_playback_events() {
+        let server = Server::start();
+        let player = server.proxy(PLAYER_INTERFACE);
+        assert_eq!(player.get_property::<String>("PlaybackStatus").unwrap(), "Stopped");
+
+        server.state.events.publish(Event::PlaybackStarted { name: "airhorn".to_string() });
+        server.wait_for_status("Playing");
+
+        let metadata: HashMap<String, OwnedValue> = player.get_property("Metadata").unwrap();
+        let title: String = metadata["xesam:title"].clone().try_into().unwrap();
+        assert_eq!(title, "airhorn");
+        let track_id: ObjectPath<'_> = metadata["mpris:trackid"].clone().try_into().unwrap();
+        assert_eq!(track_id.as_str(), "/org/soundbar/soundbite/0");
+
+        server.state.events.publish(Event::PlaybackFinished { name: "airhorn".to_string() });
+        server.wait_for_status("Stopped");
+
+        //Play replays the last soundbite
+        player.call_method("Play", &()).unwrap();
+        assert!(matches!(server.message(), Message::PlaySoundbite(name) if name == "airhorn"));
+    }
+
+    #[test]
+    fn player_volume_is_clamped() {
+        let server = Server::start();
+        let player = server.proxy(PLAYER_INTERFACE);
+
+        player.set_property("Volume", 0.5f64).unwrap();
+        assert_eq!(control::master_volume(&server.state), Some(0.5));
+
+        player.set_property("Volume", 0f64).unwrap();
+        assert_eq!(control::master_volume(&server.state), Some(MIN_VOLUME as f32));
+
+        player.set_property("Volume", 1000f64).unwrap();
+        assert_eq!(control::master_volume(&server.state), Some(MAX_VOLUME as f32));
+    }
+}
@@ -0,0 +1,486 @@
+use std::{
+    io::ErrorKind,
+    io,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crossbeam::channel::RecvTimeoutError;
+use log::{trace, error};
+use once_cell::sync::Lazy;
+use rosc::{
+    decoder,
+    encoder,
+    OscMessage,
+    OscPacket,
+    OscType,
+};
+
+use super::{
+    control,
+    events::Event,
+    settings::OscSettings,
+    soundstate::SoundState,
+    utils::SoundManagerError,
+};
+
+const ADDRESS_PREFIX: &str = "/soundbar";
+//How often the threads check whether the server was stopped
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+const MAX_PACKET_SIZE: usize = rosc::decoder::MTU;
+
+//Dropping it stops both the server and the notifications
+struct OscServer {
+    running: Arc<AtomicBool>,
+    //Joined on drop so the port is free again for a restart
+    receiver: Option<JoinHandle<()>>,
+}
+
+impl Drop for OscServer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(receiver) = self.receiver.take() {
+            let _ = receiver.join();
+        }
+    }
+}
+
+static OSC_SERVER: Lazy<Mutex<Option<OscServer>>> = Lazy::new(|| Mutex::new(None));
+
+//Stops the running server, if any, and starts a new one when enabled
+pub fn restart(state: &SoundState, settings: &OscSettings) -> Result<(), SoundManagerError> {
+    let mut osc_server = OSC_SERVER.lock().unwrap();
+    *osc_server = None;
+
+    if !settings.enabled {
+        return Ok(());
+    }
+
+    let targets = resolve_targets(&settings.targets)?;
+
+    let address = if settings.allow_lan {
+        Ipv4Addr::UNSPECIFIED
+    } else {
+        Ipv4Addr::LOCALHOST
+    };
+    let socket = UdpSocket::bind(SocketAddr::from((address, settings.port)))
+        .and_then(|socket| {
+            socket.set_read_timeout(Some(POLL_INTERVAL))?;
+            Ok(socket)
+        })
+        .map_err(|err| {
+            error!("Unable to start OSC server on port {} [[{:?}]]", settings.port, err);
+            SoundManagerError::OscUnavailable(err.to_string())
+        })?;
+    let notifier = Notifier::bind(targets)
+        .map_err(|err| SoundManagerError::OscUnavailable(err.to_string()))?;
+
+    let running = Arc::new(AtomicBool::new(true));
+    let receiver = {
+        let state = state.clone();
+        let running = running.clone();
+        thread::spawn(move || receive(socket, &state, &running))
+    };
+    if !notifier.targets.is_empty() {
+        let events = state.events.subscribe();
+        let running = running.clone();
+        thread::spawn(move || {
+            while running.load(Ordering::SeqCst) {
+                match events.recv_timeout(POLL_INTERVAL) {
+                    Ok(event) => notifier.notify(&event),
+                    Err(RecvTimeoutError::Timeout) => {},
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+        });
+    }
+
+    trace!("OSC server listening on {}:{}", address, settings.port);
+    *osc_server = Some(OscServer {
+        running,
+        receiver: Some(receiver),
+    });
+    Ok(())
+}
+
+fn resolve_targets(targets: &[String]) -> Result<Vec<SocketAddr>, SoundManagerError> {
+    targets.iter()
+        .map(|target| {
+            target.to_socket_addrs()
+                .ok()
+                .and_then(|mut addresses| addresses.next())
+                .ok_or_else(|| SoundManagerError::InvalidOscTarget(target.clone()))
+        })
+        .collect()
+}
+
+fn receive(socket: UdpSocket, state: &SoundState, running: &AtomicBool) {
+    let mut buffer = [0u8; MAX_PACKET_SIZE];
+    while running.load(Ordering::SeqCst) {
+        let size = match socket.recv_from(&mut buffer) {
+            Ok((size, _)) => size,
+            Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+            Err(err) => {
+                error!("Unable to receive OSC packet [[{:?}]]", err);
+                continue;
+            }
+        };
+
+        match decoder::decode_udp(&buffer[..size]) {
+            Ok((_, packet)) => handle_packet(packet, state),
+            Err(err) => error!("Invalid OSC packet [[{:?}]]", err),
+        }
+    }
+
+    trace!("OSC server stopped");
+}
+
+fn handle_packet(packet: OscPacket, state: &SoundState) {
+    match packet {
+        OscPacket::Message(message) => {
+            if let Err(err) = handle_message(&message, state) {
+                error!("Unable to handle OSC message {} [[{:?}]]", message.addr, err);
+            }
+        },
+        OscPacket::Bundle(bundle) => {
+            for packet in bundle.content {
+                handle_packet(packet, state);
+            }
+        },
+    }
+}
+
+//The name comes from the address, e.g. /soundbar/play/airhorn, or from
+// a string argument for names OSC addresses can't hold
+fn handle_message(message: &OscMessage, state: &SoundState) -> Result<(), SoundManagerError> {
+    let path = match message.addr.strip_prefix(ADDRESS_PREFIX) {
+        Some(path) => path.trim_start_matches('/'),
+        None => return Ok(()),
+    };
+    let (action, name) = match path.split_once('/') {
+        Some((action, name)) => (action, Some(name.to_string())),
+        None => (path, None),
+    };
+    let name = name.or_else(|| string_arg(&message.args));
+
+    match action {
+        //Buttons send 1 on press and 0 on release, only presses count
+        "play" | "stop" if is_release(&message.args) => Ok(()),
+        "play" => match name {
+            Some(name) => control::play(state, &name),
+            None => Ok(()),
+        },
+        "stop" => control::stop(state, name.as_deref()),
+        "volume" => match (name, float_arg(&message.args)) {
+            (Some(name), Some(volume)) => control::set_volume(state, &name, volume),
+            (None, volume) => control::set_master_volume(state, volume),
+            (Some(_), None) => Err(SoundManagerError::InvalidVolumeValue),
+        },
+        _ => {
+            trace!("Unknown OSC address {}", message.addr);
+            Ok(())
+        },
+    }
+}
+
+fn string_arg(args: &[OscType]) -> Option<String> {
+    args.iter().find_map(|arg| match arg {
+        OscType::String(value) => Some(value.clone()),
+        _ => None,
+    })
+}
+
+fn float_arg(args: &[OscType]) -> Option<f32> {
+    args.iter().find_map(|arg| match arg {
+        OscType::Float(value) => Some(*value),
+        OscType::Double(value) => Some(*value as f32),
+        OscType::Int(value) => Some(*value as f32),
+        _ => None,
+    })
+}
+
+fn is_release(args: &[OscType]) -> bool {
+    match args.first() {
+        Some(OscType::Bool(value)) => !value,
+        Some(OscType::Int(_) | OscType::Float(_) | OscType::Double(_)) => {
+            float_arg(&args[..1]) == Some(0f32)
+        },
+        _ => false,
+    }
+}
+
+//Notifications go out from their own sockets, the server one is bound to
+// localhost unless the LAN is allowed and couldn't reach LAN targets
+struct Notifier {
+    targets: Vec<SocketAddr>,
+    ipv4: Option<UdpSocket>,
+    ipv6: Option<UdpSocket>,
+}
+
+impl Notifier {
+    //One socket per address family in use
+    fn bind(targets: Vec<SocketAddr>) -> io::Result<Notifier> {
+        let ipv4 = match targets.iter().any(SocketAddr::is_ipv4) {
+            true => Some(UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?),
+            false => None,
+        };
+        let ipv6 = match targets.iter().any(SocketAddr::is_ipv6) {
+            true => Some(UdpSocket::bind((Ipv6Addr::UNSPECIFIED, 0))?),
+            false => None,
+        };
+
+        Ok(Notifier {
+            targets,
+            ipv4,
+            ipv6,
+        })
+    }
+
+    fn send(&self, buffer: &[u8], target: &SocketAddr) -> io::Result<usize> {
+        let socket = match target {
+            SocketAddr::V4(_) => self.ipv4.as_ref(),
+            SocketAddr::V6(_) => self.ipv6.as_ref(),
+        };
+        match socket {
+            Some(socket) => socket.send_to(buffer, target),
+            None => Err(io::Error::new(ErrorKind::Unsupported, "no socket for the address family")),
+        }
+    }
+
+    //Sends /soundbar/started, /soundbar/stopped and /soundbar/finished with
+    // the soundbite name
+    fn notify(&self, event: &Event) {
+        let (action, name) = match event {
+            Event::PlaybackStarted { name } => ("started", name),
+            Event::PlaybackStopped { name } => ("stopped", name),
+            Event::PlaybackFinished { name } => ("finished", name),
+            _ => return,
+        };
+
+        let packet = OscPacket::Message(OscMessage {
+            addr: format!("{}/{}", ADDRESS_PREFIX, action),
+            args: vec![OscType::String(name.clone())],
+        });
+        let buffer = match encoder::encode(&packet) {
+            Ok(buffer) => buffer,
+            Err(err) => {
+                error!("Unable to encode OSC notification [[{:?}]]", err);
+                return;
+            }
+        };
+
+        for target in self.targets.iter() {
+            if let Err(err) = self.send(&buffer, target) {
+                error!("Unable to send OSC notification to {} [[{:?}]]", target, err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossbeam::channel::Receiver;
+    use rosc::OscBundle;
+
+    use super::*;
+    use super::super::{
+        soundbite::{Soundbite, SoundbiteData},
+        soundstate::Message,
+    };
+
+    const TIMEOUT: Duration = Duration::from_secs(2);
+
+    struct Server {
+        state: SoundState,
+        messages: Receiver<Message>,
+        client: UdpSocket,
+        running: Arc<AtomicBool>,
+        receiver: Option<JoinHandle<()>>,
+    }
+
+    impl Server {
+        fn start() -> Server {
+            let data = SoundbiteData::from_samples("airhorn".to_string(), vec![0; 10], 1, 44100, 1f32, 1f32);
+            let (state, messages) = SoundState::for_tests(vec![Soundbite::from_data(data)]);
+
+            let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+            socket.set_read_timeout(Some(POLL_INTERVAL)).unwrap();
+            let client = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+            client.connect(socket.local_addr().unwrap()).unwrap();
+
+            let running = Arc::new(AtomicBool::new(true));
+            let receiver = {
+                let state = state.clone();
+                let running = running.clone();
+                thread::spawn(move || receive(socket, &state, &running))
+            };
+
+            Server {
+                state,
+                messages,
+                client,
+                running,
+                receiver: Some(receiver),
+            }
+        }
+
+        fn send(&self, packet: OscPacket) {
+            self.client.send(&encoder::encode(&packet).unwrap()).unwrap();
+        }
+
+        fn message(&self) -> Message {
+            self.messages.recv_timeout(TIMEOUT).expect("no message")
+        }
+
+        //Every packet sent before was handled once this one was
+        fn sync(&self) {
+            self.send(message("/soundbar/stop", vec![]));
+            assert!(matches!(self.message(), Message::StopSoundbite(None)));
+        }
+    }
+
+    impl Drop for Server {
+        fn drop(&mut self) {
+            self.running.store(false, Ordering::SeqCst);
+            if let Some(receiver) = self.receiver.take() {
+                let _ = receiver.join();
+            }
+        }
+    }
+
+    fn message(addr: &str, args: Vec<OscType>) -> OscPacket {
+        OscPacket::Message(OscMessage {
+            addr: addr.to_string(),
+            args,
+        })
+    }
+
+    #[test]
+    fn play_and_stop() {
+        let server = Server::start();
+
+        server.send(message("/soundbar/play/airhorn", vec![]));
+        assert!(matches!(server.message(), Message::PlaySoundbite(name) if name == "airhorn"));
+
+        server.send(message("/soundbar/play", vec![OscType::String("airhorn".to_string())]));
+        assert!(matches!(server.message(), Message::PlaySoundbite(name) if name == "airhorn"));
+
+        server.send(message("/soundbar/stop/airhorn", vec![]));
+        assert!(matches!(server.message(), Message::StopSoundbite(Some(name)) if name == "airhorn"));
+
+        server.send(message("/soundbar/stop", vec![]));
+        assert!(matches!(server.message(), Message::StopSoundbite(None)));
+    }
+
+    #[test]
+    fn buttons_only_fire_on_press() {
+        let server = Server::start();
+
+        server.send(message("/soundbar/play/airhorn", vec![OscType::Float(0f32)]));
+        server.send(message("/soundbar/play/airhorn", vec![OscType::Int(0)]));
+        server.send(message("/soundbar/play/airhorn", vec![OscType::Bool(false)]));
+        server.send(message("/soundbar/play/airhorn", vec![OscType::Float(1f32)]));
+        assert!(matches!(server.message(), Message::PlaySoundbite(name) if name == "airhorn"));
+        server.sync();
+        assert!(server.messages.is_empty());
+    }
+
+    #[test]
+    fn unknown_soundbites_and_addresses_are_ignored() {
+        let server = Server::start();
+
+        server.send(message("/soundbar/play/missing", vec![]));
+        server.send(message("/soundbar/rewind", vec![]));
+        server.send(message("/other/play/airhorn", vec![]));
+        server.sync();
+        assert!(server.messages.is_empty());
+    }
+
+    #[test]
+    fn bundles() {
+        let server = Server::start();
+
+        server.send(OscPacket::Bundle(OscBundle {
+            timetag: (0, 1).into(),
+            content: vec![
+                message("/soundbar/play/airhorn", vec![]),
+                message("/soundbar/stop/airhorn", vec![]),
+            ],
+        }));
+        assert!(matches!(server.message(), Message::PlaySoundbite(_)));
+        assert!(matches!(server.message(), Message::StopSoundbite(Some(_))));
+    }
+
+    #[test]
+    fn volume() {
+        let server = Server::start();
+
+        server.send(message("/soundbar/volume/airhorn", vec![OscType::Float(50f32)]));
+        server.send(message("/soundbar/volume", vec![OscType::Int(80)]));
+        //Out of range
+        server.send(message("/soundbar/volume/airhorn", vec![OscType::Float(500f32)]));
+        server.sync();
+
+        assert_eq!(server.state.soundbites.lock().unwrap()[0].data.volume, 50f32);
+        assert_eq!(control::master_volume(&server.state), Some(80f32));
+    }
+
+    //A socket bound to localhost can't send to other machines at all
+    #[test]
+    fn notifier_reaches_other_machines() {
+        //Documentation range, routed like any other remote address
+        let target = SocketAddr::from((Ipv4Addr::new(203, 0, 113, 1), 53000));
+        let notifier = Notifier::bind(vec![target]).unwrap();
+        assert!(notifier.ipv4.as_ref().unwrap().local_addr().unwrap().ip().is_unspecified());
+        assert!(notifier.ipv6.is_none());
+
+        let has_route = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+            .and_then(|socket| socket.connect(target))
+            .is_ok();
+        if has_route {
+            notifier.send(b"", &target).unwrap();
+
+            let loopback = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+            assert!(loopback.send_to(b"", target).is_err());
+        }
+    }
+
+    #[test]
+    fn notifications() {
+        let listener = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        listener.set_read_timeout(Some(TIMEOUT)).unwrap();
+        let (state, _messages) = SoundState::for_tests(Vec::new());
+        let settings = OscSettings {
+            enabled: true,
+            port: 0,
+            allow_lan: false,
+            targets: vec![listener.local_addr().unwrap().to_string()],
+        };
+        restart(&state, &settings).unwrap();
+
+        let name = "airhorn".to_string();
+        state.events.publish(Event::HotkeysToggled { enabled: false });
+        state.events.publish(Event::PlaybackStarted { name: name.clone() });
+        state.events.publish(Event::PlaybackStopped { name: name.clone() });
+        state.events.publish(Event::PlaybackFinished { name: name.clone() });
+
+        let mut buffer = [0u8; MAX_PACKET_SIZE];
+        for action in ["started", "stopped", "finished"] {
+            let size = listener.recv(&mut buffer).expect("no notification");
+            match decoder::decode_udp(&buffer[..size]).unwrap().1 {
+                OscPacket::Message(message) => {
+                    assert_eq!(message.addr, format!("/soundbar/{}", action));
+                    assert_eq!(message.args, vec![OscType::String(name.clone())]);
+                },
+                packet => panic!("unexpected packet {:?}", packet),
+            }
+        }
+
+        restart(&state, &OscSettings::default()).unwrap();
+    }
+}
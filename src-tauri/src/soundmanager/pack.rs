@@ -0,0 +1,281 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Cursor, Read, Write},
+    path::Path,
+};
+
+use serde::{
+    Serialize,
+    Deserialize
+};
+use zip::{
+    ZipArchive,
+    ZipWriter,
+    write::FileOptions,
+    CompressionMethod,
+};
+
+use super::{
+    export,
+    import,
+    key_task::{self, KeyTaskCode, TriggerMode},
+    settings::ImportLimits,
+    soundbite::SoundbiteData,
+    utils::{SoundManagerError, NewSoundbiteError},
+};
+
+pub const PACK_VERSION: u32 = 1;
+const MANIFEST_FILE: &str = "manifest.json";
+const AUDIO_FOLDER: &str = "audio";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PackSoundbite {
+    pub name: String,
+    //Path of the WAV file inside the archive
+    pub file: String,
+    pub volume: f32,
+    pub speed: f32,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PackBinding {
+    pub soundbite: String,
+    pub keycode: KeyTaskCode,
+    #[serde(default)]
+    pub swallow: bool,
+    #[serde(default)]
+    pub trigger: TriggerMode,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PackManifest {
+    pub version: u32,
+    pub soundbites: Vec<PackSoundbite>,
+    #[serde(default)]
+    pub bindings: Vec<PackBinding>,
+    #[serde(default)]
+    pub categories: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum PackConflict {
+    //A soundbite with the same name is already in the library or earlier in
+    // the pack, kept as is
+    NameUsed {
+        name: String,
+    },
+    //The key code is already bound, the pack binding is dropped
+    KeyTaskUsed {
+        soundbite: String,
+        keycode: KeyTaskCode,
+        used_by: String,
+    },
+    //Old key code more than one chord packed to, the pack binding is dropped
+    KeyCodeOutdated {
+        soundbite: String,
+        keycode: KeyTaskCode,
+    },
+    //Above the import limits
+    TooLarge {
+        name: String,
+        max_file_size: u64,
+    },
+    TooLong {
+        name: String,
+        max_duration: f32,
+    },
+    //Outside of (0, 200] in the manifest
+    InvalidVolume {
+        name: String,
+        volume: f32,
+    },
+    InvalidSpeed {
+        name: String,
+        speed: f32,
+    },
+    //Missing from the archive or unreadable
+    MissingFile {
+        name: String,
+        file: String,
+    },
+    InvalidAudio {
+        name: String,
+    },
+}
+
+impl PackConflict {
+    //Reason the soundbite entry couldn't be imported
+    pub fn rejected(entry: &PackSoundbite, err: SoundManagerError) -> PackConflict {
+        let name = entry.name.clone();
+        match err {
+            SoundManagerError::NewSoundbiteError(NewSoundbiteError::NameUsed(_)) =>
+                PackConflict::NameUsed { name },
+            SoundManagerError::NewSoundbiteError(NewSoundbiteError::FileTooLarge(_, max_file_size)) =>
+                PackConflict::TooLarge { name, max_file_size },
+            SoundManagerError::NewSoundbiteError(NewSoundbiteError::DurationTooLong(_, max_duration)) =>
+                PackConflict::TooLong { name, max_duration },
+            SoundManagerError::InvalidVolumeValue => PackConflict::InvalidVolume {
+                name,
+                volume: entry.volume,
+            },
+            SoundManagerError::InvalidSpeedValue => PackConflict::InvalidSpeed {
+                name,
+                speed: entry.speed,
+            },
+            SoundManagerError::InvalidPack(file) => PackConflict::MissingFile { name, file },
+            _ => PackConflict::InvalidAudio { name },
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct PackImportReport {
+    pub imported: Vec<String>,
+    pub conflicts: Vec<PackConflict>,
+}
+
+pub fn write_pack(
+    path: &Path,
+    soundbites: &[SoundbiteData],
+    bindings: Vec<PackBinding>,
+    categories: Vec<String>,
+) -> Result<(), SoundManagerError> {
+    let pack_error = || SoundManagerError::UnableToExport(path.display().to_string());
+
+    let file = File::create(path).map_err(|_| pack_error())?;
+    let mut zip = ZipWriter::new(BufWriter::new(file));
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let mut manifest = PackManifest {
+        version: PACK_VERSION,
+        soundbites: Vec::new(),
+        bindings,
+        categories,
+    };
+
+    for (index, data) in soundbites.iter().enumerate() {
+        let file_name = export::file_name_for(&format!("{}_{}", index, data.name), "wav");
+        let file = format!("{}/{}", AUDIO_FOLDER, file_name.display());
+
+        let mut wav = Cursor::new(Vec::new());
+        export::write_wav(data, &mut wav, false).map_err(|_| pack_error())?;
+
+        zip.start_file(file.clone(), options).map_err(|_| pack_error())?;
+        zip.write_all(wav.get_ref()).map_err(|_| pack_error())?;
+
+        manifest.soundbites.push(PackSoundbite {
+            name: data.name.clone(),
+            file,
+            volume: data.volume,
+            speed: data.speed,
+            category: data.category.clone(),
+            tags: data.tags.clone(),
+        });
+    }
+
+    zip.start_file(MANIFEST_FILE, options).map_err(|_| pack_error())?;
+    serde_json::to_writer_pretty(&mut zip, &manifest).map_err(|_| pack_error())?;
+
+    zip.finish().map_err(|_| pack_error())?
+        .flush().map_err(|_| pack_error())
+}
+
+pub struct Pack {
+    archive: ZipArchive<BufReader<File>>,
+    pub manifest: PackManifest,
+    //Bindings whose old key code couldn't be migrated
+    pub dropped_bindings: Vec<PackBinding>,
+}
+
+impl Pack {
+    pub fn open(path: &Path) -> Result<Pack, SoundManagerError> {
+        let pack_error = || SoundManagerError::InvalidPack(path.display().to_string());
+
+        let file = File::open(path).map_err(|_| pack_error())?;
+        let mut archive = ZipArchive::new(BufReader::new(file)).map_err(|_| pack_error())?;
+        let mut manifest: PackManifest = {
+            let reader = archive.by_name(MANIFEST_FILE).map_err(|_| pack_error())?;
+            serde_json::from_reader(reader).map_err(|_| pack_error())?
+        };
+
+        if manifest.version > PACK_VERSION {
+            return Err(SoundManagerError::UnsupportedPackVersion(manifest.version));
+        }
+
+        //Packs exported with the old key code packing
+        let mut dropped_bindings = Vec::new();
+        let mut bindings = Vec::new();
+        for mut binding in manifest.bindings.drain(..) {
+            match key_task::migrate_keycode(binding.keycode) {
+                Some(keycode) => {
+                    binding.keycode = keycode;
+                    bindings.push(binding);
+                },
+                None => dropped_bindings.push(binding),
+            }
+        }
+        manifest.bindings = bindings;
+
+        Ok(Pack {
+            archive,
+            manifest,
+            dropped_bindings,
+        })
+    }
+
+    //Entries are held to the same limits as imported files, and their volume
+    // and speed to the ones set_volume and set_speed accept
+    pub fn read_soundbite(
+        &mut self,
+        soundbite: &PackSoundbite,
+        limits: &ImportLimits,
+    ) -> Result<SoundbiteData, SoundManagerError> {
+        if !(soundbite.volume > 0f32 && soundbite.volume <= 200f32) {
+            return Err(SoundManagerError::InvalidVolumeValue);
+        }
+        if !(soundbite.speed > 0f32 && soundbite.speed <= 200f32) {
+            return Err(SoundManagerError::InvalidSpeedValue);
+        }
+
+        let pack_error = || SoundManagerError::InvalidPack(soundbite.file.clone());
+        let too_large = || SoundManagerError::NewSoundbiteError(
+            NewSoundbiteError::FileTooLarge(soundbite.file.clone(), limits.max_file_size)
+        );
+
+        let entry = self.archive.by_name(&soundbite.file).map_err(|_| pack_error())?;
+        if entry.size() > limits.max_file_size {
+            return Err(too_large());
+        }
+
+        //The declared size may lie, never read past the limit
+        let mut buffer = Vec::new();
+        entry.take(limits.max_file_size + 1)
+            .read_to_end(&mut buffer).map_err(|_| pack_error())?;
+        if buffer.len() as u64 > limits.max_file_size {
+            return Err(too_large());
+        }
+
+        let decoded = import::decode_reader(
+            Cursor::new(buffer),
+            soundbite.file.clone(),
+            limits,
+            |_, _| {}
+        ).map_err(SoundManagerError::NewSoundbiteError)?;
+        let mut data = SoundbiteData::from_samples(
+            soundbite.name.clone(),
+            decoded.buffer,
+            decoded.channels,
+            decoded.sample_rate,
+            soundbite.volume,
+            soundbite.speed,
+        );
+        data.category = soundbite.category.clone();
+        data.tags = soundbite.tags.clone();
+        Ok(data)
+    }
+}
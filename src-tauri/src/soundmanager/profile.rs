@@ -0,0 +1,121 @@
+use serde::{
+    Serialize,
+    Deserialize
+};
+
+use super::{
+    key_task::{KeyBinding, KeyTaskCode},
+    SoundbitesKeyTasks,
+};
+
+pub const DEFAULT_PROFILE: &str = "Default";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Profile {
+    pub name: String,
+    //Bindings of the active profile live in SoundbitesKeyTasks, this copy
+    // is refreshed only when switching away from it or saving
+    #[serde(default)]
+    pub keytasks: SoundbitesKeyTasks,
+    #[serde(default)]
+    pub master_volume: Option<f32>,
+    #[serde(default)]
+    pub output_device: Option<String>,
+    //Key code switching to this profile from any other one
+    #[serde(default)]
+    pub switch_keycode: Option<KeyTaskCode>,
+}
+
+impl Profile {
+    pub fn new(name: String) -> Profile {
+        Profile {
+            name,
+            keytasks: SoundbitesKeyTasks::new(),
+            master_volume: None,
+            output_device: None,
+            switch_keycode: None,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ProfileInfo {
+    pub name: String,
+    pub active: bool,
+    pub master_volume: Option<f32>,
+    pub output_device: Option<String>,
+    pub switch_keycode: Option<KeyTaskCode>,
+}
+
+#[derive(Debug)]
+pub struct Profiles {
+    pub active: String,
+    pub profiles: Vec<Profile>,
+}
+
+impl Profiles {
+    pub fn new(profiles: Vec<Profile>, active: Option<String>) -> Profiles {
+        let mut profiles = profiles;
+        if profiles.is_empty() {
+            profiles.push(Profile::new(DEFAULT_PROFILE.to_string()));
+        }
+
+        let active = match active {
+            Some(active) if profiles.iter().any(|p| p.name == active) => active,
+            _ => profiles[0].name.clone(),
+        };
+
+        Profiles {
+            active,
+            profiles,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Profile> {
+        self.profiles.iter_mut().find(|profile| profile.name == name)
+    }
+
+    pub fn active(&self) -> &Profile {
+        self.get(&self.active).expect("Active profile missing")
+    }
+
+    pub fn active_mut(&mut self) -> &mut Profile {
+        let active = self.active.clone();
+        self.get_mut(&active).expect("Active profile missing")
+    }
+
+    pub fn master_volume(&self) -> f32 {
+        self.active().master_volume.unwrap_or(1f32)
+    }
+
+    pub fn find_by_switch_keycode(&self, keycode: KeyTaskCode) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.switch_keycode == Some(keycode))
+    }
+
+    //Same shifting as remove_soundbite, for the bindings of inactive profiles
+    pub fn remove_soundbite(&mut self, index: usize) {
+        self.retain_bindings(|binding| binding.soundbite_removed(index));
+    }
+
+    //Bindings of the active profile live in SoundbitesKeyTasks
+    pub fn retain_bindings<F: FnMut(&mut KeyBinding) -> bool>(&mut self, mut keep: F) {
+        let active = self.active.clone();
+        for profile in self.profiles.iter_mut().filter(|p| p.name != active) {
+            profile.keytasks.retain(|_, binding| keep(binding));
+        }
+    }
+
+    pub fn info(&self) -> Vec<ProfileInfo> {
+        self.profiles.iter().map(|profile| ProfileInfo {
+            name: profile.name.clone(),
+            active: profile.name == self.active,
+            master_volume: profile.master_volume,
+            output_device: profile.output_device.clone(),
+            switch_keycode: profile.switch_keycode,
+        }).collect()
+    }
+}
@@ -0,0 +1,108 @@
+use std::time::{Duration, Instant};
+
+use serde::{
+    Serialize,
+    Deserialize
+};
+
+//Longest gap or crossfade, in seconds
+const MAX_TRANSITION: f32 = 60f32;
+
+//How a queued soundbite follows the previous one, back to back when None
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Transition {
+    //Silence after the previous one finished
+    Gap {
+        seconds: f32,
+    },
+    //Starts while the previous one fades out
+    Crossfade {
+        seconds: f32,
+    },
+}
+
+impl Transition {
+    pub fn is_valid(&self) -> bool {
+        (0f32..=MAX_TRANSITION).contains(&self.seconds())
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f32(self.seconds())
+    }
+
+    fn seconds(&self) -> f32 {
+        match self {
+            Transition::Gap { seconds } | Transition::Crossfade { seconds } => *seconds,
+        }
+    }
+}
+
+//Soundbites are referenced by name, removed ones are skipped when played
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Playlist {
+    pub name: String,
+    pub soundbites: Vec<String>,
+    #[serde(default)]
+    pub transition: Option<Transition>,
+}
+
+pub type Playlists = Vec<Playlist>;
+
+//Now-playing queue, moved on by the manager as its soundbites finish
+#[derive(Debug, Default)]
+pub struct Queue {
+    pub soundbites: Vec<String>,
+    pub transition: Option<Transition>,
+    //Entry playing, or played last
+    pub current: Option<usize>,
+    //False once stopped or past the last entry
+    pub active: bool,
+    //Start of the next entry, while in a gap
+    pub next_at: Option<Instant>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct QueueInfo {
+    pub soundbites: Vec<String>,
+    pub transition: Option<Transition>,
+    pub current: Option<usize>,
+    pub active: bool,
+}
+
+impl Queue {
+    //Name of the entry playing, if the queue is
+    pub fn playing(&self) -> Option<&String> {
+        if !self.active {
+            return None;
+        }
+
+        self.current.and_then(|index| self.soundbites.get(index))
+    }
+
+    pub fn stop(&mut self) {
+        self.active = false;
+        self.next_at = None;
+    }
+
+    pub fn clear(&mut self) {
+        self.stop();
+        self.soundbites.clear();
+        self.current = None;
+    }
+
+    pub fn rename(&mut self, name: &str, new_name: &str) {
+        self.soundbites.iter_mut()
+            .filter(|soundbite| *soundbite == name)
+            .for_each(|soundbite| *soundbite = new_name.to_string());
+    }
+
+    pub fn info(&self) -> QueueInfo {
+        QueueInfo {
+            soundbites: self.soundbites.clone(),
+            transition: self.transition,
+            current: self.current,
+            active: self.active,
+        }
+    }
+}
@@ -0,0 +1,635 @@
+use chrono::{
+    DateTime,
+    Datelike,
+    Duration,
+    Local,
+    NaiveDate,
+    NaiveDateTime,
+    NaiveTime,
+    TimeZone,
+    Timelike,
+};
+use log::trace;
+use serde::{
+    Serialize,
+    Deserialize
+};
+
+//Cron rules are looked up this far ahead before giving up, e.g. Feb 30
+const MAX_LOOKAHEAD_YEARS: i32 = 5;
+
+//Source of the current time, replaced to drive the scheduler
+// deterministically, in any zone
+pub trait Clock<Tz: TimeZone = Local>: Send {
+    fn now(&self) -> DateTime<Tz>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ScheduleRule {
+    //Once, at the given local time
+    At {
+        time: DateTime<Local>,
+    },
+    //Standard five fields cron expression, minute hour day month weekday
+    Cron {
+        expression: String,
+    },
+    //Once the seconds elapsed from when it was added, again and again
+    // when repeating
+    Countdown {
+        seconds: u32,
+        #[serde(default)]
+        repeat: bool,
+    },
+}
+
+impl ScheduleRule {
+    //Error message when the rule can never fire
+    pub fn validate(&self, now: DateTime<Local>) -> Result<(), String> {
+        match self {
+            ScheduleRule::At { time } if *time <= now => Err("time already passed".to_string()),
+            ScheduleRule::Cron { expression } => CronExpression::parse(expression).map(|_| ()),
+            ScheduleRule::Countdown { seconds: 0, .. } => Err("countdown of 0 seconds".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    fn first<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> Option<DateTime<Local>> {
+        match self {
+            ScheduleRule::At { time } => Some(*time).filter(|time| time > now),
+            ScheduleRule::Cron { expression } => CronExpression::parse(expression).ok()?
+                .next_after(now)
+                .map(|next| next.with_timezone(&Local)),
+            ScheduleRule::Countdown { seconds, .. } =>
+                Some(now.with_timezone(&Local) + Duration::seconds(*seconds as i64)),
+        }
+    }
+
+    //Repeating countdowns keep their pace unless they fell behind
+    fn following<Tz: TimeZone>(
+        &self,
+        fired: DateTime<Local>,
+        now: &DateTime<Tz>
+    ) -> Option<DateTime<Local>> {
+        match self {
+            ScheduleRule::Countdown { seconds, repeat: true } => {
+                let interval = Duration::seconds(*seconds as i64);
+                let next = fired + interval;
+                Some(if next > *now { next } else { now.with_timezone(&Local) + interval })
+            },
+            ScheduleRule::Cron { .. } => self.first(now),
+            _ => None,
+        }
+    }
+
+    fn is_recurring(&self) -> bool {
+        matches!(self, ScheduleRule::Cron { .. } | ScheduleRule::Countdown { repeat: true, .. })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Schedule {
+    pub name: String,
+    //Referenced by name, a removed soundbite is reported when due
+    pub soundbite: String,
+    pub rule: ScheduleRule,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    //None once a one-shot fired or was missed
+    #[serde(default)]
+    pub next: Option<DateTime<Local>>,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl Schedule {
+    pub fn new(name: String, soundbite: String, rule: ScheduleRule) -> Schedule {
+        Schedule {
+            name,
+            soundbite,
+            rule,
+            enabled: true,
+            next: None,
+        }
+    }
+}
+
+//Next times are kept in local time whatever zone the clock is in
+pub struct Scheduler<Tz: TimeZone = Local> {
+    clock: Box<dyn Clock<Tz>>,
+    pub schedules: Vec<Schedule>,
+}
+
+impl Scheduler {
+    pub fn new(schedules: Vec<Schedule>) -> Scheduler {
+        Scheduler::with_clock(Box::new(SystemClock), schedules)
+    }
+}
+
+impl<Tz: TimeZone> Scheduler<Tz> {
+    //Recurring schedules due while the app was closed resume from now,
+    // one-shots are missed
+    pub fn with_clock(clock: Box<dyn Clock<Tz>>, schedules: Vec<Schedule>) -> Scheduler<Tz> {
+        let now = clock.now();
+        let mut schedules = schedules;
+        for schedule in schedules.iter_mut() {
+            match schedule.next {
+                Some(next) if next > now => {},
+                Some(_) if !schedule.rule.is_recurring() => {
+                    trace!("Schedule named {} missed", schedule.name);
+                    schedule.next = None;
+                },
+                _ if schedule.rule.is_recurring() => schedule.next = schedule.rule.first(&now),
+                _ => {},
+            }
+        }
+
+        Scheduler {
+            clock,
+            schedules,
+        }
+    }
+
+    pub fn now(&self) -> DateTime<Local> {
+        self.clock.now().with_timezone(&Local)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Schedule> {
+        self.schedules.iter_mut().find(|schedule| schedule.name == name)
+    }
+
+    pub fn add(&mut self, schedule: Schedule) {
+        let mut schedule = schedule;
+        schedule.next = schedule.rule.first(&self.clock.now());
+        self.schedules.push(schedule);
+    }
+
+    //Enabling starts countdowns over
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        let now = self.clock.now();
+        match self.get_mut(name) {
+            Some(schedule) => {
+                if enabled && !schedule.enabled {
+                    schedule.next = schedule.rule.first(&now);
+                }
+                schedule.enabled = enabled;
+                true
+            },
+            None => false,
+        }
+    }
+
+    //Schedules due since the last call, each moved on to its next time
+    pub fn take_due(&mut self) -> Vec<Schedule> {
+        let now = self.clock.now();
+        let mut due = Vec::new();
+        for schedule in self.schedules.iter_mut().filter(|schedule| schedule.enabled) {
+            match schedule.next {
+                Some(next) if next <= now => {
+                    schedule.next = schedule.rule.following(next, &now);
+                    due.push(schedule.clone());
+                },
+                _ => {},
+            }
+        }
+
+        due
+    }
+}
+
+//Bit sets of the values each field matches
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronExpression {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    //Restricted days and weekdays match when either does, as in cron
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl CronExpression {
+    //Fields accept *, values, ranges, lists and steps, e.g. */15 9-17 * * 1-5
+    pub fn parse(expression: &str) -> Result<CronExpression, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("{} fields instead of 5", fields.len()));
+        }
+
+        let field = |index: usize, min: u32, max: u32| {
+            parse_field(fields[index], min, max)
+                .ok_or_else(|| format!("invalid field {}", fields[index]))
+        };
+
+        //Sunday is both 0 and 7
+        let mut weekdays = field(4, 0, 7)?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays |= 1;
+        }
+
+        Ok(CronExpression {
+            minutes: field(0, 0, 59)?,
+            hours: field(1, 0, 23)?,
+            days: field(2, 1, 31)?,
+            months: field(3, 1, 12)?,
+            weekdays,
+            any_day: fields[2] == "*",
+            any_weekday: fields[4] == "*",
+        })
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+
+    //First matching minute strictly after time, local times skipped by a
+    // DST change are skipped as well and repeated ones match once each time
+    // they come round
+    pub fn next_after<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        //Moved on in the zone rather than in local time, so the minute after
+        // 02:59 is the second 02:00 when clocks go back
+        let into_minute = time.naive_local().time();
+        let start = (time.clone()
+            - Duration::seconds(into_minute.second() as i64)
+            - Duration::nanoseconds(into_minute.nanosecond() as i64)
+            + Duration::minutes(1)).naive_local();
+        let last_year = start.year() + MAX_LOOKAHEAD_YEARS;
+
+        let mut date = start.date();
+        let mut from = start.time();
+        while date.year() <= last_year {
+            if self.months & (1 << date.month()) != 0 && self.matches_day(date) {
+                if let Some(found) = self.first_time_from(date, from, time) {
+                    return Some(found);
+                }
+            }
+
+            date = date.succ_opt()?;
+            from = NaiveTime::MIN;
+        }
+
+        None
+    }
+
+    fn first_time_from<Tz: TimeZone>(
+        &self,
+        date: NaiveDate,
+        from: NaiveTime,
+        after: &DateTime<Tz>
+    ) -> Option<DateTime<Tz>> {
+        for hour in from.hour()..24 {
+            if self.hours & (1 << hour) == 0 {
+                continue;
+            }
+
+            let first_minute = if hour == from.hour() { from.minute() } else { 0 };
+            for minute in first_minute..60 {
+                if self.minutes & (1 << minute) == 0 {
+                    continue;
+                }
+
+                //In the hour repeated when clocks go back, the first pass may
+                // be over already
+                let naive = NaiveDateTime::new(date, NaiveTime::from_hms_opt(hour, minute, 0)?);
+                let local = after.timezone().from_local_datetime(&naive);
+                if let Some(time) = [local.clone().earliest(), local.latest()]
+                    .into_iter()
+                    .flatten()
+                    .filter(|time| time > after)
+                    .min()
+                {
+                    return Some(time);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+fn parse_field(field: &str, min: u32, max: u32) -> Option<u64> {
+    let mut values = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|step| *step > 0)?),
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (start.parse().ok()?, end.parse().ok()?)
+        } else {
+            //5/10 means from 5 to the end every 10
+            let value = range.parse().ok()?;
+            (value, if step > 1 { max } else { value })
+        };
+
+        if start < min || end > max || start > end {
+            return None;
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            values |= 1 << value;
+        }
+    }
+
+    Some(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use chrono::Utc;
+    use chrono_tz::{Europe::Berlin, Tz};
+
+    use super::*;
+
+    //Every test runs in a zone with DST, whatever the machine is set to
+    fn berlin(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Tz> {
+        Berlin.with_ymd_and_hms(year, month, day, hour, minute, 0).earliest().unwrap()
+    }
+
+    #[derive(Clone)]
+    struct FakeClock(Arc<Mutex<DateTime<Tz>>>);
+
+    impl FakeClock {
+        fn new(now: DateTime<Tz>) -> FakeClock {
+            FakeClock(Arc::new(Mutex::new(now)))
+        }
+
+        fn advance(&self, seconds: i64) {
+            *self.0.lock().unwrap() += Duration::seconds(seconds);
+        }
+    }
+
+    impl Clock<Tz> for FakeClock {
+        fn now(&self) -> DateTime<Tz> {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    fn scheduler(clock: &FakeClock, schedules: Vec<Schedule>) -> Scheduler<Tz> {
+        Scheduler::with_clock(Box::new(clock.clone()), schedules)
+    }
+
+    //Next times of every schedule, kept in local time
+    fn next_times(scheduler: &Scheduler<Tz>) -> Vec<Option<DateTime<Tz>>> {
+        scheduler.schedules.iter()
+            .map(|schedule| schedule.next.map(|next| next.with_timezone(&Berlin)))
+            .collect()
+    }
+
+    fn schedule(rule: ScheduleRule) -> Schedule {
+        Schedule::new("schedule".to_string(), "airhorn".to_string(), rule)
+    }
+
+    fn next_after(expression: &str, time: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        CronExpression::parse(expression).unwrap().next_after(&time)
+    }
+
+    #[test]
+    fn at_fires_once() {
+        let now = berlin(2026, 10, 19, 12, 0);
+        let clock = FakeClock::new(now);
+        let mut scheduler = scheduler(&clock, Vec::new());
+        let time = (now + Duration::minutes(10)).with_timezone(&Local);
+        scheduler.add(schedule(ScheduleRule::At { time }));
+
+        clock.advance(599);
+        assert!(scheduler.take_due().is_empty());
+
+        clock.advance(1);
+        let due = scheduler.take_due();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].soundbite, "airhorn");
+        assert_eq!(scheduler.schedules[0].next, None);
+
+        clock.advance(3600);
+        assert!(scheduler.take_due().is_empty());
+    }
+
+    #[test]
+    fn countdown_fires_once_unless_repeating() {
+        let clock = FakeClock::new(berlin(2026, 10, 19, 12, 0));
+        let mut scheduler = scheduler(&clock, Vec::new());
+        scheduler.add(schedule(ScheduleRule::Countdown { seconds: 60, repeat: false }));
+
+        clock.advance(60);
+        assert_eq!(scheduler.take_due().len(), 1);
+        assert_eq!(scheduler.schedules[0].next, None);
+
+        clock.advance(60);
+        assert!(scheduler.take_due().is_empty());
+    }
+
+    #[test]
+    fn repeating_countdown_keeps_its_pace() {
+        let start = berlin(2026, 10, 19, 12, 0);
+        let clock = FakeClock::new(start);
+        let mut scheduler = scheduler(&clock, Vec::new());
+        scheduler.add(schedule(ScheduleRule::Countdown { seconds: 60, repeat: true }));
+
+        //Noticed late, the next one is still counted from when it was due
+        clock.advance(65);
+        assert_eq!(scheduler.take_due().len(), 1);
+        assert_eq!(next_times(&scheduler), vec![Some(start + Duration::seconds(120))]);
+
+        //Fell behind, counted from now instead of firing repeatedly
+        clock.advance(200);
+        assert_eq!(scheduler.take_due().len(), 1);
+        assert!(scheduler.take_due().is_empty());
+        assert_eq!(next_times(&scheduler), vec![Some(start + Duration::seconds(325))]);
+    }
+
+    #[test]
+    fn disabled_schedules_wait_and_restart_when_enabled() {
+        let start = berlin(2026, 10, 19, 12, 0);
+        let clock = FakeClock::new(start);
+        let mut scheduler = scheduler(&clock, Vec::new());
+        scheduler.add(schedule(ScheduleRule::Countdown { seconds: 60, repeat: false }));
+        assert!(scheduler.set_enabled("schedule", false));
+
+        clock.advance(60);
+        assert!(scheduler.take_due().is_empty());
+
+        assert!(scheduler.set_enabled("schedule", true));
+        assert_eq!(next_times(&scheduler), vec![Some(start + Duration::seconds(120))]);
+        assert!(!scheduler.set_enabled("missing", true));
+    }
+
+    #[test]
+    fn one_shots_due_while_closed_are_missed() {
+        let now = berlin(2026, 10, 19, 12, 0);
+        let with_next = |rule: ScheduleRule, next: DateTime<Local>| Schedule {
+            next: Some(next),
+            ..schedule(rule)
+        };
+        let past = (now - Duration::hours(1)).with_timezone(&Local);
+        let future = (now + Duration::hours(1)).with_timezone(&Local);
+
+        let clock = FakeClock::new(now);
+        let mut scheduler = scheduler(&clock, vec![
+            with_next(ScheduleRule::At { time: past }, past),
+            with_next(ScheduleRule::Countdown { seconds: 60, repeat: false }, past),
+            with_next(ScheduleRule::Countdown { seconds: 60, repeat: true }, past),
+            with_next(ScheduleRule::Cron { expression: "0 * * * *".to_string() }, past),
+            with_next(ScheduleRule::At { time: future }, future),
+        ]);
+
+        assert_eq!(next_times(&scheduler), vec![
+            None,
+            None,
+            Some(now + Duration::seconds(60)),
+            Some(berlin(2026, 10, 19, 13, 0)),
+            Some(future.with_timezone(&Berlin)),
+        ]);
+        assert!(scheduler.take_due().is_empty());
+    }
+
+    #[test]
+    fn parse_expands_fields() {
+        let expression = CronExpression::parse("*/15 9-17 1,15 * 1-5").unwrap();
+        assert_eq!(expression.minutes, 1 << 0 | 1 << 15 | 1 << 30 | 1 << 45);
+        assert_eq!(expression.hours, (9..=17).fold(0, |bits, hour| bits | 1 << hour));
+        assert_eq!(expression.days, 1 << 1 | 1 << 15);
+        assert_eq!(expression.months, (1..=12).fold(0, |bits, month| bits | 1 << month));
+        assert_eq!(expression.weekdays, (1..=5).fold(0, |bits, weekday| bits | 1 << weekday));
+        assert!(!expression.any_day && !expression.any_weekday);
+
+        //From the value to the end of the field
+        let expression = CronExpression::parse("5/20 0-12/6 * * *").unwrap();
+        assert_eq!(expression.minutes, 1 << 5 | 1 << 25 | 1 << 45);
+        assert_eq!(expression.hours, 1 << 0 | 1 << 6 | 1 << 12);
+        assert!(expression.any_day && expression.any_weekday);
+    }
+
+    #[test]
+    fn parse_treats_seven_as_sunday() {
+        let expression = CronExpression::parse("0 0 * * 7").unwrap();
+        assert_ne!(expression.weekdays & 1, 0);
+        assert_eq!(
+            expression,
+            CronExpression { weekdays: expression.weekdays, ..CronExpression::parse("0 0 * * 0").unwrap() }
+        );
+    }
+
+    #[test]
+    fn parse_rejects_invalid_expressions() {
+        for expression in [
+            "",
+            "* * * *",
+            "* * * * * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "* * * 13 *",
+            "* * * * 8",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "a * * * *",
+            "1,,2 * * * *",
+        ] {
+            assert!(CronExpression::parse(expression).is_err(), "{} accepted", expression);
+        }
+    }
+
+    #[test]
+    fn next_after_is_strictly_after() {
+        let time = berlin(2026, 10, 19, 12, 0);
+        assert_eq!(next_after("* * * * *", time), Some(berlin(2026, 10, 19, 12, 1)));
+        assert_eq!(next_after("0 12 * * *", time), Some(berlin(2026, 10, 20, 12, 0)));
+
+        let time = time + Duration::seconds(30);
+        assert_eq!(next_after("* * * * *", time), Some(berlin(2026, 10, 19, 12, 1)));
+    }
+
+    #[test]
+    fn next_after_follows_steps_and_ranges() {
+        //Friday evening, on to Monday morning
+        let time = berlin(2026, 10, 23, 17, 50);
+        assert_eq!(next_after("*/15 9-17 * * 1-5", time), Some(berlin(2026, 10, 26, 9, 0)));
+        assert_eq!(next_after("*/15 9-17 * * 1-5", berlin(2026, 10, 26, 9, 0)), Some(berlin(2026, 10, 26, 9, 15)));
+        assert_eq!(next_after("0 0 1 */3 *", time), Some(berlin(2027, 1, 1, 0, 0)));
+    }
+
+    #[test]
+    fn next_after_matches_sunday_as_seven() {
+        let time = berlin(2026, 10, 19, 12, 0);
+        assert_eq!(next_after("0 0 * * 7", time), Some(berlin(2026, 10, 25, 0, 0)));
+        assert_eq!(next_after("0 0 * * 0", time), Some(berlin(2026, 10, 25, 0, 0)));
+    }
+
+    #[test]
+    fn restricted_day_and_weekday_match_either() {
+        //Mondays or the 1st, Sunday Nov 1st comes before Monday Nov 2nd
+        assert_eq!(next_after("0 0 1 * 1", berlin(2026, 10, 20, 0, 0)), Some(berlin(2026, 10, 26, 0, 0)));
+        assert_eq!(next_after("0 0 1 * 1", berlin(2026, 10, 27, 0, 0)), Some(berlin(2026, 11, 1, 0, 0)));
+
+        //Only one restricted, both have to match
+        assert_eq!(next_after("0 0 * * 1", berlin(2026, 10, 27, 0, 0)), Some(berlin(2026, 11, 2, 0, 0)));
+        assert_eq!(next_after("0 0 1 * *", berlin(2026, 10, 20, 0, 0)), Some(berlin(2026, 11, 1, 0, 0)));
+        assert_eq!(next_after("0 0 13 * 5", berlin(2026, 10, 19, 0, 0)), Some(berlin(2026, 10, 23, 0, 0)));
+    }
+
+    #[test]
+    fn next_after_gives_up_on_impossible_dates() {
+        assert_eq!(next_after("0 0 30 2 *", berlin(2026, 10, 19, 0, 0)), None);
+    }
+
+    #[test]
+    fn next_after_skips_times_missing_when_clocks_go_forward() {
+        //02:00 jumps to 03:00 on March 29th 2026
+        let time = berlin(2026, 3, 28, 12, 0);
+        assert_eq!(next_after("30 2 * * *", time), Some(berlin(2026, 3, 30, 2, 30)));
+    }
+
+    #[test]
+    fn next_after_moves_on_in_the_repeated_hour() {
+        //03:00 goes back to 02:00 on October 25th 2026
+        let first_pass = Utc.with_ymd_and_hms(2026, 10, 25, 0, 30, 0).unwrap().with_timezone(&Berlin);
+        let second_pass = first_pass + Duration::hours(1);
+        assert_eq!(second_pass.naive_local(), first_pass.naive_local());
+
+        let next = next_after("* * * * *", first_pass).unwrap();
+        assert_eq!(next, first_pass + Duration::minutes(1));
+
+        let next = next_after("* * * * *", second_pass).unwrap();
+        assert_eq!(next, second_pass + Duration::minutes(1));
+    }
+
+    #[test]
+    fn cron_fires_once_a_minute_through_the_repeated_hour() {
+        let clock = FakeClock::new(berlin(2026, 10, 25, 1, 59));
+        let mut scheduler = scheduler(&clock, Vec::new());
+        scheduler.add(schedule(ScheduleRule::Cron { expression: "* * * * *".to_string() }));
+
+        //Two hours of wall time, 02:00 to 02:59 twice
+        for _ in 0..120 {
+            clock.advance(60);
+            assert_eq!(scheduler.take_due().len(), 1);
+            clock.advance(30);
+            assert!(scheduler.take_due().is_empty());
+            clock.advance(-30);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use super::soundbite::SoundbiteData;
+
+const NAME_WEIGHT: i32 = 3;
+const CATEGORY_WEIGHT: i32 = 2;
+const TAG_WEIGHT: i32 = 2;
+const SOURCE_WEIGHT: i32 = 1;
+
+//Scores query as a case-insensitive subsequence of text, None when a
+// query character can't be found. Consecutive characters and characters
+// at the start of a word weigh more, gaps between matches weigh less
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    let mut score = 0;
+    let mut query_index = 0;
+    let mut last_match: Option<usize> = None;
+    for (index, c) in text.iter().enumerate() {
+        if query_index == query.len() {
+            break;
+        }
+
+        if *c != query[query_index] {
+            continue;
+        }
+
+        score += 1;
+        if index == 0 {
+            score += 10;
+        } else if !text[index - 1].is_alphanumeric() {
+            score += 8;
+        }
+
+        match last_match {
+            Some(last) if last + 1 == index => score += 5,
+            Some(last) => score -= ((index - last - 1) as i32).min(5),
+            None => {},
+        }
+
+        last_match = Some(index);
+        query_index += 1;
+    }
+
+    if query_index < query.len() {
+        return None;
+    }
+
+    let query: String = query.into_iter().collect();
+    let text: String = text.into_iter().collect();
+    if text == query {
+        score += 100;
+    } else if text.contains(&query) {
+        score += 50;
+    }
+
+    Some(score)
+}
+
+//Every whitespace separated term has to match at least one field, the
+// best field for each term adds up to the final score
+pub fn score_soundbite(query: &str, data: &SoundbiteData) -> Option<i32> {
+    let source = data.source.as_ref()
+        .and_then(|source| source.file_name())
+        .map(|file_name| file_name.to_string_lossy().to_string());
+
+    let mut total = 0;
+    for term in query.split_whitespace() {
+        let mut best = fuzzy_score(term, &data.name).map(|score| score * NAME_WEIGHT);
+
+        let mut consider = |score: Option<i32>, weight: i32| {
+            if let Some(score) = score {
+                let score = score * weight;
+                match best {
+                    Some(b) if b >= score => {},
+                    _ => best = Some(score),
+                }
+            }
+        };
+
+        if let Some(category) = &data.category {
+            consider(fuzzy_score(term, category), CATEGORY_WEIGHT);
+        }
+        for tag in data.tags.iter() {
+            consider(fuzzy_score(term, tag), TAG_WEIGHT);
+        }
+        if let Some(source) = &source {
+            consider(fuzzy_score(term, source), SOURCE_WEIGHT);
+        }
+
+        total += best?;
+    }
+
+    Some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(name: &str) -> SoundbiteData {
+        SoundbiteData::from_samples(name.to_string(), Vec::new(), 1, 44100, 1f32, 1f32)
+    }
+
+    #[test]
+    fn exact_beats_prefix_beats_subsequence() {
+        let exact = fuzzy_score("drum", "drum").unwrap();
+        let prefix = fuzzy_score("drum", "drumroll").unwrap();
+        let inner = fuzzy_score("drum", "snare drum").unwrap();
+        let subsequence = fuzzy_score("drum", "dark rumble").unwrap();
+        assert!(exact > prefix);
+        assert!(prefix > inner);
+        assert!(inner > subsequence);
+    }
+
+    #[test]
+    fn ignores_case() {
+        assert_eq!(fuzzy_score("DRUM", "drum"), fuzzy_score("drum", "Drum"));
+        assert!(fuzzy_score("ÉCHO", "écho").is_some());
+    }
+
+    #[test]
+    fn no_match() {
+        assert_eq!(fuzzy_score("xyz", "drum"), None);
+        assert_eq!(fuzzy_score("murd", "drum"), None);
+        assert_eq!(score_soundbite("xyz", &data("drum")), None);
+    }
+
+    #[test]
+    fn empty_query_matches_everything() {
+        assert_eq!(fuzzy_score("", "drum"), Some(0));
+        assert_eq!(score_soundbite("  ", &data("drum")), Some(0));
+    }
+
+    #[test]
+    fn matches_metadata() {
+        let mut tagged = data("applause");
+        tagged.tags.push("crowd".to_string());
+        tagged.category = Some("Reactions".to_string());
+        tagged.source = Some("/sounds/clap_take2.wav".into());
+
+        assert!(score_soundbite("crowd", &tagged).is_some());
+        assert!(score_soundbite("reactions", &tagged).is_some());
+        assert!(score_soundbite("take2", &tagged).is_some());
+        //Every term has to match some field
+        assert!(score_soundbite("applause crowd", &tagged).is_some());
+        assert!(score_soundbite("applause drum", &tagged).is_none());
+    }
+
+    #[test]
+    fn name_weighs_more_than_metadata() {
+        let named = data("crowd");
+        let mut tagged = data("applause");
+        tagged.tags.push("crowd".to_string());
+
+        assert!(score_soundbite("crowd", &named) > score_soundbite("crowd", &tagged));
+    }
+}
@@ -0,0 +1,106 @@
+use std::path::PathBuf;
+
+use serde::{
+    Serialize,
+    Deserialize
+};
+
+use super::{
+    focus::ActivationRules,
+    key_task::{KeyTask, KeyTaskCode, KeyCode, SysKeyCode},
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ImportLimits {
+    //Bytes
+    pub max_file_size: u64,
+    //Seconds
+    pub max_duration: f32,
+}
+
+impl Default for ImportLimits {
+    fn default() -> Self {
+        ImportLimits {
+            max_file_size: 100 * 1024 * 1024,
+            max_duration: 300f32,
+        }
+    }
+}
+
+//Chord suspending every swallowing binding for a while, in case a
+// swallowed chord is needed by another application
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct SwallowEscape {
+    pub keycode: KeyTaskCode,
+    //Seconds
+    pub duration: u64,
+}
+
+impl Default for SwallowEscape {
+    fn default() -> Self {
+        SwallowEscape {
+            keycode: KeyTask::new(KeyCode::SCROLL_LOCK, Some(SysKeyCode::LSHIFT)).get_code(),
+            duration: 30,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ApiSettings {
+    pub enabled: bool,
+    pub port: u16,
+    //Listens on every interface instead of localhost only
+    pub allow_lan: bool,
+    //Generated when the API is first enabled
+    pub token: String,
+}
+
+impl Default for ApiSettings {
+    fn default() -> Self {
+        ApiSettings {
+            enabled: false,
+            port: 7313,
+            allow_lan: false,
+            token: String::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct OscSettings {
+    pub enabled: bool,
+    //UDP port messages are received on
+    pub port: u16,
+    pub allow_lan: bool,
+    //host:port addresses notified when soundbites start and stop
+    pub targets: Vec<String>,
+}
+
+impl Default for OscSettings {
+    fn default() -> Self {
+        OscSettings {
+            enabled: false,
+            port: 9000,
+            allow_lan: false,
+            targets: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Settings {
+    pub import_limits: ImportLimits,
+    pub watch_folder: Option<PathBuf>,
+    pub active_profile: Option<String>,
+    pub activation_rules: ActivationRules,
+    pub swallow_escape: SwallowEscape,
+    //Chord toggling every other hotkey on and off
+    pub pause_keycode: Option<KeyTaskCode>,
+    pub api: ApiSettings,
+    pub osc: OscSettings,
+}
@@ -1,4 +1,7 @@
-use std::io::Cursor;
+use std::{
+    io::Cursor,
+    path::PathBuf,
+};
 
 use serde::{
     Serialize,
@@ -23,7 +26,7 @@ use super::utils::{
 //TODO: define struct SoundbiteData for serialization purposes
 // to use inside the existing Soundbite struct
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SoundbiteData {
     pub name: String,
     buffer: Vec<i16>,
@@ -31,6 +34,15 @@ pub struct SoundbiteData {
     sample_rate: u32,
     pub volume: f32,
     pub speed: f32,
+    //File the soundbite was imported from, if any
+    #[serde(default)]
+    pub source: Option<PathBuf>,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub choke_group: Option<String>,
 }
 
 impl SoundbiteData {
@@ -43,14 +55,73 @@ impl SoundbiteData {
         let source = Decoder::new_mp3(Cursor::new(buffer))?;
         let channels = source.channels();
         let sample_rate = source.sample_rate();
-        Ok(SoundbiteData {
+        Ok(SoundbiteData::from_samples(
             name,
-            buffer: source.collect(),
+            source.collect(),
             channels,
             sample_rate,
             volume,
             speed,
-        })
+        ))
+    }
+
+    pub fn from_samples(
+        name: String,
+        buffer: Vec<i16>,
+        channels: u16,
+        sample_rate: u32,
+        volume: f32,
+        speed: f32,
+    ) -> SoundbiteData {
+        SoundbiteData {
+            name,
+            buffer,
+            channels,
+            sample_rate,
+            volume,
+            speed,
+            source: None,
+            category: None,
+            tags: Vec::new(),
+            choke_group: None,
+        }
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    pub fn samples(&self) -> &[i16] {
+        &self.buffer
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    //Seconds, at normal speed
+    pub fn duration(&self) -> f32 {
+        if self.channels == 0 || self.sample_rate == 0 {
+            return 0f32;
+        }
+
+        self.buffer.len() as f32 / self.channels as f32 / self.sample_rate as f32
+    }
+
+    pub fn set_samples(
+        &mut self,
+        buffer: Vec<i16>,
+        channels: u16,
+        sample_rate: u32
+    ) -> &mut Self {
+        self.buffer = buffer;
+        self.channels = channels;
+        self.sample_rate = sample_rate;
+        self
     }
 }
 
@@ -105,9 +176,9 @@ impl Soundbite {
         self
     }
 
-    pub fn play(&self) {
+    pub fn play(&self, master_volume: f32) {
         self.sink.stop();
-        self.sink.set_volume(self.data.volume);
+        self.sink.set_volume(self.data.volume * master_volume);
         self.sink.set_speed(self.data.speed);
         self.sink.append(
             SamplesBuffer::new(
@@ -121,4 +192,22 @@ impl Soundbite {
     pub fn stop(&self) {
         self.sink.stop();
     }
+
+    //Volume while fading, gain is a factor of the soundbite own volume
+    pub fn set_gain(&self, master_volume: f32, gain: f32) {
+        self.sink.set_volume(self.data.volume * master_volume * gain);
+    }
+
+    pub fn gain(&self, master_volume: f32) -> f32 {
+        let volume = self.data.volume * master_volume;
+        if volume > 0f32 {
+            self.sink.volume() / volume
+        } else {
+            1f32
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        !self.sink.empty()
+    }
 }
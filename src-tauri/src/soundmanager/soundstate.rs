@@ -1,5 +1,6 @@
 use std::{
     sync::{Arc, Mutex},
+    path::PathBuf,
 };
 
 use crossbeam::channel::{Sender, Receiver};
@@ -7,34 +8,102 @@ use crossbeam::channel::{Sender, Receiver};
 use super::{
     Soundbites,
     SoundbitesKeyTasks,
+    Categories,
+    group::ChokeGroups,
+    variant::VariantSets,
+    macros::Macros,
+    profile::Profiles,
+    queue::{Queue, Playlists},
+    schedule::Scheduler,
     SoundManagerError,
     SoundbiteData,
+    events::EventBus,
+    settings::Settings,
+    trigger::TriggerBindings,
+    key_task::DroppedBinding,
 };
 
 pub enum Message {
     NewSoundbite(SoundbiteData),
+    ImportSoundbite {
+        name: String,
+        path: PathBuf,
+    },
+    ImportFolder(PathBuf),
+    WatchFolder(Option<PathBuf>),
+    ImportPack(PathBuf),
+    SwitchProfile(String),
+    PlaySoundbite(String),
+    //Every playing soundbite when None
+    StopSoundbite(Option<String>),
+    PlayVariantSet(String),
+    RunMacro(String),
+    StopMacro(String),
+    Enqueue(String),
+    QueueNext,
+    QueuePrevious,
+    ClearQueue,
+    //Replaces the queue with the playlist and starts it
+    PlayPlaylist(String),
+    //Active profile output device changed
+    RefreshOutputDevice,
+    //Renamed by the manager so the soundbites playing keep their state,
+    // the result is sent back on done
+    RenameSoundbite {
+        name: String,
+        new_name: String,
+        done: Sender<Result<(), SoundManagerError>>,
+    },
 }
 
+#[derive(Clone)]
 pub struct SoundState {
     pub soundbites: Arc<Mutex<Soundbites>>,
     pub soundbites_keytasks: Arc<Mutex<SoundbitesKeyTasks>>,
+    pub trigger_bindings: Arc<Mutex<TriggerBindings>>,
+    pub categories: Arc<Mutex<Categories>>,
+    pub choke_groups: Arc<Mutex<ChokeGroups>>,
+    pub variant_sets: Arc<Mutex<VariantSets>>,
+    pub macros: Arc<Mutex<Macros>>,
+    pub profiles: Arc<Mutex<Profiles>>,
+    pub settings: Arc<Mutex<Settings>>,
+    pub queue: Arc<Mutex<Queue>>,
+    pub playlists: Arc<Mutex<Playlists>>,
+    pub scheduler: Arc<Mutex<Scheduler>>,
+    pub events: EventBus,
+    //Found at load, never changes afterwards
+    pub dropped_bindings: Arc<Vec<DroppedBinding>>,
 
     pub messages: Sender<Message>,
     pub responses: Receiver<Result<String, SoundManagerError>>,
 }
 
+#[cfg(test)]
 impl SoundState {
-    pub fn new(
-        soundbites: Arc<Mutex<Soundbites>>,
-        soundbites_keytasks: Arc<Mutex<SoundbitesKeyTasks>>,
-        messages: Sender<Message>,
-        responses: Receiver<Result<String, SoundManagerError>>,
-    ) -> SoundState {
-        SoundState {
-            soundbites,
-            soundbites_keytasks,
+    //Library with the given soundbites and everything else empty, along
+    // with the messages meant for the manager
+    pub fn for_tests(soundbites: Soundbites) -> (SoundState, Receiver<Message>) {
+        let (messages, messages_rx) = crossbeam::channel::unbounded();
+        let (_, responses) = crossbeam::channel::unbounded();
+        let state = SoundState {
+            soundbites: Arc::new(Mutex::new(soundbites)),
+            soundbites_keytasks: Arc::default(),
+            trigger_bindings: Arc::default(),
+            categories: Arc::default(),
+            choke_groups: Arc::default(),
+            variant_sets: Arc::default(),
+            macros: Arc::default(),
+            profiles: Arc::new(Mutex::new(Profiles::new(Vec::new(), None))),
+            settings: Arc::default(),
+            queue: Arc::default(),
+            playlists: Arc::default(),
+            scheduler: Arc::new(Mutex::new(Scheduler::new(Vec::new()))),
+            events: EventBus::new(),
+            dropped_bindings: Arc::default(),
             messages,
             responses,
-        }
+        };
+
+        (state, messages_rx)
     }
 }
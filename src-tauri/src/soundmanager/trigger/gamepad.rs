@@ -0,0 +1,62 @@
+use std::{
+    thread,
+    time::Duration,
+};
+
+use anyhow::Result;
+use gilrs::{Button, EventType, Gilrs};
+use log::{trace, error};
+
+use super::{dispatch, TriggerCode, TriggerEvent, TriggerSource};
+
+pub struct GamepadSource;
+
+impl TriggerSource for GamepadSource {
+    fn name(&self) -> &'static str {
+        "gamepad"
+    }
+
+    fn start(&self) -> Result<()> {
+        //Gilrs isn't Send, it has to be created on the thread polling it
+        thread::spawn(|| {
+            let mut gilrs = match Gilrs::new() {
+                Ok(gilrs) => gilrs,
+                Err(err) => {
+                    error!("Unable to init gamepad support [[{:?}]]", err);
+                    return;
+                }
+            };
+
+            loop {
+                let event = match gilrs.next_event_blocking(Some(Duration::from_millis(500))) {
+                    Some(event) => event,
+                    None => continue,
+                };
+
+                let event = match event.event {
+                    EventType::ButtonPressed(button, _) if button != Button::Unknown => {
+                        TriggerEvent::Pressed(button_trigger(button))
+                    },
+                    EventType::ButtonReleased(button, _) if button != Button::Unknown => {
+                        TriggerEvent::Released(button_trigger(button))
+                    },
+                    EventType::Connected => {
+                        trace!("Gamepad {} connected", gilrs.gamepad(event.id).name());
+                        continue;
+                    },
+                    _ => continue,
+                };
+
+                dispatch(event);
+            }
+        });
+
+        Ok(())
+    }
+}
+
+fn button_trigger(button: Button) -> TriggerCode {
+    TriggerCode::GamepadButton {
+        button: format!("{:?}", button),
+    }
+}
@@ -0,0 +1,137 @@
+use std::{
+    collections::{HashMap, HashSet},
+    thread,
+    time::Duration,
+};
+
+use anyhow::Result;
+use log::{trace, error};
+use midir::{MidiInput, MidiInputConnection};
+
+use super::{dispatch, TriggerCode, TriggerEvent, TriggerSource};
+
+const CLIENT_NAME: &str = "Soundbar";
+//Ports are looked up again so devices can be plugged in at any time
+const RESCAN_INTERVAL: Duration = Duration::from_secs(2);
+
+//Controllers currently at or above 64, per connection
+type ControlState = HashSet<(u8, u8)>;
+
+pub struct MidiSource;
+
+impl TriggerSource for MidiSource {
+    fn name(&self) -> &'static str {
+        "midi"
+    }
+
+    fn start(&self) -> Result<()> {
+        //Fails early when there's no MIDI backend at all
+        MidiInput::new(CLIENT_NAME)?;
+
+        thread::spawn(|| {
+            #[cfg(unix)]
+            let _virtual_port = create_virtual_port();
+
+            let mut connections: HashMap<String, MidiInputConnection<ControlState>> = HashMap::new();
+            loop {
+                if let Err(err) = connect_new_ports(&mut connections) {
+                    error!("Unable to scan MIDI ports [[{:?}]]", err);
+                }
+
+                thread::sleep(RESCAN_INTERVAL);
+            }
+        });
+
+        Ok(())
+    }
+}
+
+//Lets other applications, e.g. a DAW, send to a port of ours
+#[cfg(unix)]
+fn create_virtual_port() -> Option<MidiInputConnection<ControlState>> {
+    use midir::os::unix::VirtualInput;
+
+    let input = match MidiInput::new(CLIENT_NAME) {
+        Ok(input) => input,
+        Err(err) => {
+            error!("Unable to create MIDI client [[{:?}]]", err);
+            return None;
+        }
+    };
+
+    match input.create_virtual(CLIENT_NAME, on_message, ControlState::new()) {
+        Ok(connection) => Some(connection),
+        Err(err) => {
+            error!("Unable to create virtual MIDI port [[{:?}]]", err);
+            None
+        }
+    }
+}
+
+fn connect_new_ports(
+    connections: &mut HashMap<String, MidiInputConnection<ControlState>>
+) -> Result<()> {
+    let input = MidiInput::new(CLIENT_NAME)?;
+    let names: Vec<String> = input.ports().iter()
+        .filter_map(|port| input.port_name(port).ok())
+        .filter(|name| !name.starts_with(CLIENT_NAME))
+        .collect();
+
+    connections.retain(|name, _| names.contains(name));
+
+    for name in names {
+        if connections.contains_key(&name) {
+            continue;
+        }
+
+        //connect consumes the client, so each port gets its own
+        let input = MidiInput::new(CLIENT_NAME)?;
+        let port = match input.ports().into_iter()
+            .find(|port| input.port_name(port).ok().as_deref() == Some(name.as_str())) {
+            Some(port) => port,
+            None => continue,
+        };
+
+        match input.connect(&port, CLIENT_NAME, on_message, ControlState::new()) {
+            Ok(connection) => {
+                trace!("Connected to MIDI port {}", name);
+                connections.insert(name, connection);
+            },
+            Err(err) => error!("Unable to connect to MIDI port {} [[{:?}]]", name, err),
+        }
+    }
+
+    Ok(())
+}
+
+fn on_message(_timestamp: u64, message: &[u8], controls: &mut ControlState) {
+    if let Some(event) = parse_message(message, controls) {
+        dispatch(event);
+    }
+}
+
+fn parse_message(message: &[u8], controls: &mut ControlState) -> Option<TriggerEvent> {
+    let status = *message.first()?;
+    let channel = status & 0x0F;
+    let number = *message.get(1)?;
+    let value = *message.get(2)?;
+
+    match status & 0xF0 {
+        0x90 if value > 0 => Some(TriggerEvent::Pressed(
+            TriggerCode::MidiNote { channel, note: number }
+        )),
+        //Note on with velocity 0 is a note off
+        0x80 | 0x90 => Some(TriggerEvent::Released(
+            TriggerCode::MidiNote { channel, note: number }
+        )),
+        0xB0 => {
+            let trigger = TriggerCode::MidiControl { channel, controller: number };
+            if value >= 64 {
+                controls.insert((channel, number)).then_some(TriggerEvent::Pressed(trigger))
+            } else {
+                controls.remove(&(channel, number)).then_some(TriggerEvent::Released(trigger))
+            }
+        },
+        _ => None,
+    }
+}
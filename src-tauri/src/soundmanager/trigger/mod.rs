@@ -0,0 +1,187 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+
+use crossbeam::channel::{bounded, unbounded, Receiver, Sender};
+use log::{trace, error};
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{
+    Serialize,
+    Deserialize
+};
+
+use super::key_task::KeyBinding;
+
+#[cfg(feature = "gamepad")]
+pub mod gamepad;
+#[cfg(feature = "midi")]
+pub mod midi;
+
+//Input other than the keyboard that can be bound to a soundbite
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(tag = "source", rename_all = "kebab-case")]
+pub enum TriggerCode {
+    //Channel is 0 based, from any connected port
+    MidiNote {
+        channel: u8,
+        note: u8,
+    },
+    //Pressed when the value goes to 64 or above, released below it
+    MidiControl {
+        channel: u8,
+        controller: u8,
+    },
+    //Button name as reported by gilrs, from any connected gamepad
+    GamepadButton {
+        button: String,
+    },
+}
+
+impl TriggerCode {
+    pub fn label(&self) -> String {
+        match self {
+            TriggerCode::MidiNote { channel, note } => {
+                format!("MIDI Ch {} Note {}", channel + 1, note)
+            },
+            TriggerCode::MidiControl { channel, controller } => {
+                format!("MIDI Ch {} CC {}", channel + 1, controller)
+            },
+            TriggerCode::GamepadButton { button } => format!("Gamepad {}", button),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TriggerEvent {
+    Pressed(TriggerCode),
+    Released(TriggerCode),
+}
+
+//Bindings are shared by every profile, unlike key tasks
+pub type TriggerBindings = HashMap<TriggerCode, KeyBinding>;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TriggerInfo {
+    pub trigger: TriggerCode,
+    pub display: String,
+    //Name of the soundbite, variant set or macro
+    pub soundbite: String,
+    pub binding: KeyBinding,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LearnedTrigger {
+    pub trigger: TriggerCode,
+    pub display: String,
+    //Soundbite, variant set or macro the trigger is already bound to
+    pub bound_to: Option<String>,
+}
+
+pub trait TriggerSource {
+    fn name(&self) -> &'static str;
+    //Spawns whatever the source needs and reports through dispatch
+    fn start(&self) -> anyhow::Result<()>;
+}
+
+pub static TRIGGER_EVENTS: OnceCell<Sender<TriggerEvent>> = OnceCell::new();
+
+#[derive(Default)]
+struct Learn {
+    sender: Option<Sender<TriggerCode>>,
+    //Of the learn in progress, handed back by start_learn
+    id: u64,
+    //Learned triggers, whose release isn't dispatched either
+    held: HashSet<TriggerCode>,
+}
+
+static LEARN: Lazy<Mutex<Learn>> = Lazy::new(|| Mutex::new(Learn::default()));
+
+//Replaces any learn in progress, whose receiver gets disconnected
+pub fn start_learn() -> (u64, Receiver<TriggerCode>) {
+    let (tx, rx) = bounded(1);
+    let mut learn = LEARN.lock().unwrap();
+    learn.id += 1;
+    learn.sender = Some(tx);
+    (learn.id, rx)
+}
+
+pub fn stop_learn() {
+    LEARN.lock().unwrap().sender = None;
+}
+
+//Stops the learn only if no newer one replaced it
+pub fn end_learn(id: u64) {
+    let mut learn = LEARN.lock().unwrap();
+    if learn.id == id {
+        learn.sender = None;
+    }
+}
+
+//Called by every source, hands the event to learn mode or to the manager
+pub fn dispatch(event: TriggerEvent) {
+    {
+        let mut learn = LEARN.lock().unwrap();
+        match &event {
+            TriggerEvent::Pressed(trigger) => {
+                if let Some(sender) = learn.sender.take() {
+                    let _ = sender.send(trigger.clone());
+                    learn.held.insert(trigger.clone());
+                    return;
+                }
+            },
+            TriggerEvent::Released(trigger) => {
+                if learn.held.remove(trigger) {
+                    return;
+                }
+            },
+        }
+    }
+
+    if let Some(sender) = TRIGGER_EVENTS.get() {
+        let _ = sender.send(event);
+    }
+}
+
+fn sources() -> Vec<Box<dyn TriggerSource>> {
+    vec![
+        #[cfg(feature = "midi")]
+        Box::new(midi::MidiSource),
+        #[cfg(feature = "gamepad")]
+        Box::new(gamepad::GamepadSource),
+    ]
+}
+
+pub fn start_sources() -> Receiver<TriggerEvent> {
+    let (tx, rx) = unbounded();
+    if TRIGGER_EVENTS.set(tx).is_err() {
+        error!("Trigger sources already started");
+        return rx;
+    }
+
+    for source in sources() {
+        match source.start() {
+            Ok(_) => trace!("Trigger source {} started", source.name()),
+            Err(err) => error!("Unable to start trigger source {} [[{:?}]]", source.name(), err),
+        }
+    }
+
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ending_a_replaced_learn_keeps_the_newer_one() {
+        let (old, old_learned) = start_learn();
+        let (_, learned) = start_learn();
+        assert!(old_learned.recv().is_err());
+
+        end_learn(old);
+        assert!(LEARN.lock().unwrap().sender.is_some());
+        stop_learn();
+        assert!(learned.recv().is_err());
+    }
+}
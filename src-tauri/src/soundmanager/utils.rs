@@ -1,14 +1,55 @@
-use super::{KeyTaskCode, SoundbiteData};
+use super::{
+    KeyTaskCode,
+    Soundbites,
+    SoundbitesKeyTasks,
+    key_task::TriggerMode,
+};
 
 use thiserror::Error;
-use serde::Serialize;
+use serde::{
+    Serialize,
+    Deserialize
+};
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SoundbiteInfo {
     pub name: String,
     pub volume: f32,
     pub speed: f32,
     pub keycode: KeyTaskCode,
+    pub swallow: bool,
+    pub trigger: TriggerMode,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+    pub choke_group: Option<String>,
+}
+
+impl SoundbiteInfo {
+    pub fn new(
+        soundbites: &Soundbites,
+        soundbites_keytasks: &SoundbitesKeyTasks,
+        index: usize,
+    ) -> SoundbiteInfo {
+        let (keycode, swallow, trigger) = match soundbites_keytasks.iter().find(|(_, binding)| {
+            binding.is_soundbite(index)
+        }) {
+            Some(item) => (*item.0, item.1.swallow, item.1.trigger),
+            None => (0, false, TriggerMode::default())
+        };
+
+        let soundbite = &soundbites[index];
+        SoundbiteInfo {
+            name: soundbite.data.name.clone(),
+            volume: soundbite.data.volume,
+            speed: soundbite.data.speed,
+            keycode,
+            swallow,
+            trigger,
+            category: soundbite.data.category.clone(),
+            tags: soundbite.data.tags.clone(),
+            choke_group: soundbite.data.choke_group.clone(),
+        }
+    }
 }
 
 #[derive(Error, Debug, Clone, Serialize)]
@@ -21,6 +62,16 @@ pub enum NewSoundbiteError {
     UnableToSendSoundbite(String),
     #[error("Unable to create soundbite {0} from data")]
     UnableToCreateFromData(String),
+    #[error("File {0} not found")]
+    FileNotFound(String),
+    #[error("Unable to read file {0}")]
+    UnableToReadFile(String),
+    #[error("File {0} is not a supported audio file")]
+    UnsupportedFormat(String),
+    #[error("File {0} is larger than {1} bytes")]
+    FileTooLarge(String, u64),
+    #[error("File {0} is longer than {1} seconds")]
+    DurationTooLong(String, f32),
 }
 
 #[derive(Error, Debug, Clone, Serialize)]
@@ -31,12 +82,110 @@ pub enum SoundManagerError {
     SoundbiteNotFound(String),
     #[error("Key code {0} already used")]
     KeyTaskUsed(KeyTaskCode),
+    #[error("Soundbite named {0} has no key code")]
+    KeyTaskNotSet(String),
+    #[error("{0} has no key code")]
+    BindingNotSet(String),
+    #[error("No key pressed before timeout")]
+    CaptureTimedOut,
+    #[error("Key capture cancelled")]
+    CaptureCancelled,
+    #[error("Trigger {0} already used")]
+    TriggerUsed(String),
+    #[error("Trigger {0} not bound")]
+    TriggerNotFound(String),
+    #[error("No trigger received before timeout")]
+    LearnTimedOut,
+    #[error("Trigger learn cancelled")]
+    LearnCancelled,
     #[error("Soundbite named {0} already exists")]
     SoundbiteAlreadyExists(String),
     #[error("Invalid volume value")]
     InvalidVolumeValue,
     #[error("Invalid speed value")]
     InvalidSpeedValue,
+    #[error("Invalid import limits")]
+    InvalidImportLimits,
+    #[error("Invalid activation rule, a process or a title is required")]
+    InvalidActivationRule,
+    #[error("Unable to send message to backend")]
+    UnableToSendMessage,
+    #[error("Unable to export to {0}")]
+    UnableToExport(String),
+    #[error("Invalid soundbite pack {0}")]
+    InvalidPack(String),
+    #[error("Unsupported soundbite pack version {0}")]
+    UnsupportedPackVersion(u32),
+    #[error("Category named {0} not found")]
+    CategoryNotFound(String),
+    #[error("Category named {0} already exists")]
+    CategoryAlreadyExists(String),
+    #[error("Invalid category name")]
+    InvalidCategoryName,
+    #[error("Invalid tag")]
+    InvalidTag,
+    #[error("Profile named {0} not found")]
+    ProfileNotFound(String),
+    #[error("Profile named {0} already exists")]
+    ProfileAlreadyExists(String),
+    #[error("Profile named {0} is active")]
+    ProfileActive(String),
+    #[error("Invalid profile name")]
+    InvalidProfileName,
+    #[error("Output device {0} not found")]
+    OutputDeviceNotFound(String),
+    #[error("Choke group named {0} not found")]
+    ChokeGroupNotFound(String),
+    #[error("Choke group named {0} already exists")]
+    ChokeGroupAlreadyExists(String),
+    #[error("Invalid choke group name")]
+    InvalidChokeGroupName,
+    #[error("Invalid crossfade, at most 60 seconds")]
+    InvalidCrossfade,
+    #[error("Variant set named {0} not found")]
+    VariantSetNotFound(String),
+    #[error("Variant set named {0} already exists")]
+    VariantSetAlreadyExists(String),
+    #[error("Invalid variant set name")]
+    InvalidVariantSetName,
+    #[error("Macro named {0} not found")]
+    MacroNotFound(String),
+    #[error("Macro named {0} already exists")]
+    MacroAlreadyExists(String),
+    #[error("Invalid macro name")]
+    InvalidMacroName,
+    #[error("Invalid macro step {0}, volumes go from 0 to 1 and durations up to an hour")]
+    InvalidMacroStep(usize),
+    #[error("Schedule named {0} not found")]
+    ScheduleNotFound(String),
+    #[error("Schedule named {0} already exists")]
+    ScheduleAlreadyExists(String),
+    #[error("Invalid schedule name")]
+    InvalidScheduleName,
+    #[error("Invalid schedule, {0}")]
+    InvalidSchedule(String),
+    #[error("Playlist named {0} not found")]
+    PlaylistNotFound(String),
+    #[error("Invalid playlist name")]
+    InvalidPlaylistName,
+    #[error("Invalid transition, at most 60 seconds")]
+    InvalidTransition,
+    #[error("Unable to start the API server, {0}")]
+    ApiUnavailable(String),
+    #[error("Unable to start the OSC server, {0}")]
+    OscUnavailable(String),
+    #[error("Invalid OSC target {0}, expected host:port")]
+    InvalidOscTarget(String),
     #[error("Unable to close app")]
     CloseAppError,
 }
+
+#[derive(Error, Debug)]
+pub enum IpcError {
+    #[error("soundbar is not running")]
+    NotRunning,
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("Invalid message from soundbar, {0}")]
+    InvalidMessage(#[from] serde_json::Error),
+}
@@ -0,0 +1,109 @@
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    Rng,
+};
+use serde::{
+    Serialize,
+    Deserialize
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum VariantMode {
+    //Never the same variant twice in a row
+    #[default]
+    Random,
+    RoundRobin,
+    //Chance proportional to the variant weight
+    Weighted,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Variant {
+    pub soundbite: String,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+//One binding for several soundbites, one of them picked each time it fires.
+// Soundbites are referenced by name, removed ones are skipped when picked
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VariantSet {
+    pub name: String,
+    pub variants: Vec<Variant>,
+    #[serde(default)]
+    pub mode: VariantMode,
+    //Index of the variant picked last
+    #[serde(skip)]
+    last: Option<usize>,
+}
+
+pub type VariantSets = Vec<VariantSet>;
+
+impl VariantSet {
+    pub fn new(name: String, variants: Vec<Variant>, mode: VariantMode) -> VariantSet {
+        VariantSet {
+            name,
+            variants,
+            mode,
+            last: None,
+        }
+    }
+
+    //Soundbite to play next, among the ones which still exist
+    pub fn pick(&mut self, exists: impl Fn(&str) -> bool) -> Option<String> {
+        let available: Vec<usize> = (0..self.variants.len())
+            .filter(|&index| exists(&self.variants[index].soundbite))
+            .collect();
+        let count = available.len();
+        if count == 0 {
+            return None;
+        }
+
+        let mut rng = rand::thread_rng();
+        //Position of the variant picked last among the available ones
+        let last = self.last.and_then(|last| available.iter().position(|&index| index == last));
+        let picked = match self.mode {
+            //Carries on after the last one even when it was removed since
+            VariantMode::RoundRobin => match self.last {
+                Some(last) => available.iter().position(|&index| index > last).unwrap_or(0),
+                None => 0,
+            },
+            VariantMode::Random => match last {
+                Some(last) if count > 1 => {
+                    let picked = rng.gen_range(0..count - 1);
+                    if picked >= last { picked + 1 } else { picked }
+                },
+                _ => rng.gen_range(0..count),
+            },
+            //Every weight at 0 falls back to random
+            VariantMode::Weighted => match WeightedIndex::new(
+                available.iter().map(|&index| self.variants[index].weight)
+            ) {
+                Ok(weights) => weights.sample(&mut rng),
+                Err(_) => rng.gen_range(0..count),
+            },
+        };
+
+        let index = available[picked];
+        self.last = Some(index);
+        Some(self.variants[index].soundbite.clone())
+    }
+
+    //Soundbite picked last, stopped when a held binding is released
+    pub fn last_picked(&self) -> Option<&String> {
+        self.last
+            .and_then(|last| self.variants.get(last))
+            .map(|variant| &variant.soundbite)
+    }
+
+    pub fn rename_soundbite(&mut self, name: &str, new_name: &str) {
+        self.variants.iter_mut()
+            .filter(|variant| variant.soundbite == name)
+            .for_each(|variant| variant.soundbite = new_name.to_string());
+    }
+}
@@ -0,0 +1,78 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use crossbeam::channel::{unbounded, Receiver};
+use log::error;
+use notify::{
+    Watcher,
+    RecommendedWatcher,
+    RecursiveMode,
+    EventKind,
+};
+
+//Editors and copies fire several events per file, wait for them to settle
+// before touching the soundbite
+const SETTLE_TIME: Duration = Duration::from_millis(500);
+
+pub struct FolderWatcher {
+    folder: PathBuf,
+    //Dropping the watcher stops the notifications
+    _watcher: RecommendedWatcher,
+    changes: Receiver<notify::Result<notify::Event>>,
+    pending: HashMap<PathBuf, Instant>,
+}
+
+impl FolderWatcher {
+    pub fn new(folder: PathBuf) -> Result<FolderWatcher> {
+        let (tx, changes) = unbounded();
+        let mut watcher = notify::recommended_watcher(
+            move |event| {
+                let _ = tx.send(event);
+            }
+        )?;
+        watcher.watch(&folder, RecursiveMode::NonRecursive)?;
+
+        Ok(FolderWatcher {
+            folder,
+            _watcher: watcher,
+            changes,
+            pending: HashMap::new(),
+        })
+    }
+
+    //Returns the paths whose last change is older than SETTLE_TIME
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        for event in self.changes.try_iter() {
+            match event {
+                Ok(event) => match event.kind {
+                    EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) => {
+                        for path in event.paths {
+                            self.pending.insert(path, Instant::now());
+                        }
+                    },
+                    _ => {},
+                },
+                Err(err) => error!(
+                    "Error while watching folder {} [[{:?}]]",
+                    self.folder.display(),
+                    err
+                ),
+            }
+        }
+
+        let settled: Vec<PathBuf> = self.pending
+            .iter()
+            .filter(|(_, changed)| changed.elapsed() >= SETTLE_TIME)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in settled.iter() {
+            self.pending.remove(path);
+        }
+
+        settled
+    }
+}
@@ -1,5 +1,6 @@
 import '../scss/styles.scss'
 import { invoke } from "@tauri-apps/api/tauri";
+import { listen } from "@tauri-apps/api/event";
 
 type SoundbiteInfo = {
     name: string,
@@ -8,6 +9,16 @@ type SoundbiteInfo = {
     keycode: number,
 }
 
+type PlaybackEvent = {
+    name: string,
+}
+
+type PlaybackPosition = {
+    name: string,
+    position: number,
+    duration: number,
+}
+
 let selectedSoundbite: HTMLElement | null;
 
 let soundbiteInfo: HTMLElement | null;
@@ -19,6 +30,7 @@ let soundbiteVolumeValue: HTMLLabelElement | null;
 let soundbiteSpeedRange: HTMLInputElement | null;
 let soundbiteSpeedValue: HTMLLabelElement | null;
 let soundbiteKeycode: HTMLElement | null;
+let playbackProgress: HTMLElement | null;
 
 let isKeyRecording = false;
 let sysKeyRecorded: string | null;
@@ -41,6 +53,7 @@ function updateSoundbiteInfo(event: MouseEvent) {
         invoke('get_soundbite', { name: selectedSoundbite.textContent })
             .then((response) => {
                 soundbiteInfo?.removeAttribute('hidden');
+                playbackProgress!.style.width = '0%';
 
                 let info = response as SoundbiteInfo;
                 soundbiteName!.textContent = info.name;
@@ -119,7 +132,7 @@ function stopRecording(save: boolean) {
             }
 
             if (sysKeycode != 0) {
-                keycode = sysKeycode << 2;
+                keycode = sysKeycode << 8;
             }
         }
 
@@ -157,6 +170,33 @@ function recordKey(event: KeyboardEvent) {
     }
 }
 
+function findSoundbiteItem(name: string): HTMLElement | undefined {
+    let items = document.querySelectorAll<HTMLElement>('#soundbites-list a');
+    return Array.from(items).find((item) => item.textContent === name);
+}
+
+function setProgress(name: string, progress: number) {
+    if (selectedSoundbite?.textContent === name) {
+        playbackProgress!.style.width = (progress * 100) + '%';
+    }
+}
+
+function onPlaybackStarted(name: string) {
+    findSoundbiteItem(name)?.classList.add('playing');
+    setProgress(name, 0);
+}
+
+function onPlaybackEnded(name: string) {
+    findSoundbiteItem(name)?.classList.remove('playing');
+    setProgress(name, 0);
+}
+
+function onPlaybackPosition(position: PlaybackPosition) {
+    if (position.duration > 0) {
+        setProgress(position.name, position.position / position.duration);
+    }
+}
+
 function playSound(_: MouseEvent) {
     invoke('play_soundbite', { name: selectedSoundbite!.textContent })
 }
@@ -251,6 +291,7 @@ window.addEventListener("DOMContentLoaded", () => {
     soundbiteSpeedRange = document.getElementById('speed-range') as HTMLInputElement;
     soundbiteSpeedValue = document.getElementById('speed-range-value') as HTMLLabelElement;
     soundbiteKeycode = document.getElementById('keycode-value');
+    playbackProgress = document.getElementById('playback-progress');
 
     soundbiteName!.onclick = (_) => {
         soundbiteName!.hidden = true;
@@ -322,6 +363,11 @@ window.addEventListener("DOMContentLoaded", () => {
     document.getElementById('settings')!.onclick = onSettings;
     document.getElementById("soundbites-list")!.onclick = updateSoundbiteInfo;
 
+    listen<PlaybackEvent>('playback-started', (event) => onPlaybackStarted(event.payload.name));
+    listen<PlaybackEvent>('playback-stopped', (event) => onPlaybackEnded(event.payload.name));
+    listen<PlaybackEvent>('playback-finished', (event) => onPlaybackEnded(event.payload.name));
+    listen<PlaybackPosition>('playback-position', (event) => onPlaybackPosition(event.payload));
+
     invoke("get_soundbites")
         .then((response) => {
             let soundbites = response as [string];